@@ -0,0 +1,394 @@
+//! Derive macros for battle-arena.
+//!
+//! See `battle_arena::de` for what they generate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, Generics, Ident,
+    Lifetime, LitStr, Result,
+};
+
+/// Implements `DeserializeIn` for a struct or enum.
+///
+/// Fields and variants can be renamed with `#[arena(rename = "name")]`.
+#[proc_macro_derive(DeserializeIn, attributes(arena))]
+pub fn derive_deserialize_in(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// What every generated visitor needs to know about the type.
+struct Context {
+    name: Ident,
+    /// The arena lifetime, the type's own if it has one
+    lifetime: Lifetime,
+    /// The type's generics, with the arena lifetime and bounds added
+    generics: Generics,
+    ty_generics: TokenStream2,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let mut lifetimes = input.generics.lifetimes();
+    let lifetime = match lifetimes.next() {
+        Some(param) => param.lifetime.clone(),
+        None => parse_quote!('__arena),
+    };
+    if let Some(extra) = lifetimes.next() {
+        return Err(Error::new_spanned(
+            extra,
+            "types deserialized in an arena can only borrow the arena",
+        ));
+    }
+
+    let mut generics = input.generics.clone();
+    if input.generics.lifetimes().next().is_none() {
+        generics.params.insert(0, parse_quote!(#lifetime));
+    }
+    let type_params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::battle_arena::de::DeserializeIn<#lifetime>));
+    }
+
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let context = Context {
+        name: input.ident.clone(),
+        lifetime,
+        ty_generics: quote!(#ty_generics),
+        generics,
+    };
+
+    let name = &context.name;
+    let name_str = name.to_string();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let visitor = format_ident!("__Visitor");
+            let fields = format_ident!("__FIELDS");
+            let definition = fields_visitor(
+                &context,
+                &visitor,
+                &fields,
+                quote!(#name),
+                &data.fields,
+                &name_str,
+            )?;
+            let call = match &data.fields {
+                Fields::Named(_) => {
+                    quote!(deserializer.deserialize_struct(#name_str, #fields, __visitor))
+                }
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    quote!(deserializer.deserialize_newtype_struct(#name_str, __visitor))
+                }
+                Fields::Unnamed(fields) => {
+                    let len = fields.unnamed.len();
+                    quote!(deserializer.deserialize_tuple_struct(#name_str, #len, __visitor))
+                }
+                Fields::Unit => quote!(deserializer.deserialize_unit_struct(#name_str, __visitor)),
+            };
+
+            quote! {
+                #definition
+                let __visitor = #visitor { arena, marker: ::std::marker::PhantomData };
+                #call
+            }
+        }
+        Data::Enum(data) => enum_body(&context, data)?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "unions cannot be deserialized in an arena",
+            ))
+        }
+    };
+
+    let lifetime = &context.lifetime;
+    let ty_generics = &context.ty_generics;
+    let (impl_generics, _, where_clause) = context.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::battle_arena::de::DeserializeIn<#lifetime> for #name #ty_generics
+        #where_clause
+        {
+            fn deserialize_in<'de, __D: ::battle_arena::de::serde::Deserializer<'de>>(
+                deserializer: __D,
+                arena: &#lifetime ::battle_arena::Arena,
+            ) -> ::std::result::Result<Self, __D::Error> {
+                #body
+            }
+        }
+    })
+}
+
+/// The name of a field or variant, as `#[arena(rename = "...")]` sets it.
+fn serialized_name(attrs: &[Attribute], ident: &Ident) -> Result<String> {
+    let mut name = ident.to_string();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("arena")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unknown arena attribute, expected `rename`"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
+
+/// Define a visitor named `visitor` building `constructor` out of `fields`,
+/// along with the `field_names` constant for named fields.
+fn fields_visitor(
+    context: &Context,
+    visitor: &Ident,
+    field_names_const: &Ident,
+    constructor: TokenStream2,
+    fields: &Fields,
+    expecting: &str,
+) -> Result<TokenStream2> {
+    let serde = quote!(::battle_arena::de::serde);
+    let de = quote!(::battle_arena::de);
+
+    let bindings: Vec<Ident> = (0..fields.len())
+        .map(|index| format_ident!("__field{}", index))
+        .collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let indexes: Vec<usize> = (0..fields.len()).collect();
+    let construct = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote!(#constructor { #(#idents: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#constructor(#(#bindings),*)),
+        Fields::Unit => quote!(#constructor),
+    };
+
+    let mut methods = match fields {
+        Fields::Unit => quote! {
+            fn visit_unit<__E: #serde::de::Error>(self) -> ::std::result::Result<Self::Value, __E> {
+                ::std::result::Result::Ok(#construct)
+            }
+        },
+        _ => quote! {
+            fn visit_seq<__A: #serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: __A,
+            ) -> ::std::result::Result<Self::Value, __A::Error> {
+                #(
+                    let #bindings = seq
+                        .next_element_seed(#de::InArena::<#types>::new(self.arena))?
+                        .ok_or_else(|| #serde::de::Error::invalid_length(#indexes, &self))?;
+                )*
+                ::std::result::Result::Ok(#construct)
+            }
+        },
+    };
+
+    let mut names = quote!();
+    match fields {
+        Fields::Named(named) => {
+            let field_names = named
+                .named
+                .iter()
+                .map(|field| serialized_name(&field.attrs, field.ident.as_ref().unwrap()))
+                .collect::<Result<Vec<_>>>()?;
+            names = quote!(const #field_names_const: &[&str] = &[#(#field_names),*];);
+
+            methods.extend(quote! {
+                fn visit_map<__A: #serde::de::MapAccess<'de>>(
+                    self,
+                    mut map: __A,
+                ) -> ::std::result::Result<Self::Value, __A::Error> {
+                    #( let mut #bindings: ::std::option::Option<#types> = None; )*
+                    let names = #de::Identifier { names: #field_names_const, strict: false };
+                    while let Some(key) = map.next_key_seed(names)? {
+                        match key {
+                            #(
+                                Some(#indexes) => {
+                                    if #bindings.is_some() {
+                                        return Err(#serde::de::Error::duplicate_field(#field_names));
+                                    }
+                                    #bindings = Some(map.next_value_seed(
+                                        #de::InArena::<#types>::new(self.arena),
+                                    )?);
+                                }
+                            )*
+                            _ => {
+                                map.next_value::<#serde::de::IgnoredAny>()?;
+                            }
+                        }
+                    }
+                    #(
+                        let #bindings = #bindings
+                            .ok_or_else(|| #serde::de::Error::missing_field(#field_names))?;
+                    )*
+                    ::std::result::Result::Ok(#construct)
+                }
+            });
+        }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let ty = &unnamed.unnamed[0].ty;
+            methods.extend(quote! {
+                fn visit_newtype_struct<__D: #serde::Deserializer<'de>>(
+                    self,
+                    deserializer: __D,
+                ) -> ::std::result::Result<Self::Value, __D::Error> {
+                    let __field0 = #serde::de::DeserializeSeed::deserialize(
+                        #de::InArena::<#ty>::new(self.arena),
+                        deserializer,
+                    )?;
+                    ::std::result::Result::Ok(#construct)
+                }
+            });
+        }
+        _ => {}
+    }
+
+    let visitor_def = visitor_definition(context, visitor, expecting, methods);
+
+    Ok(quote! {
+        #names
+        #visitor_def
+    })
+}
+
+/// A visitor struct for the type, and its `Visitor` impl with `methods`.
+fn visitor_definition(
+    context: &Context,
+    visitor: &Ident,
+    expecting: &str,
+    methods: TokenStream2,
+) -> TokenStream2 {
+    let serde = quote!(::battle_arena::de::serde);
+    let Context {
+        name,
+        lifetime,
+        ty_generics,
+        ..
+    } = context;
+
+    let (impl_generics, visitor_generics, where_clause) = context.generics.split_for_impl();
+    let mut de_generics = context.generics.clone();
+    de_generics.params.insert(0, parse_quote!('de));
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
+    quote! {
+        struct #visitor #impl_generics #where_clause {
+            arena: &#lifetime ::battle_arena::Arena,
+            marker: ::std::marker::PhantomData<fn() -> #name #ty_generics>,
+        }
+
+        impl #de_impl_generics #serde::de::Visitor<'de> for #visitor #visitor_generics #where_clause {
+            type Value = #name #ty_generics;
+
+            fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(#expecting)
+            }
+
+            #methods
+        }
+    }
+}
+
+fn enum_body(context: &Context, data: &syn::DataEnum) -> Result<TokenStream2> {
+    let serde = quote!(::battle_arena::de::serde);
+    let de = quote!(::battle_arena::de);
+    let name = &context.name;
+    let name_str = name.to_string();
+
+    let variant_names = data
+        .variants
+        .iter()
+        .map(|variant| serialized_name(&variant.attrs, &variant.ident))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut definitions = quote!();
+    let mut arms = quote!();
+    for (index, variant) in data.variants.iter().enumerate() {
+        let ident = &variant.ident;
+        let expecting = format!("variant {name_str}::{ident}");
+        let arm = match &variant.fields {
+            Fields::Unit => quote! {
+                #serde::de::VariantAccess::unit_variant(variant)?;
+                ::std::result::Result::Ok(#name::#ident)
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote! {
+                    #serde::de::VariantAccess::newtype_variant_seed(
+                        variant,
+                        #de::InArena::<#ty>::new(self.arena),
+                    )
+                    .map(#name::#ident)
+                }
+            }
+            fields => {
+                // Each variant with fields gets a visitor of its own
+                let visitor = format_ident!("__Variant{}Visitor", index);
+                let field_names = format_ident!("__VARIANT{}_FIELDS", index);
+                definitions.extend(fields_visitor(
+                    context,
+                    &visitor,
+                    &field_names,
+                    quote!(#name::#ident),
+                    fields,
+                    &expecting,
+                )?);
+
+                let visitor = quote!(#visitor {
+                    arena: self.arena,
+                    marker: ::std::marker::PhantomData,
+                });
+                match fields {
+                    Fields::Named(_) => quote! {
+                        #serde::de::VariantAccess::struct_variant(
+                            variant,
+                            #field_names,
+                            #visitor,
+                        )
+                    },
+                    _ => {
+                        let len = fields.len();
+                        quote!(#serde::de::VariantAccess::tuple_variant(variant, #len, #visitor))
+                    }
+                }
+            }
+        };
+
+        arms.extend(quote!(Some(#index) => { #arm }));
+    }
+
+    let methods = quote! {
+        fn visit_enum<__A: #serde::de::EnumAccess<'de>>(
+            self,
+            data: __A,
+        ) -> ::std::result::Result<Self::Value, __A::Error> {
+            let names = #de::Identifier { names: __VARIANTS, strict: true };
+            let (index, variant) = data.variant_seed(names)?;
+            match index {
+                #arms
+                _ => unreachable!("unknown variants are rejected"),
+            }
+        }
+    };
+    let visitor = format_ident!("__Visitor");
+    let visitor_def = visitor_definition(context, &visitor, &format!("enum {name_str}"), methods);
+
+    Ok(quote! {
+        const __VARIANTS: &[&str] = &[#(#variant_names),*];
+        #definitions
+        #visitor_def
+        deserializer.deserialize_enum(
+            #name_str,
+            __VARIANTS,
+            #visitor { arena, marker: ::std::marker::PhantomData },
+        )
+    })
+}
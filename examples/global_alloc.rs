@@ -0,0 +1,29 @@
+//! Pointing the global allocator at [`ArenaGlobal`] in a one-shot program.
+//!
+//! Nothing else changes: every `Box`, `Vec` and `String` bumps through
+//! the arena's chunks, and the memory goes back when the process exits.
+//!
+//! Run with `cargo run --release --example global_alloc`.
+
+use std::{collections::HashMap, time::Instant};
+
+use battle_arena::ArenaGlobal;
+
+#[global_allocator]
+static GLOBAL: ArenaGlobal = ArenaGlobal::new();
+
+fn main() {
+    let started = Instant::now();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for n in 0..200_000_u32 {
+        *counts.entry(format!("word{}", n % 5000)).or_default() += 1;
+    }
+
+    println!(
+        "{} words counted in {:?}, using {} chunks",
+        counts.len(),
+        started.elapsed(),
+        GLOBAL.chunks()
+    );
+}
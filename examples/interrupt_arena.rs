@@ -0,0 +1,43 @@
+//! Sharing a [`GlobalArena`] between thread mode and an interrupt handler.
+//!
+//! On a Cortex-M target (such as `thumbv7em-none-eabihf`), `critical-section`
+//! is implemented by `cortex-m` with its `critical-section-single-core` feature,
+//! and `on_interrupt` would be an `#[interrupt]` handler. Here a thread stands in
+//! for the interrupt, and `critical-section`'s `std` implementation for the core.
+//!
+//! Run with `cargo run --example interrupt_arena --features critical-section`.
+
+use battle_arena::GlobalArena;
+
+/// Memory for samples, with no heap behind it.
+static ARENA: GlobalArena<4096> = GlobalArena::new();
+
+/// A batch of readings, handed from the interrupt to thread mode.
+struct Samples {
+    readings: [u16; 8],
+}
+
+/// Allocating takes bounded time: the buffer never grows.
+fn on_interrupt(tick: u16) -> &'static mut Samples {
+    let readings = std::array::from_fn(|i| tick * 8 + i as u16);
+
+    ARENA
+        .alloc_static(Samples { readings })
+        .expect("sample buffer is full")
+}
+
+fn main() {
+    let interrupt = std::thread::spawn(|| (0..4).map(on_interrupt).collect::<Vec<_>>());
+
+    // Scratch space in thread mode, given back when the closure returns
+    let checksum = ARENA.with(|arena| {
+        let scratch = arena.alloc_slice_fill_with(16, |i| i as u32).unwrap();
+        scratch.iter().sum::<u32>()
+    });
+
+    for samples in interrupt.join().unwrap() {
+        println!("{:?}", samples.readings);
+    }
+    println!("checksum {checksum}");
+    println!("{} bytes left", ARENA.with(|arena| arena.remaining()));
+}
@@ -0,0 +1,134 @@
+use std::{
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+
+use crate::{
+    builder::Config,
+    chunk::{Chunk, ChunkFooter, ChunkList},
+    index_to_chunk_size, size_to_index, Arena, MIN_BLOCK_SIZE,
+};
+
+/// Where the biggest chunk fitting in a region starts, and its size.
+///
+/// The footer goes at the end of the region, and the chunk right below it.
+fn fit_region(region: NonNull<u8>, len: usize) -> Option<(NonNull<u8>, usize)> {
+    let start = region.as_ptr() as usize;
+    let end = start.checked_add(len)?;
+    let footer = end.checked_sub(size_of::<ChunkFooter>())? & !(align_of::<ChunkFooter>() - 1);
+
+    let available = footer.checked_sub(start)?;
+    if available < MIN_BLOCK_SIZE {
+        return None;
+    }
+    let size = 1 << available.ilog2();
+
+    Some((unsafe { region.add(footer - size - start) }, size))
+}
+
+impl Arena {
+    /// Create an arena using only the memory regions it is given, such as
+    /// a budget handed over by a host application.
+    ///
+    /// Each region becomes one chunk, of the biggest power of two that fits
+    /// next to its footer, and the rest of the region is left unused.
+    /// The arena never allocates chunks of its own, allocations that do not fit
+    /// in the regions fail like they would in a [`no_growth_scope`](Arena::no_growth_scope),
+    /// and the regions are never deallocated.
+    ///
+    /// # Safety
+    ///
+    /// Every region must be valid for reads and writes of its length,
+    /// must not overlap another, and must not be used by anything else
+    /// until the arena is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a region cannot hold a chunk of
+    /// [`MIN_BLOCK_SIZE`] bytes along with its footer.
+    pub unsafe fn from_raw_parts(regions: impl IntoIterator<Item = (NonNull<u8>, usize)>) -> Arena {
+        let arena = Arena::with_config(Config {
+            fixed_chunks: true,
+            ..Config::default()
+        });
+        let chunks = &mut *arena.chunks.get();
+
+        for (region, len) in regions {
+            let Some((start, size)) = fit_region(region, len) else {
+                panic!("region of {len} bytes at {region:p} is too small for a chunk");
+            };
+
+            let class = size_to_index(size);
+            while chunks.len() <= class {
+                let size = index_to_chunk_size(chunks.len());
+                chunks.push(ChunkList::empty(size, arena.state_ref()));
+            }
+            chunks[class].adopt(start);
+        }
+
+        arena
+    }
+}
+
+impl ChunkList {
+    /// Install a chunk in memory that is not ours, putting it on the free list.
+    ///
+    /// `start` has to be valid for a chunk of this list's size and its footer.
+    pub(crate) unsafe fn adopt(&self, start: NonNull<u8>) {
+        let chunk = Chunk::adopt(start, self.chunk_size(), self);
+        self.link(chunk);
+        self.free_now(chunk).expect("failed to free chunk");
+    }
+}
+
+#[test]
+fn adopted_regions() {
+    use std::alloc::{alloc, dealloc, Layout};
+
+    let layout = Layout::from_size_align(64 << 10, 4096).unwrap();
+    let memory = unsafe { NonNull::new(alloc(layout)).unwrap() };
+    let region = |offset: usize, len| unsafe { (memory.add(offset), len) };
+
+    let mut arena = unsafe {
+        Arena::from_raw_parts([
+            region(0, 1000),
+            region(4096, 4096),
+            region(16384, 400),
+            region(32768, 20000),
+        ])
+    };
+    let stats = arena.stats();
+    assert_eq!(stats.chunks, 4);
+    assert!(stats.reserved_bytes < layout.size());
+
+    let inside = |ptr: *const u8| {
+        let start = memory.as_ptr() as usize;
+        (start..start + layout.size()).contains(&(ptr as usize))
+    };
+    let small: Vec<_> = (0..2_u8)
+        .map(|i| arena.alloc_slice_fill_copy(200, &i))
+        .collect();
+    let big = arena.alloc_slice_fill_copy(10_000, &9_u8);
+    assert!(small.iter().all(|slice| inside(slice.as_raw().cast())));
+    assert!(inside(big.as_raw().cast()));
+
+    // Nothing grows past the adopted chunks
+    let error = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_copy(3000, &0_u8);
+    }))
+    .unwrap_err();
+    assert!(error
+        .downcast_ref::<String>()
+        .unwrap()
+        .contains("no-growth"));
+    drop((small, big));
+    assert!(!arena.is_poisoned());
+    arena.validate().unwrap();
+
+    // Shrinking keeps chunks that are not ours to free
+    arena.shrink_to_fit();
+    assert_eq!(arena.stats().chunks, 4);
+    drop(arena);
+
+    unsafe { dealloc(memory.as_ptr(), layout) };
+}
@@ -0,0 +1,87 @@
+use crate::Arena;
+
+/// The allocations libraries need from an arena,
+/// so they can accept any arena that implements it.
+///
+/// Values come back as plain references borrowing the arena, and they
+/// are never dropped, like in most bump allocators. The memory is only
+/// given back when the arena itself drops.
+pub trait ArenaAlloc {
+    /// Move a value into the arena.
+    #[allow(clippy::mut_from_ref)]
+    fn alloc_value<T>(&self, value: T) -> &mut T;
+
+    /// Copy a string into the arena.
+    fn alloc_str(&self, source: &str) -> &str;
+
+    /// Copy a slice into the arena.
+    fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> &[T];
+}
+
+/// Every value is leaked, so its chunk is kept until the arena drops.
+/// Destructors only run if [`ArenaBuilder::drop_on_teardown`](crate::ArenaBuilder::drop_on_teardown)
+/// was enabled.
+impl ArenaAlloc for Arena {
+    #[inline]
+    #[track_caller]
+    fn alloc_value<T>(&self, value: T) -> &mut T {
+        self.alloc(value).leak()
+    }
+
+    #[inline]
+    #[track_caller]
+    fn alloc_str(&self, source: &str) -> &str {
+        Arena::alloc_str(self, source).leak()
+    }
+
+    #[inline]
+    #[track_caller]
+    fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> &[T] {
+        Arena::alloc_slice_copy(self, source).leak()
+    }
+}
+
+#[cfg(feature = "bumpalo")]
+impl ArenaAlloc for bumpalo::Bump {
+    #[inline]
+    fn alloc_value<T>(&self, value: T) -> &mut T {
+        self.alloc(value)
+    }
+
+    #[inline]
+    fn alloc_str(&self, source: &str) -> &str {
+        bumpalo::Bump::alloc_str(self, source)
+    }
+
+    #[inline]
+    fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> &[T] {
+        bumpalo::Bump::alloc_slice_copy(self, source)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn parse_words<'a, A: ArenaAlloc>(arena: &'a A, text: &str) -> &'a [&'a str] {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|word| arena.alloc_str(word))
+        .collect();
+
+    arena.alloc_slice_copy(&words)
+}
+
+#[test]
+fn generic_over_arenas() {
+    let arena = Arena::new();
+    let words = parse_words(&arena, "one two  three");
+    assert_eq!(words, ["one", "two", "three"]);
+
+    let count = arena.alloc_value(0_usize);
+    *count += words.len();
+    assert_eq!(*count, 3);
+
+    #[cfg(feature = "bumpalo")]
+    {
+        let bump = bumpalo::Bump::new();
+        assert_eq!(parse_words(&bump, "one two"), ["one", "two"]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::{future::poll_fn, io, mem::MaybeUninit, pin::Pin};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{collections::ArenaCollector, ptr::Boxed, Arena};
+
+/// Size of the first segment read by [`Arena::alloc_from_async_reader_to_end`].
+const FIRST_SEGMENT: usize = 256;
+
+/// Read from `reader` into `buf` once, returning how many bytes were read.
+async fn read_into<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut [MaybeUninit<u8>],
+) -> io::Result<usize> {
+    let mut buf = ReadBuf::uninit(buf);
+    poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf)).await?;
+
+    Ok(buf.filled().len())
+}
+
+impl Arena {
+    /// Read exactly `len` bytes from an async reader, straight into the arena.
+    ///
+    /// The bytes are read into uninitialized memory, nothing is zeroed first.
+    /// If the future is dropped halfway, the partially filled slice is released with it.
+    pub async fn alloc_from_async_reader<R: AsyncRead + Unpin>(
+        &self,
+        reader: &mut R,
+        len: usize,
+    ) -> io::Result<Boxed<'_, [u8]>> {
+        let mut bytes = self.alloc_uninit_slice::<u8>(len);
+
+        let mut filled = 0;
+        while filled < len {
+            match read_into(reader, &mut bytes[filled..]).await? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                read => filled += read,
+            }
+        }
+
+        Ok(unsafe { bytes.assume_init_prefix(len) })
+    }
+
+    /// Read everything from an async reader until it ends, straight into the arena.
+    ///
+    /// The bytes are read in segments that double in size,
+    /// which are moved into a bigger allocation when they fill up.
+    /// If the future is dropped halfway, everything read so far is released with it.
+    pub async fn alloc_from_async_reader_to_end<R: AsyncRead + Unpin>(
+        &self,
+        reader: &mut R,
+    ) -> io::Result<Boxed<'_, [u8]>> {
+        let mut bytes = ArenaCollector::new(self);
+        bytes.reserve(FIRST_SEGMENT);
+
+        loop {
+            if bytes.len() == bytes.capacity() {
+                bytes.reserve(bytes.capacity());
+            }
+
+            match read_into(reader, bytes.spare_capacity_mut()).await? {
+                0 => return Ok(bytes.finish()),
+                read => unsafe { bytes.set_len(bytes.len() + read) },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn exact_async_reads() {
+    use tokio::io::AsyncWriteExt;
+
+    let arena = Arena::new();
+    block_on(async {
+        let (mut writer, mut reader) = tokio::io::duplex(8);
+        let write = async {
+            writer.write_all(b"hello, world").await.unwrap();
+            drop(writer);
+        };
+        let read = async {
+            let hello = arena.alloc_from_async_reader(&mut reader, 5).await.unwrap();
+            assert_eq!(&*hello, b"hello");
+
+            // Short read, the writer is gone
+            let error = arena
+                .alloc_from_async_reader(&mut reader, 100)
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        };
+        tokio::join!(write, read);
+    });
+}
+
+#[test]
+fn async_reads_to_end() {
+    use tokio::io::AsyncWriteExt;
+
+    let arena = Arena::new();
+    let payload: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+    block_on(async {
+        let (mut writer, mut reader) = tokio::io::duplex(64);
+        let write = async {
+            writer.write_all(&payload).await.unwrap();
+            drop(writer);
+        };
+        let read = async {
+            let bytes = arena
+                .alloc_from_async_reader_to_end(&mut reader)
+                .await
+                .unwrap();
+            assert_eq!(&*bytes, &payload[..]);
+        };
+        tokio::join!(write, read);
+    });
+}
+
+#[test]
+fn cancelled_async_read() {
+    use std::{
+        future::Future,
+        task::{Context, Waker},
+    };
+    use tokio::io::AsyncWriteExt;
+
+    let mut arena = Arena::new();
+    let (mut writer, mut reader) = tokio::io::duplex(64);
+
+    {
+        let read = arena.alloc_from_async_reader(&mut reader, 100);
+        let mut read = std::pin::pin!(read);
+        let mut cx = Context::from_waker(Waker::noop());
+        block_on(writer.write_all(b"partial")).unwrap();
+        assert!(read.as_mut().poll(&mut cx).is_pending());
+    }
+
+    // Dropping the future released its chunk reference
+    arena.shrink_to_fit();
+    assert_eq!(arena.stats().chunks, 0);
+}
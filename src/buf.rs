@@ -0,0 +1,147 @@
+use std::mem::MaybeUninit;
+
+use bytes::buf::{BufMut, UninitSlice};
+
+use crate::{ptr::Boxed, Arena};
+
+/// Size of the first segment of an [`ArenaBufMut`].
+const FIRST_SEGMENT: usize = 256;
+
+/// A [`BufMut`] writing straight into arena memory.
+///
+/// Bytes are written into uninitialized segments that double in size.
+/// [`ArenaBufMut::finish`] returns them as one slice, copying them
+/// once only if more than one segment was used.
+pub struct ArenaBufMut<'arena> {
+    arena: &'arena Arena,
+    /// Segments that were filled up
+    full: Vec<Boxed<'arena, [u8]>>,
+    /// Segment being written to
+    current: Option<Boxed<'arena, [MaybeUninit<u8>]>>,
+    /// Bytes written to the current segment
+    filled: usize,
+    /// Bytes written in total
+    len: usize,
+}
+
+impl<'arena> ArenaBufMut<'arena> {
+    /// Create an empty buffer.
+    pub fn new(arena: &'arena Arena) -> Self {
+        Self {
+            arena,
+            full: Vec::new(),
+            current: None,
+            filled: 0,
+            len: 0,
+        }
+    }
+
+    /// How many bytes were written.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if no bytes were written.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Turn the written bytes into one contiguous slice.
+    pub fn finish(mut self) -> Boxed<'arena, [u8]> {
+        let current = self.current.take();
+        let filled = self.filled;
+
+        if self.full.is_empty() {
+            return match current {
+                Some(current) => unsafe { current.assume_init_prefix(filled) },
+                None => self.arena.alloc_slice_copy(&[]),
+            };
+        }
+
+        let mut bytes = self.arena.alloc_uninit_slice::<u8>(self.len);
+        let mut offset = 0;
+        let written = self.full.iter().map(|segment| unsafe {
+            std::slice::from_raw_parts(segment.as_raw().cast::<MaybeUninit<u8>>(), segment.len())
+        });
+        let last = current.as_ref().map(|current| &current[..filled]);
+
+        for segment in written.chain(last) {
+            bytes[offset..offset + segment.len()].copy_from_slice(segment);
+            offset += segment.len();
+        }
+
+        unsafe { bytes.assume_init_prefix(self.len) }
+    }
+}
+
+unsafe impl BufMut for ArenaBufMut<'_> {
+    #[inline]
+    fn remaining_mut(&self) -> usize {
+        usize::MAX - self.len
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        let capacity = self.current.as_ref().map_or(0, |current| current.len());
+        assert!(
+            cnt <= capacity - self.filled,
+            "advanced past the end of the segment"
+        );
+        self.filled += cnt;
+        self.len += cnt;
+
+        // Keep the full segment around until the buffer finishes
+        if self.filled == capacity && capacity != 0 {
+            if let Some(current) = self.current.take() {
+                self.full.push(current.assume_init_prefix(capacity));
+            }
+            self.filled = 0;
+        }
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        if self.current.is_none() {
+            let size = match self.full.last() {
+                Some(last) => last.len().saturating_mul(2),
+                None => FIRST_SEGMENT,
+            };
+            self.current = Some(self.arena.alloc_uninit_slice(size));
+        }
+
+        match &mut self.current {
+            Some(current) => UninitSlice::uninit(&mut current[self.filled..]),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl Arena {
+    /// Create a [`BufMut`] that writes into this arena.
+    pub fn buf_mut(&self) -> ArenaBufMut<'_> {
+        ArenaBufMut::new(self)
+    }
+}
+
+#[test]
+fn buf_mut_segments() {
+    let arena = Arena::new();
+
+    let mut buf = arena.buf_mut();
+    buf.put_u32(0xDEAD_BEEF);
+    buf.put_slice(b"short");
+    let single = buf.finish();
+    assert_eq!(&*single, b"\xDE\xAD\xBE\xEFshort");
+
+    // Spans several segments, then gets copied together
+    let payload: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+    let mut buf = arena.buf_mut();
+    buf.put_u8(1);
+    buf.put_slice(&payload);
+    assert_eq!(buf.len(), 2001);
+    let bytes = buf.finish();
+    assert_eq!(bytes[0], 1);
+    assert_eq!(&bytes[1..], &payload[..]);
+
+    assert!(arena.buf_mut().finish().is_empty());
+}
@@ -0,0 +1,297 @@
+use crate::{
+    index_to_chunk_size, size_to_index,
+    warnings::{WarningConfig, Warnings},
+    Arena, MIN_BLOCK_SIZE,
+};
+
+/// How chunks at or above [`HUGE_PAGE_SIZE`](crate::HUGE_PAGE_SIZE) are backed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HugePagePolicy {
+    /// Always use normal pages.
+    #[default]
+    Off,
+    /// Request transparent huge pages (`madvise(MADV_HUGEPAGE)`).
+    ///
+    /// The kernel is free to ignore the advice, so these chunks are counted as
+    /// [`ArenaStats::advised_chunks`](crate::ArenaStats::advised_chunks), not as huge page chunks.
+    /// Only Linux has them, elsewhere chunks get normal pages.
+    Transparent,
+    /// Map explicit huge pages (`MAP_HUGETLB`, or `MEM_LARGE_PAGES` on Windows),
+    /// falling back to [`HugePagePolicy::Transparent`] when the reservation fails,
+    /// as counted by [`ArenaStats::huge_page_fallbacks`](crate::ArenaStats::huge_page_fallbacks).
+    ///
+    /// Windows needs the "Lock pages in memory" privilege for them, and only maps
+    /// chunks whose size is a multiple of the large page size.
+    /// Other platforms have none, and every chunk falls back.
+    Explicit,
+}
+
+/// Options shared by every chunk list of an arena.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Config {
+    pub(crate) huge_pages: HugePagePolicy,
+    pub(crate) teardown_drops: bool,
+    pub(crate) max_free_chunks: Option<usize>,
+    pub(crate) label: Option<&'static str>,
+    pub(crate) min_align: usize,
+    /// Whether growing inside a no-growth scope panics, defaults to debug builds
+    pub(crate) no_growth_panics: Option<bool>,
+    /// How many epochs epoch allocations live for, at least one
+    pub(crate) keep_epochs: usize,
+    /// Bytes of address space to reserve for chunks up front
+    pub(crate) reserve: Option<usize>,
+    /// Whether the chunks were handed over, and no more can be allocated
+    pub(crate) fixed_chunks: bool,
+    /// Chunk size of each size class, powers of two when unset
+    pub(crate) size_classes: Option<fn(usize) -> usize>,
+    /// How many chunk requests freed chunks are held back for
+    #[cfg(feature = "quarantine")]
+    pub(crate) quarantine: usize,
+    /// Whether quarantined chunks are overwritten
+    #[cfg(feature = "quarantine")]
+    pub(crate) poison_quarantined: bool,
+    /// NUMA node the chunk memory is bound to
+    #[cfg(feature = "numa")]
+    pub(crate) numa_node: Option<u32>,
+}
+
+impl Config {
+    /// Chunk size of the size class at `index`.
+    #[inline]
+    pub(crate) fn class_size(&self, index: usize) -> usize {
+        match self.size_classes {
+            Some(class_size) => class_size(index),
+            None => index_to_chunk_size(index),
+        }
+    }
+
+    /// Index of the smallest size class with chunks of at least `size` bytes.
+    #[inline]
+    pub(crate) fn class_for_size(&self, size: usize) -> usize {
+        let Some(class_size) = self.size_classes else {
+            return size_to_index(size);
+        };
+
+        // Gallop to a class that is big enough, then bisect the last step
+        let (mut low, mut high) = (0, 1);
+        if class_size(0) >= size {
+            return 0;
+        }
+        while class_size(high) < size {
+            low = high;
+            high *= 2;
+        }
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if class_size(middle) < size {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        high
+    }
+
+    /// Index and chunk size of the class holding chunks of `chunk_size` bytes,
+    /// for panic messages.
+    #[inline]
+    pub(crate) fn class_of(&self, chunk_size: usize) -> (usize, usize) {
+        (self.class_for_size(chunk_size), chunk_size)
+    }
+}
+
+/// Configures and creates an [`Arena`].
+#[derive(Debug, Default, Clone)]
+pub struct ArenaBuilder {
+    config: Config,
+    warnings: WarningConfig,
+}
+
+impl ArenaBuilder {
+    /// Create a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the huge page policy for big chunks.
+    pub fn huge_pages(mut self, policy: HugePagePolicy) -> Self {
+        self.config.huge_pages = policy;
+        self
+    }
+
+    /// Reserve `bytes` of contiguous address space up front, and carve
+    /// chunks out of it instead of allocating them one by one.
+    ///
+    /// Pages of a chunk are only committed once the bump pointer reaches them,
+    /// and decommitted again when the chunk is reset or deallocated.
+    /// Every chunk takes whole pages. Once the reservation is used up, or where
+    /// address space cannot be reserved (such as wasm), chunks are allocated as usual.
+    pub fn reserve_address_space(mut self, bytes: usize) -> Self {
+        self.config.reserve = Some(bytes);
+        self
+    }
+
+    /// Bind the memory of every chunk to a NUMA node, so threads running
+    /// on that node avoid remote memory accesses.
+    ///
+    /// Chunks are mapped directly and bound before they are touched. When the node
+    /// does not exist, chunks cannot be allocated. Chunks carved from a reservation
+    /// are not bound, and only Linux can bind memory, elsewhere this does nothing.
+    #[cfg(feature = "numa")]
+    pub fn numa_node(mut self, node: u32) -> Self {
+        self.config.numa_node = Some(node);
+        self
+    }
+
+    /// Hold freed chunks back from reuse for the next `chunk_requests` times
+    /// any class needs a chunk, to catch pointers that outlive their values.
+    ///
+    /// Chunks from [`ArenaBuilder::reserve_address_space`] are made inaccessible
+    /// while they are held, so touching them faults right away.
+    #[cfg(feature = "quarantine")]
+    pub fn quarantine(mut self, chunk_requests: usize) -> Self {
+        self.config.quarantine = chunk_requests;
+        self
+    }
+
+    /// Overwrite the memory of quarantined chunks with [`QUARANTINE_POISON`](crate::QUARANTINE_POISON),
+    /// unless they are made inaccessible.
+    #[cfg(feature = "quarantine")]
+    pub fn poison_quarantined(mut self, enabled: bool) -> Self {
+        self.config.poison_quarantined = enabled;
+        self
+    }
+
+    /// Run the destructors of values that are still alive when the arena drops,
+    /// such as leaked handles, newest first.
+    ///
+    /// This keeps track of the drop glue of every allocation that needs it.
+    pub fn drop_on_teardown(mut self, enabled: bool) -> Self {
+        self.config.teardown_drops = enabled;
+        self
+    }
+
+    /// Keep at most `n` free chunks in each size class.
+    ///
+    /// Chunks released beyond that are deallocated right away,
+    /// instead of being kept for reuse. By default every chunk is kept.
+    pub fn max_free_chunks_per_class(mut self, n: usize) -> Self {
+        self.config.max_free_chunks = Some(n);
+        self
+    }
+
+    /// Align every allocation to at least `align` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn min_align(mut self, align: usize) -> Self {
+        assert!(
+            align.is_power_of_two(),
+            "minimum alignment must be a power of two"
+        );
+        self.config.min_align = align;
+        self
+    }
+
+    /// Choose whether growing the arena inside a
+    /// [`no_growth_scope`](Arena::no_growth_scope) panics right away,
+    /// or fails the allocation like the backing allocator would.
+    ///
+    /// By default it panics in debug builds only.
+    pub fn no_growth_panics(mut self, enabled: bool) -> Self {
+        self.config.no_growth_panics = Some(enabled);
+        self
+    }
+
+    /// Keep values from [`Arena::alloc_epoch`] for `n` epochs,
+    /// so they expire on the `n`th call to [`Arena::advance_epoch`].
+    ///
+    /// By default they only live for the epoch they were allocated in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn keep_epochs(mut self, n: usize) -> Self {
+        assert!(n > 0, "values have to live for at least one epoch");
+        self.config.keep_epochs = n;
+        self
+    }
+
+    /// Use `class_size(index)` as the chunk size of each size class,
+    /// instead of powers of two from [`MIN_BLOCK_SIZE`].
+    ///
+    /// Allocations go to the smallest class that fits them, so finer classes waste
+    /// less of each chunk, like a linear scheme that puts 257 bytes in a 320 byte
+    /// chunk instead of a 512 byte one. The catch is that every class below the one
+    /// needed is created with chunks of its own, so a scheme with many classes
+    /// reserves more memory up front. Growing geometrically past some size keeps that in check.
+    /// Chunks that are not a power of two are aligned to the power of two below their size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the first class is below [`MIN_BLOCK_SIZE`], or the second is not bigger.
+    /// Classes have to keep growing, otherwise allocations that need them fail
+    /// with [`AllocErrorKind::InvalidSizeClass`](crate::AllocErrorKind::InvalidSizeClass).
+    pub fn size_classes(mut self, class_size: fn(usize) -> usize) -> Self {
+        assert!(
+            class_size(0) >= MIN_BLOCK_SIZE,
+            "the first size class must hold at least MIN_BLOCK_SIZE bytes"
+        );
+        assert!(
+            class_size(1) > class_size(0),
+            "size classes must keep growing"
+        );
+        self.config.size_classes = Some(class_size);
+        self
+    }
+
+    /// Warn [`Arena::on_warning`] callbacks about every allocation above `bytes`.
+    pub fn warn_large_alloc(mut self, bytes: usize) -> Self {
+        self.warnings.large_alloc = Some(bytes);
+        self
+    }
+
+    /// The number of reserved bytes the arena is expected to stay under,
+    /// which [`ArenaBuilder::warn_at_watermarks`] are fractions of.
+    ///
+    /// Nothing is enforced, it only drives warnings.
+    pub fn soft_limit(mut self, bytes: usize) -> Self {
+        self.warnings.soft_limit = Some(bytes);
+        self
+    }
+
+    /// Warn [`Arena::on_warning`] callbacks when the reserved bytes cross
+    /// these fractions of the [`ArenaBuilder::soft_limit`], such as `0.5` and `0.8`.
+    ///
+    /// Each one fires once when it is crossed, and can fire again
+    /// after the arena shrank back below it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a fraction is not positive and finite.
+    pub fn warn_at_watermarks(mut self, fractions: &[f32]) -> Self {
+        assert!(
+            fractions.iter().all(|f| f.is_finite() && *f > 0.0),
+            "watermarks must be positive and finite"
+        );
+        self.warnings.watermarks = fractions.to_vec();
+        self.warnings.watermarks.sort_by(f32::total_cmp);
+        self
+    }
+
+    /// Name the arena in panic messages, stats and reports.
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.config.label = Some(label);
+        self
+    }
+
+    /// Create the arena.
+    pub fn build(self) -> Arena {
+        let mut arena = Arena::with_config(self.config);
+        arena.state.warnings = Warnings::new(self.warnings);
+
+        arena
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{chunk::ListMark, Arena};
+
+/// Bump positions of an arena, to roll back to later.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// ID of the arena it was taken from
+    arena: u64,
+    /// Chunk generation of the arena when it was taken
+    generation: u64,
+    marks: Vec<ListMark>,
+}
+
+impl Arena {
+    /// Remember how far every size class was bumped.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        Checkpoint {
+            arena: self.state.id,
+            generation: self.state.chunk_generation.get(),
+            marks: chunks.iter().map(|list| list.mark()).collect(),
+        }
+    }
+
+    /// Release everything allocated since `checkpoint` was taken.
+    ///
+    /// Every value allocated after the checkpoint must have been dropped.
+    /// Values that were leaked before it stay intact.
+    ///
+    /// If chunks were deallocated since, such as by [`Arena::shrink_to_fit`]
+    /// or the retention policy, the checkpoint may point at them and nothing is
+    /// rolled back. Returns whether the rollback happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint was taken from another arena, or if a value
+    /// allocated after it is still alive, such as one that was leaked.
+    /// Nothing is rolled back then.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) -> bool {
+        assert!(
+            checkpoint.arena == self.state.id,
+            "checkpoint belongs to another arena"
+        );
+        if checkpoint.generation != self.state.chunk_generation.get() {
+            return false;
+        }
+
+        // Rolling back over a live value would hand its memory out again.
+        // Classes created after the checkpoint only hold values allocated after it.
+        let chunks = self.chunks.get_mut();
+        let alive = chunks
+            .iter()
+            .zip(&checkpoint.marks)
+            .any(|(list, mark)| list.allocated_since(mark))
+            || chunks
+                .iter()
+                .skip(checkpoint.marks.len())
+                .any(|list| list.chunks().any(|chunk| chunk.refs() != 0));
+        assert!(!alive, "values allocated after the checkpoint are alive");
+
+        for (list, mark) in chunks.iter().zip(&checkpoint.marks) {
+            list.rewind(mark);
+        }
+
+        true
+    }
+}
+
+#[test]
+fn rollback_reclaims() {
+    let mut arena = Arena::new();
+    let kept = arena.alloc(7_u64);
+    let kept_ptr = kept.as_raw();
+    // Keeps the chunk from being reset while everything else is dropped
+    std::mem::forget(kept);
+
+    let checkpoint = arena.checkpoint();
+    let (first, remaining) = {
+        let values: Vec<_> = (0..8).map(|i| arena.alloc(i as u64)).collect();
+        let big = arena.alloc_slice_fill_copy(4096, &0_u8);
+        drop(big);
+        (values[0].as_raw(), values[0].chunk_remaining())
+    };
+    assert!(arena.rollback(checkpoint));
+
+    let value = arena.alloc(9_u64);
+    assert_eq!(value.as_raw(), first);
+    assert!(value.chunk_remaining() > remaining);
+    assert_eq!(unsafe { *kept_ptr }, 7);
+}
+
+#[test]
+fn stale_checkpoints_are_rejected() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut arena = Arena::new();
+    let checkpoint = arena.checkpoint();
+    drop(arena.alloc_slice_fill_copy(1000, &0_u8));
+    arena.shrink_to_fit();
+    assert!(!arena.rollback(checkpoint));
+
+    // An arena built where another one was is still another arena
+    let checkpoint = arena.checkpoint();
+    drop(arena);
+    let mut arena = Arena::new();
+    assert!(catch_unwind(AssertUnwindSafe(|| arena.rollback(checkpoint))).is_err());
+}
+
+#[test]
+fn rollback_over_live_values_panics() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut arena = Arena::new();
+    let checkpoint = arena.checkpoint();
+    let leaked = arena.alloc(7_u64);
+    let leaked_ptr = leaked.as_raw();
+    std::mem::forget(leaked);
+
+    let error = catch_unwind(AssertUnwindSafe(|| arena.rollback(checkpoint))).unwrap_err();
+    let message = error.downcast_ref::<&str>().unwrap();
+    assert!(message.contains("values allocated after the checkpoint are alive"));
+
+    // Nothing was rolled back, the leaked value keeps its memory
+    let value = arena.alloc(9_u64);
+    assert_ne!(value.as_raw(), leaked_ptr);
+    assert_eq!(unsafe { *leaked_ptr }, 7);
+    drop(value);
+
+    // Values leaked in a class created after the checkpoint count too
+    let mut arena = Arena::new();
+    let checkpoint = arena.checkpoint();
+    std::mem::forget(arena.alloc_slice_fill_copy(4096, &0_u8));
+    assert!(catch_unwind(AssertUnwindSafe(|| arena.rollback(checkpoint))).is_err());
+}
@@ -1,9 +1,13 @@
 use std::{
-    cell::{Cell, UnsafeCell},
+    cell::Cell,
     ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
-use crate::chunk::{Chunk, FreeList};
+use crate::{
+    chunk::{Backing, Chunk, FreeLink, ListRef},
+    state::StateRef,
+};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -22,33 +26,53 @@ pub struct ChunkFooter {
     /// Bump allocation pointer.
     pub(crate) bump: Cell<NonNull<u8>>,
 
+    /// Lowest committed byte. Reserved chunks commit their pages as the
+    /// bump pointer passes them, every other chunk is committed from the start.
+    pub(crate) committed: Cell<NonNull<u8>>,
+
     /// Next chunk
-    pub(crate) next: Option<Chunk>,
+    pub(crate) next: Cell<Option<Chunk>>,
+
+    /// Previous chunk
+    pub(crate) prev: Cell<Option<Chunk>>,
 
     /// Next free chunk
-    pub(crate) next_free: Cell<Option<Chunk>>,
+    pub(crate) next_free: FreeLink,
+
+    /// The chunk list this chunk belongs to
+    pub(crate) list: ListRef,
+
+    /// Where the chunk memory came from.
+    pub(crate) backing: Backing,
 
-    /// Pointer to the free list head
-    pub(crate) free_list: FreeList,
+    /// State of the owning arena.
+    pub(crate) state: StateRef,
 
     /// Chunk flags, contains information about
     /// whether this chunk is free, the current chunk,
     /// the reference count, the next free chunk, and
     /// the bump pointer position.
-    pub(crate) flags: UnsafeCell<u64>,
+    ///
+    /// Atomic so a [`SyncArena`](crate::SyncArena) can count references from
+    /// several threads. Single threaded arenas only load and store it,
+    /// which costs the same as plain reads and writes.
+    pub(crate) flags: AtomicU64,
 }
 
 const CURRENT_BIT: u64 = !(u64::MAX >> 1);
 const FREE_BIT: u64 = CURRENT_BIT >> 1;
-const REF_COUNT: u64 = !(CURRENT_BIT | FREE_BIT);
+const PINNED_BIT: u64 = FREE_BIT >> 1;
+const REF_COUNT: u64 = !(CURRENT_BIT | FREE_BIT | PINNED_BIT);
 
 impl ChunkFooter {
-    pub const fn new(
+    pub fn new(
         start: NonNull<u8>,
         size: usize,
         index: usize,
         next: Option<Chunk>,
-        free_list: FreeList,
+        list: ListRef,
+        backing: Backing,
+        state: StateRef,
     ) -> Self {
         let bump = unsafe { NonNull::new_unchecked(start.as_ptr().add(size)) };
 
@@ -56,22 +80,34 @@ impl ChunkFooter {
             size,
             start,
             index,
-            next,
-            free_list,
-            flags: UnsafeCell::new(0),
+            next: Cell::new(next),
+            prev: Cell::new(None),
+            list,
+            backing,
+            state,
+            flags: AtomicU64::new(0),
             bump: Cell::new(bump),
-            next_free: Cell::new(None),
+            committed: Cell::new(start),
+            next_free: FreeLink::new(),
         }
     }
 
+    /// How many bytes can still be bump allocated,
+    /// the distance from the start to the bump pointer.
     #[inline]
-    fn flags_ptr(&self) -> *mut u64 {
-        self.flags.get()
+    pub fn remaining(&self) -> usize {
+        self.bump.get().as_ptr() as usize - self.start.as_ptr() as usize
     }
 
     #[inline]
     pub fn flags(&self) -> u64 {
-        unsafe { *self.flags_ptr() }
+        self.flags.load(Ordering::Relaxed)
+    }
+
+    /// Replace the flags, only from the thread owning the chunk.
+    #[inline]
+    fn set_flags(&self, flags: u64) {
+        self.flags.store(flags, Ordering::Relaxed)
     }
 
     /// Get the reference count
@@ -86,11 +122,9 @@ impl ChunkFooter {
     pub fn add_ref(&self) -> u64 {
         let previous = self.refs();
         assert!(previous != REF_COUNT, "reference counter will overflow");
-        unsafe {
-            // Since the lower bits are where we store the counter
-            // and since we checked for an overflow, this should work.
-            *self.flags_ptr() += 1;
-        }
+        // Since the lower bits are where we store the counter
+        // and since we checked for an overflow, this should work.
+        self.set_flags(self.flags() + 1);
 
         previous
     }
@@ -101,15 +135,81 @@ impl ChunkFooter {
     pub fn remove_ref(&self) -> u64 {
         let previous = self.refs();
         assert!(previous != 0, "reference counter will underflow");
-        unsafe {
-            // Since the lower bits are where we store the counter
-            // and since we checked for an underflow, this should work.
-            *self.flags_ptr() -= 1;
-        }
+        // Since the lower bits are where we store the counter
+        // and since we checked for an underflow, this should work.
+        self.set_flags(self.flags() - 1);
+
+        previous
+    }
+
+    /// Increment the reference count from any thread, returning the previous count.
+    /// Panics on reference count overflow.
+    #[inline]
+    pub(crate) fn add_ref_shared(&self) -> u64 {
+        // A new reference comes from an existing one or the allocating
+        // thread, which already see the chunk, like `Arc`
+        let previous = self.flags.fetch_add(1, Ordering::Relaxed) & REF_COUNT;
+        assert!(previous != REF_COUNT, "reference counter overflowed");
 
         previous
     }
 
+    /// Decrement the reference count from any thread.
+    ///
+    /// Returns `true` if that was the last reference to a chunk that is
+    /// no longer current, which the caller then has to free.
+    #[inline]
+    pub(crate) fn remove_ref_shared(&self) -> bool {
+        // Acquiring makes every other thread done with the chunk memory
+        // if this was the last reference. A fence would do, but sanitizers
+        // can't follow fences
+        let previous = self.flags.fetch_sub(1, Ordering::AcqRel);
+        assert!(previous & REF_COUNT != 0, "reference counter underflowed");
+
+        previous & (REF_COUNT | CURRENT_BIT) == 1
+    }
+
+    /// Clear the current bit from any thread.
+    ///
+    /// Returns `true` if no references were left, in which case the
+    /// caller has to free the chunk, since no handle will.
+    #[inline]
+    pub(crate) fn retire_shared(&self) -> bool {
+        let previous = self.flags.fetch_and(!CURRENT_BIT, Ordering::AcqRel);
+        debug_assert!(
+            previous & CURRENT_BIT != 0,
+            "retired a chunk that was not current"
+        );
+
+        previous & REF_COUNT == 0
+    }
+
+    /// Forget every reference, such as ones held by leaked handles.
+    ///
+    /// This unpins the chunk too.
+    #[inline]
+    pub(crate) fn clear_refs(&self) {
+        self.set_flags(self.flags() & !(REF_COUNT | PINNED_BIT));
+    }
+
+    /// Checks if the pinned bit is set.
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        self.flags() & PINNED_BIT != 0
+    }
+
+    /// Keep this chunk until the arena drops, by holding
+    /// one reference that is never given back.
+    ///
+    /// Pinning an already pinned chunk does nothing.
+    #[inline]
+    pub(crate) fn pin(&self) {
+        if !self.is_pinned() {
+            self.add_ref();
+            self.set_flags(self.flags() | PINNED_BIT);
+        }
+    }
+
     /// Checks if the free bit is set.
     #[inline]
     pub fn is_free(&self) -> bool {
@@ -125,16 +225,12 @@ impl ChunkFooter {
     /// Toggle the free bit.
     #[inline]
     pub fn toggle_free(&self) {
-        unsafe {
-            *self.flags_ptr() ^= FREE_BIT;
-        }
+        self.set_flags(self.flags() ^ FREE_BIT);
     }
 
     /// Toggle the current bit.
     #[inline]
     pub fn toggle_current(&self) {
-        unsafe {
-            *self.flags_ptr() ^= CURRENT_BIT;
-        }
+        self.set_flags(self.flags() ^ CURRENT_BIT);
     }
 }
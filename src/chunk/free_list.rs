@@ -1,22 +1,252 @@
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-
-use std::{ptr::NonNull, cell::Cell};
+#[cfg(loom)]
+use loom::sync::atomic::AtomicPtr;
+#[cfg(not(loom))]
+use std::sync::atomic::AtomicPtr;
 
 use thiserror::Error;
 
-use crate::chunk::Chunk;
+use crate::{
+    chunk::{Chunk, ChunkFooter},
+    fail::fail,
+};
+
+/// Holds the top of a [`FreeList`].
+///
+/// The stack logic is written against this trait, so a thread safe
+/// list only needs a different slot and keeps the same invariants.
+pub trait FreeSlot {
+    /// Counter for the length of the stack.
+    type Count: FreeCount;
+
+    /// A slot holding no chunk.
+    fn empty() -> Self;
+
+    fn load(&self) -> Option<Chunk>;
+
+    fn store(&self, chunk: Option<Chunk>);
+
+    /// Replace the chunk in the slot with `new` if it still holds `current`.
+    /// Otherwise the chunk actually in the slot is returned.
+    fn compare_exchange(
+        &self,
+        current: Option<Chunk>,
+        new: Option<Chunk>,
+    ) -> Result<(), Option<Chunk>>;
+}
+
+/// Length counter of a [`FreeList`].
+pub trait FreeCount {
+    const ZERO: Self;
+
+    fn get(&self) -> usize;
+
+    fn add(&self, n: usize);
+
+    fn sub(&self, n: usize);
+
+    fn reset(&self);
+}
+
+impl FreeSlot for Cell<Option<Chunk>> {
+    type Count = Cell<usize>;
+
+    #[inline]
+    fn empty() -> Self {
+        Cell::new(None)
+    }
+
+    #[inline]
+    fn load(&self) -> Option<Chunk> {
+        self.get()
+    }
+
+    #[inline]
+    fn store(&self, chunk: Option<Chunk>) {
+        self.set(chunk)
+    }
+
+    #[inline]
+    fn compare_exchange(
+        &self,
+        current: Option<Chunk>,
+        new: Option<Chunk>,
+    ) -> Result<(), Option<Chunk>> {
+        // Nothing else can touch the slot in between
+        let actual = self.get();
+        if actual != current {
+            return Err(actual);
+        }
+
+        self.set(new);
+        Ok(())
+    }
+}
+
+impl FreeCount for Cell<usize> {
+    const ZERO: Self = Cell::new(0);
+
+    #[inline]
+    fn get(&self) -> usize {
+        Cell::get(self)
+    }
+
+    #[inline]
+    fn add(&self, n: usize) {
+        self.set(self.get() + n)
+    }
+
+    #[inline]
+    fn sub(&self, n: usize) {
+        self.set(self.get() - n)
+    }
+
+    #[inline]
+    fn reset(&self) {
+        self.set(0)
+    }
+}
+
+/// Link from a free chunk to the one below it on its [`FreeList`].
+///
+/// Atomic since a `FreeList<AtomicSlot>` reads it on another thread
+/// than the one that pushed the chunk. Relaxed accesses are enough,
+/// the top of the stack is released and acquired around them.
+#[derive(Debug)]
+pub struct FreeLink(AtomicPtr<ChunkFooter>);
+
+impl FreeLink {
+    #[inline]
+    pub fn new() -> Self {
+        Self(AtomicPtr::new(ptr::null_mut()))
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<Chunk> {
+        AtomicSlot::into_chunk(self.0.load(Ordering::Relaxed))
+    }
+
+    #[inline]
+    pub fn set(&self, chunk: Option<Chunk>) {
+        self.0.store(AtomicSlot::into_raw(chunk), Ordering::Relaxed)
+    }
+}
+
+/// A slot that can be shared between threads.
+///
+/// Pushing is safe from any thread, but popping takes a single consumer,
+/// since a chunk popped and pushed again in between would go unnoticed.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct AtomicSlot(AtomicPtr<ChunkFooter>);
+
+impl AtomicSlot {
+    #[inline]
+    fn into_chunk(ptr: *mut ChunkFooter) -> Option<Chunk> {
+        NonNull::new(ptr).map(Chunk)
+    }
+
+    #[inline]
+    fn into_raw(chunk: Option<Chunk>) -> *mut ChunkFooter {
+        chunk.map_or(ptr::null_mut(), |chunk| chunk.0.as_ptr())
+    }
+}
+
+impl FreeSlot for AtomicSlot {
+    type Count = AtomicUsize;
+
+    #[inline]
+    fn empty() -> Self {
+        AtomicSlot(AtomicPtr::new(ptr::null_mut()))
+    }
+
+    #[inline]
+    fn load(&self) -> Option<Chunk> {
+        Self::into_chunk(self.0.load(Ordering::Acquire))
+    }
+
+    #[inline]
+    fn store(&self, chunk: Option<Chunk>) {
+        self.0.store(Self::into_raw(chunk), Ordering::Release)
+    }
+
+    #[inline]
+    fn compare_exchange(
+        &self,
+        current: Option<Chunk>,
+        new: Option<Chunk>,
+    ) -> Result<(), Option<Chunk>> {
+        self.0
+            .compare_exchange(
+                Self::into_raw(current),
+                Self::into_raw(new),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .map(|_| ())
+            .map_err(Self::into_chunk)
+    }
+}
+
+impl FreeCount for AtomicUsize {
+    const ZERO: Self = AtomicUsize::new(0);
+
+    #[inline]
+    fn get(&self) -> usize {
+        self.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn add(&self, n: usize) {
+        self.fetch_add(n, Ordering::Relaxed);
+    }
 
+    #[inline]
+    fn sub(&self, n: usize) {
+        self.fetch_sub(n, Ordering::Relaxed);
+    }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FreeList(NonNull<Cell<Option<Chunk>>>);
+    #[inline]
+    fn reset(&self) {
+        self.store(0, Ordering::Relaxed)
+    }
+}
+
+/// Stack of chunks without allocations, linked through their footers.
+///
+/// A `FreeList<AtomicSlot>` can be pushed to from any thread, but only one
+/// thread at a time may pop from it, like a [`SyncArena`](crate::SyncArena)
+/// does under its lock. Two racing pops could see a chunk popped and pushed
+/// again between their loads, and link a chunk that is in use.
+#[derive(Debug)]
+pub struct FreeList<S: FreeSlot = Cell<Option<Chunk>>> {
+    /// Top of the stack
+    top: S,
+    /// How many chunks are on the stack
+    len: S::Count,
+}
 
-impl FreeList {
+impl<S: FreeSlot> FreeList<S> {
     pub fn new() -> Self {
-        Self(Box::leak(Box::new(Cell::new(None))).into())
+        Self {
+            top: S::empty(),
+            len: S::Count::ZERO,
+        }
     }
 
     pub fn peek(&self) -> Option<Chunk> {
-        unsafe { self.0.as_ref().get() }
+        self.top.load()
+    }
+
+    /// How many chunks are on the free list.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
     }
 
     /// Returns an error specifying why a chunk cannot be freed if
@@ -41,19 +271,35 @@ impl FreeList {
     }
 
     /// Pop a free chunk and unmark it as free, if it exists.
+    #[track_caller]
     pub fn pop(&self) -> Option<Chunk> {
-        let top = unsafe { self.0.as_ref() };
-        let popped = top.take();
+        let mut top = self.top.load();
 
-        if let Some(popped) = popped {
-            assert!(popped.is_free(), "corrupt free list");
+        let popped = loop {
+            let popped = top?;
+            let next_free = popped.next_free.get();
 
-            let next_free = popped.next_free.take();
-            top.set(next_free);
-            popped.toggle_free();
+            match self.top.compare_exchange(Some(popped), next_free) {
+                Ok(()) => break popped,
+                Err(actual) => top = actual,
+            }
+        };
+
+        if !popped.is_free() {
+            fail(
+                popped.state.config.label,
+                None,
+                Some(popped.state.config.class_of(popped.size)),
+                Some(popped),
+                "corrupt free list",
+            );
         }
 
-        popped
+        popped.next_free.set(None);
+        popped.toggle_free();
+        self.len.sub(1);
+
+        Some(popped)
     }
 
     /// Push a chunk and mark it as free.
@@ -61,23 +307,31 @@ impl FreeList {
     pub fn push(&self, chunk: Chunk) -> Result<(), FreeError> {
         Self::can_push(chunk)?;
 
-        let top = unsafe { self.0.as_ref() };
-        let next_free = top.take();
-
-        // Set the next free value and mark it as free.
-        chunk.next_free.set(next_free);
+        // Mark it as free before anyone can pop it
         chunk.toggle_free();
 
-        // Push it
-        top.set(Some(chunk));
+        let mut top = self.top.load();
+        loop {
+            // Set the next free value and push it
+            chunk.next_free.set(top);
 
+            match self.top.compare_exchange(top, Some(chunk)) {
+                Ok(()) => break,
+                Err(actual) => top = actual,
+            }
+        }
+        self.len.add(1);
+
+        #[cfg(feature = "debug-trace")]
         println!("Freed {}-{}", chunk.size, chunk.index);
 
         Ok(())
     }
 
-    pub unsafe fn drop(self) {
-        drop(Box::from_raw(self.0.as_ptr()))
+    /// Forget every chunk on the free list.
+    pub fn clear(&self) {
+        self.top.store(None);
+        self.len.reset();
     }
 }
 
@@ -90,3 +344,106 @@ pub enum FreeError {
     #[error("chunk has {0} references when it needs to be zero")]
     RefCount(u64),
 }
+
+#[cfg(test)]
+fn exercise_free_list<S: FreeSlot>() {
+    use crate::{
+        builder::Config,
+        chunk::{ChunkList, ListRef},
+        state::{State, StateRef},
+    };
+
+    let state = State::new(Config::default());
+    let state = StateRef::new(&state);
+    let list = ChunkList::empty(256, state);
+    let chunks: Vec<Chunk> = (0..3)
+        .map(|index| unsafe {
+            Chunk::try_allocate(256, index, None, ListRef::new(&list), state).unwrap()
+        })
+        .collect();
+
+    let free = FreeList::<S>::new();
+    for &chunk in &chunks {
+        free.push(chunk).unwrap();
+        assert!(matches!(free.push(chunk), Err(FreeError::AlreadyFree)));
+    }
+    assert_eq!(free.len(), 3);
+    assert_eq!(free.peek(), chunks.last().copied());
+
+    let popped: Vec<Chunk> = std::iter::from_fn(|| free.pop()).collect();
+    assert!(popped.iter().eq(chunks.iter().rev()));
+    assert_eq!(free.len(), 0);
+
+    for chunk in popped {
+        unsafe { chunk.deallocate() };
+    }
+}
+
+#[test]
+fn free_list_backends() {
+    exercise_free_list::<Cell<Option<Chunk>>>();
+    exercise_free_list::<AtomicSlot>();
+}
+
+// Only this crate can be built with `--cfg loom`, so run it with
+// `cargo rustc --lib --profile test -- --cfg loom` and the test binary.
+#[cfg(loom)]
+#[test]
+fn atomic_free_list_single_consumer() {
+    use crate::{
+        builder::Config,
+        chunk::{ChunkList, ListRef},
+        state::{State, StateRef},
+    };
+    use loom::{sync::Arc, thread};
+
+    struct Shared(FreeList<AtomicSlot>);
+    // SAFETY: pushes may race, and only the model thread pops.
+    unsafe impl Send for Shared {}
+    unsafe impl Sync for Shared {}
+
+    struct SendChunk(Chunk);
+    unsafe impl Send for SendChunk {}
+
+    loom::model(|| {
+        let state = State::new(Config::default());
+        let state = StateRef::new(&state);
+        let list = ChunkList::empty(256, state);
+        let chunks: Vec<Chunk> = (0..3)
+            .map(|index| unsafe {
+                Chunk::try_allocate(256, index, None, ListRef::new(&list), state).unwrap()
+            })
+            .collect();
+
+        let free = Arc::new(Shared(FreeList::new()));
+        free.0.push(chunks[2]).unwrap();
+
+        let pushers: Vec<_> = chunks[..2]
+            .iter()
+            .map(|&chunk| {
+                let free = free.clone();
+                let chunk = SendChunk(chunk);
+                thread::spawn(move || {
+                    let chunk = chunk;
+                    free.0.push(chunk.0).unwrap();
+                })
+            })
+            .collect();
+
+        let mut popped: Vec<Chunk> = free.0.pop().into_iter().collect();
+        for pusher in pushers {
+            pusher.join().unwrap();
+        }
+        popped.extend(std::iter::from_fn(|| free.0.pop()));
+
+        assert_eq!(free.0.len(), 0);
+        assert_eq!(popped.len(), 3);
+        for chunk in &chunks {
+            assert_eq!(popped.iter().filter(|&popped| popped == chunk).count(), 1);
+        }
+
+        for chunk in chunks {
+            unsafe { chunk.deallocate() };
+        }
+    });
+}
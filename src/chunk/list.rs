@@ -1,8 +1,16 @@
-use std::{alloc::Layout, cell::Cell};
+use std::{alloc::Layout, cell::Cell, collections::HashSet, ops::Deref, ptr::NonNull};
 
-use crate::{chunk::{FreeList, Chunk}, ptr::Ptr};
+use crate::{
+    chunk::{Backing, Chunk, FreeError, FreeList},
+    fail::fail,
+    ptr::Ptr,
+    state::{PoisonGuard, StateRef},
+    AllocError, CorruptionError, MIN_BLOCK_SIZE,
+};
 
 /// Handles chunks of a certain size.
+///
+/// Lists are boxed by the arena, so chunks can point back at them.
 #[derive(Debug)]
 pub struct ChunkList {
     /// Size of each chunk
@@ -13,66 +21,570 @@ pub struct ChunkList {
     head: Cell<Option<Chunk>>,
     /// Current chunk being operated on.
     current: Cell<Option<Chunk>>,
-    /// Pointer to the free list head
+    /// Chunks ready to be reused
     free_list: FreeList,
+    /// State of the owning arena
+    state: StateRef,
+    /// How many chunks got explicit huge pages
+    huge: Cell<usize>,
+    /// How many chunks were advised to use transparent huge pages
+    advised: Cell<usize>,
+    /// How many chunks were deallocated by the retention policy
+    trimmed: Cell<usize>,
+    /// How many undersized emergency chunks are alive
+    emergency: Cell<usize>,
+    /// Most chunks of the full size there ever were at once
+    peak: Cell<usize>,
 }
 
 impl ChunkList {
-    /// Create a default chunk list
-    pub fn new(size: usize) -> Self {
-        Self::with_capacity(size, 4)
-    }
-
-    /// Create a chunk list with `cap` chunks.
-    /// If `cap` is zero, this is no different than creating
-    /// an empty chunk list.
-    pub fn with_capacity(size: usize, cap: usize) -> Self {
-        let list = Self::empty(size);
-        list.reserve(cap);
+    /// Create a default chunk list, with a few chunks up front.
+    pub fn try_new(size: usize, state: StateRef) -> Result<Box<Self>, AllocError> {
+        let list = Self::empty(size, state);
+        list.try_reserve(4)?;
 
-        list
+        Ok(list)
     }
 
     /// Allocate n chunks
     pub fn reserve(&self, n: usize) {
+        if let Err(error) = self.try_reserve(n) {
+            fail(
+                self.state.config.label,
+                None,
+                Some(self.state.config.class_of(self.size)),
+                None,
+                error,
+            )
+        }
+    }
+
+    /// Allocate n chunks, stopping at the first that fails.
+    pub fn try_reserve(&self, n: usize) -> Result<(), AllocError> {
         for _ in 0..n {
-            self.allocate_chunk();
+            self.try_allocate_chunk()
+                .map_err(|error| self.state.refuse_growth(error))?;
         }
+
+        Ok(())
     }
 
     /// Create a chunk list with no chunks (yet).
-    pub fn empty(size: usize) -> Self {
-        assert!(size.is_power_of_two(), "chunk size must be a power of two");
+    pub fn empty(size: usize, state: StateRef) -> Box<Self> {
+        assert!(
+            size >= MIN_BLOCK_SIZE,
+            "chunks must hold at least MIN_BLOCK_SIZE bytes"
+        );
 
+        Box::new(Self::unboxed(size, state))
+    }
+
+    /// Create an empty chunk list in place, for a chunk that is not
+    /// allocated by it, such as the buffer of a [`StackArena`](crate::StackArena).
+    pub(crate) fn unboxed(size: usize, state: StateRef) -> Self {
         Self {
             size,
             len: Cell::new(0),
             head: Cell::new(None),
             current: Cell::new(None),
             free_list: FreeList::new(),
+            state,
+            huge: Cell::new(0),
+            advised: Cell::new(0),
+            trimmed: Cell::new(0),
+            emergency: Cell::new(0),
+            peak: Cell::new(0),
         }
     }
 
+    /// Size of each chunk
+    #[inline]
+    pub fn chunk_size(&self) -> usize {
+        self.size
+    }
+
+    /// How many chunks there are
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// How many chunks got explicit huge pages
+    #[inline]
+    pub fn huge_chunks(&self) -> usize {
+        self.huge.get()
+    }
+
+    /// How many chunks were advised to use transparent huge pages
+    #[inline]
+    pub fn advised_chunks(&self) -> usize {
+        self.advised.get()
+    }
+
+    /// How many chunks were deallocated by the retention policy
+    #[inline]
+    pub fn trimmed_chunks(&self) -> usize {
+        self.trimmed.get()
+    }
+
+    /// How many undersized emergency chunks are alive
+    #[inline]
+    pub fn emergency_chunks(&self) -> usize {
+        self.emergency.get()
+    }
+
+    /// Most chunks of the full size there ever were at once
+    #[inline]
+    pub fn peak_chunks(&self) -> usize {
+        self.peak.get()
+    }
+
+    /// Bytes requested from the backing allocator for this list's chunks.
+    pub fn reserved_bytes(&self) -> usize {
+        let full = Chunk::layout(self.size).map_or(0, |(layout, _)| layout.size());
+        if self.emergency.get() == 0 && !self.state.config.fixed_chunks {
+            return full * self.len();
+        }
+
+        self.chunks().map(|chunk| chunk.reserved_bytes()).sum()
+    }
+
+    /// Drop the memory behind the pages of every free chunk, keeping them
+    /// mapped, and return how many bytes were given back.
+    /// Adopted chunks are not ours to touch.
+    #[cfg(feature = "madvise")]
+    pub fn release_free_pages(&self) -> usize {
+        std::iter::successors(self.free_list.peek(), |chunk| chunk.next_free.get())
+            .filter(|chunk| !matches!(chunk.backing, Backing::External | Backing::Inline))
+            .map(|chunk| unsafe { super::sys::discard(chunk.start, chunk.size) })
+            .sum()
+    }
+
+    /// Bytes of this list's chunks that are backed by memory,
+    /// counting every page a chunk touches.
+    #[cfg(all(feature = "resident", unix))]
+    pub fn resident_bytes(&self) -> usize {
+        self.chunks()
+            .map(|chunk| unsafe { super::sys::resident(chunk.start, chunk.reserved_bytes()) })
+            .sum()
+    }
+
     /// Allocate a new chunk
     /// and push it onto the chunk
     /// stack and free list.
-    fn allocate_chunk(&self) -> Chunk {
+    #[track_caller]
+    fn try_allocate_chunk(&self) -> Result<Chunk, AllocError> {
+        self.state.check_growth(self.size)?;
+
         let index = self.len.get();
-        let chunk = unsafe { Chunk::allocate(self.size, index, self.head.get(), self.free_list) };
-        chunk.free().unwrap();
+        let next = self.head.get();
 
+        #[cfg(feature = "latency-hist")]
+        let started = std::time::Instant::now();
+
+        let chunk =
+            unsafe { Chunk::try_allocate(self.size, index, next, ListRef::new(self), self.state)? };
+
+        #[cfg(feature = "latency-hist")]
+        self.state.latency.record(started.elapsed());
+
+        // The chunk is lost if this unwinds before it is linked
+        let guard = PoisonGuard::new(&self.state);
+
+        // Fresh chunks skip the retention policy, they were asked for
+        self.free_list.push(chunk).unwrap();
+
+        if chunk.backing.is_huge() {
+            self.huge.set(self.huge.get() + 1);
+        }
+        if chunk.backing.is_advised() {
+            self.advised.set(self.advised.get() + 1);
+        }
+        self.state.count_chunk_allocation(chunk);
+        self.link(chunk);
+        guard.disarm();
+
+        Ok(chunk)
+    }
+
+    /// Allocate an emergency chunk just big enough for `layout`
+    /// and push it onto the chunk stack.
+    ///
+    /// It never becomes the current chunk, and it is deallocated
+    /// as soon as its allocation is gone.
+    #[track_caller]
+    fn try_allocate_emergency(&self, layout: Layout) -> Result<Chunk, AllocError> {
+        self.state.check_growth(layout.size())?;
+
+        let index = self.len.get();
+        let next = self.head.get();
+        let chunk = unsafe {
+            Chunk::try_allocate_emergency(layout, index, next, ListRef::new(self), self.state)?
+        };
+
+        self.emergency.set(self.emergency.get() + 1);
+        self.state.count_chunk_allocation(chunk);
+        self.link(chunk);
+
+        Ok(chunk)
+    }
+
+    /// Push a freshly allocated chunk onto the chunk stack.
+    pub(crate) fn link(&self, chunk: Chunk) {
+        if let Some(next) = chunk.next.get() {
+            next.prev.set(Some(chunk));
+        }
         self.head.set(Some(chunk));
-        self.len.set(index + 1);
+        self.len.set(self.len.get() + 1);
 
-        chunk
+        let full = self.len.get() - self.emergency.get();
+        self.peak.set(self.peak.get().max(full));
+    }
+
+    /// Walk every chunk, newest first.
+    pub(crate) fn chunks(&self) -> impl Iterator<Item = Chunk> + '_ {
+        std::iter::successors(self.head.get(), |chunk| chunk.next.get())
+    }
+
+    /// Give a chunk without allocations back to the list.
+    ///
+    /// It is kept on the free list, unless the list already
+    /// holds as many free chunks as the retention policy allows.
+    /// Emergency chunks are always deallocated.
+    pub fn free(&self, chunk: Chunk) -> Result<(), FreeError> {
+        FreeList::<Cell<Option<Chunk>>>::can_push(chunk)?;
+
+        #[cfg(feature = "trace-export")]
+        self.state.trace.chunk_free(chunk);
+
+        #[cfg(feature = "quarantine")]
+        if self.state.config.quarantine > 0 && chunk.backing != Backing::Emergency {
+            let config = &self.state.config;
+            self.state
+                .quarantine
+                .hold(chunk, config.quarantine, config.poison_quarantined);
+
+            return Ok(());
+        }
+
+        self.free_now(chunk)
+    }
+
+    /// Give a chunk back to the list right away, skipping the quarantine.
+    pub(crate) fn free_now(&self, chunk: Chunk) -> Result<(), FreeError> {
+        // Adopted chunks are not ours to deallocate
+        if chunk.backing == Backing::External {
+            return self.free_list.push(chunk);
+        }
+
+        if chunk.backing == Backing::Emergency {
+            self.unlink(chunk);
+            unsafe { chunk.deallocate() };
+
+            return Ok(());
+        }
+
+        match self.state.config.max_free_chunks {
+            Some(max) if self.free_list.len() >= max => {
+                self.unlink(chunk);
+                unsafe { chunk.deallocate() };
+                self.trimmed.set(self.trimmed.get() + 1);
+
+                Ok(())
+            }
+            _ => self.free_list.push(chunk),
+        }
+    }
+
+    /// Remove a chunk from the chunk stack, so it can be deallocated.
+    fn unlink(&self, chunk: Chunk) {
+        let (prev, next) = (chunk.prev.get(), chunk.next.get());
+
+        match prev {
+            Some(prev) => prev.next.set(next),
+            None => self.head.set(next),
+        }
+        if let Some(next) = next {
+            next.prev.set(prev);
+        }
+
+        if chunk.backing.is_huge() {
+            self.huge.set(self.huge.get() - 1);
+        }
+        if chunk.backing.is_advised() {
+            self.advised.set(self.advised.get() - 1);
+        }
+        if chunk.backing == Backing::Emergency {
+            self.emergency.set(self.emergency.get() - 1);
+        }
+        self.len.set(self.len() - 1);
+        self.state
+            .chunk_generation
+            .set(self.state.chunk_generation.get() + 1);
+    }
+
+    /// Take a chunk with allocations off the list for good, leaving its memory
+    /// to whoever holds them. It may be the current chunk, but not a free one.
+    pub(crate) fn detach(&self, chunk: Chunk) {
+        if chunk.is_current() {
+            chunk.toggle_current();
+            self.current.set(None);
+        }
+
+        self.unlink(chunk);
+    }
+
+    /// Deallocate every chunk that holds no allocations,
+    /// returning how many were released.
+    pub fn release_free(&self) -> usize {
+        // An empty current chunk can go too
+        if let Some(current) = self.current.get() {
+            if current.refs() == 0 {
+                current.toggle_current();
+                self.current.set(None);
+                self.free_list.push(current).expect("failed to free chunk");
+            }
+        }
+
+        // Newest chunks go first, so a reservation hands their ranges out
+        // again in the order they were carved
+        let free: Vec<Chunk> = self.chunks().filter(|chunk| chunk.is_free()).collect();
+        self.free_list.clear();
+
+        let mut released = 0;
+        for chunk in free {
+            // Adopted chunks are not ours to deallocate, they go back on the free list
+            if chunk.backing == Backing::External {
+                chunk.toggle_free();
+                self.free_list.push(chunk).expect("failed to free chunk");
+                continue;
+            }
+
+            self.unlink(chunk);
+            unsafe { chunk.deallocate() };
+            released += 1;
+        }
+
+        released
+    }
+
+    /// Check the chunk stack and the free list against each other,
+    /// reporting the first broken invariant.
+    pub(crate) fn validate(&self, class: usize) -> Result<(), CorruptionError> {
+        let len = self.len();
+
+        // The chunk stack, newest first
+        let mut stack = HashSet::new();
+        let mut prev = None;
+        let mut next = self.head.get();
+        while let Some(chunk) = next {
+            if stack.len() == len || !stack.insert(chunk) {
+                return Err(CorruptionError::ChunkStackLoops { class, len });
+            }
+
+            let index = chunk.index;
+            if chunk.prev.get() != prev {
+                return Err(CorruptionError::BrokenLink { class, index });
+            }
+            if chunk.list != ListRef::new(self) {
+                return Err(CorruptionError::WrongList { class, index });
+            }
+
+            prev = Some(chunk);
+            next = chunk.next.get();
+        }
+        if stack.len() != len {
+            let found = stack.len();
+            return Err(CorruptionError::ChunkCount { class, len, found });
+        }
+
+        // The free list, every member of which is on the stack once
+        let mut free = HashSet::new();
+        let mut next = self.free_list.peek();
+        while let Some(chunk) = next {
+            if !stack.contains(&chunk) || !free.insert(chunk) {
+                return Err(CorruptionError::FreeListLoops { class });
+            }
+
+            let index = chunk.index;
+            if !chunk.is_free() {
+                return Err(CorruptionError::FreeNotMarked { class, index });
+            }
+            if chunk.refs() != 0 {
+                let refs = chunk.refs();
+                return Err(CorruptionError::FreeWithRefs { class, index, refs });
+            }
+            if chunk.remaining() != chunk.size {
+                return Err(CorruptionError::FreeNotReset { class, index });
+            }
+
+            next = chunk.next_free.get();
+        }
+        if free.len() != self.free_list.len() {
+            let (len, found) = (self.free_list.len(), free.len());
+            return Err(CorruptionError::FreeCount { class, len, found });
+        }
+
+        for &chunk in &stack {
+            let index = chunk.index;
+            if chunk.is_current() && chunk.is_free() {
+                return Err(CorruptionError::CurrentAndFree { class, index });
+            }
+            if chunk.is_free() && !free.contains(&chunk) {
+                return Err(CorruptionError::MarkedNotListed { class, index });
+            }
+            if chunk.is_current() != (self.current.get() == Some(chunk)) {
+                return Err(CorruptionError::CurrentMismatch { class, index });
+            }
+            if chunk.refs() == 0
+                && !chunk.is_current()
+                && !chunk.is_free()
+                && !self.quarantined(chunk)
+            {
+                return Err(CorruptionError::Orphaned { class, index });
+            }
+        }
+
+        // The current chunk has to be one of ours
+        match self.current.get() {
+            Some(current) if !stack.contains(&current) => Err(CorruptionError::CurrentMismatch {
+                class,
+                index: current.index,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks if a freed chunk is held back from reuse.
+    #[inline]
+    fn quarantined(&self, _chunk: Chunk) -> bool {
+        #[cfg(feature = "quarantine")]
+        return self.state.quarantine.contains(_chunk);
+
+        #[cfg(not(feature = "quarantine"))]
+        false
+    }
+
+    /// Where the current chunk is, how far it was bumped and how many references it had.
+    pub(crate) fn mark(&self) -> ListMark {
+        let current = self.current.get();
+
+        ListMark {
+            current,
+            bump: current.map(|chunk| chunk.bump.get()),
+            refs: current.map_or(0, |chunk| chunk.refs()),
+        }
+    }
+
+    /// Checks if a value allocated since `mark` is still alive
+    /// where [`ChunkList::rewind`] would release it.
+    pub(crate) fn allocated_since(&self, mark: &ListMark) -> bool {
+        let current = self.current.get();
+        if let Some(chunk) = current.filter(|&chunk| Some(chunk) != mark.current) {
+            if chunk.refs() != 0 {
+                return true;
+            }
+        }
+
+        mark.current
+            .is_some_and(|chunk| !chunk.is_free() && chunk.refs() > mark.refs)
+    }
+
+    /// Release everything allocated in the current chunk since `mark`.
+    ///
+    /// Nothing allocated since may be alive, see [`ChunkList::allocated_since`].
+    pub(crate) fn rewind(&self, mark: &ListMark) {
+        let current = self.current.get();
+
+        // A chunk that became current later goes back to the free list
+        if let Some(chunk) = current.filter(|&chunk| Some(chunk) != mark.current) {
+            chunk.toggle_current();
+            self.current.set(None);
+            if chunk.refs() == 0 {
+                unsafe { chunk.reset_bump() };
+                self.state.on_chunk_reset(chunk);
+                self.free_list.push(chunk).expect("failed to free chunk");
+            }
+        }
+
+        let (Some(chunk), Some(bump)) = (mark.current, mark.bump) else {
+            return;
+        };
+
+        // Everything in it was released and it was recycled in the meantime
+        if chunk.is_free() {
+            return;
+        }
+
+        if !chunk.is_current() {
+            chunk.toggle_current();
+            self.current.set(Some(chunk));
+        }
+        if chunk.bump.get() < bump {
+            chunk.bump.set(bump);
+        }
+    }
+
+    /// Reclaim every chunk, even ones still referenced by leaked handles.
+    ///
+    /// The caller has to make sure no handle into this list is used again.
+    pub(crate) unsafe fn reset(&self) {
+        for chunk in self.chunks() {
+            chunk.clear_refs();
+            chunk.reset_bump();
+            self.state.on_chunk_discarded(chunk);
+
+            if chunk.is_current() {
+                chunk.toggle_current();
+            }
+        }
+        self.current.set(None);
+
+        // Rebuild the free list from scratch, oldest at the bottom
+        // like freshly reserved chunks
+        self.free_list.clear();
+        let chunks: Vec<Chunk> = self.chunks().collect();
+        for chunk in chunks.into_iter().rev() {
+            if chunk.backing == Backing::Emergency {
+                self.unlink(chunk);
+                chunk.deallocate();
+                continue;
+            }
+            if chunk.is_free() {
+                chunk.toggle_free();
+            }
+            self.free_list.push(chunk).expect("failed to free chunk");
+        }
     }
 
     /// Pops a chunk from the free list or it allocates a new one.
-    fn pop_or_alloc(&self) -> Chunk {
-        if self.free_list.peek().is_none() {
-            self.allocate_chunk();
+    #[track_caller]
+    fn try_pop_or_alloc(&self, layout: Layout) -> Result<Chunk, AllocError> {
+        #[cfg(feature = "quarantine")]
+        self.state.quarantine.tick();
+
+        let fresh = self.free_list.peek().is_none();
+        if fresh {
+            self.try_allocate_chunk()?;
+        }
+
+        match self.free_list.pop() {
+            Some(chunk) => {
+                #[cfg(feature = "trace-export")]
+                if !fresh {
+                    self.state.trace.chunk_reuse(chunk);
+                }
+
+                Ok(chunk)
+            }
+            None => fail(
+                self.state.config.label,
+                Some(layout),
+                Some(self.state.config.class_of(self.size)),
+                None,
+                "failed to get a chunk",
+            ),
         }
-        self.free_list.pop().expect("failed to get a chunk")
     }
 
     /// Gets the current chunk.
@@ -83,40 +595,153 @@ impl ChunkList {
     ///
     /// It is up to the caller to ensure that the provided layout
     /// actually can fit within an empty chunk.
-    fn get_current(&self, layout: Layout) -> Chunk {
-        if let Some(current) = self.current.get() {
-            // Check that the current chunk can fit a layout.
+    ///
+    /// If no new chunk can be allocated, the current chunk is left alone.
+    #[track_caller]
+    fn try_get_current(&self, layout: Layout) -> Result<Chunk, AllocError> {
+        let current = self.current.get();
+
+        // Check that the current chunk can fit a layout.
+        if let Some(current) = current {
             if current.can_fit(layout) {
-                return current;
+                return Ok(current);
             }
+        }
 
-            // Disable the current flag
+        // Either there was no current, or the previous current chunk could not fit the value
+        let new_current = self.try_pop_or_alloc(layout)?;
+
+        // Disable the current flag
+        if let Some(current) = current {
             current.toggle_current();
         }
 
-        // Either there was no current, or the previous current chunk could not fit the value
-        let new_current = self.pop_or_alloc();
         new_current.toggle_current();
         self.current.set(Some(new_current));
 
-        new_current
+        Ok(new_current)
     }
 
-    pub(crate) fn allocate(&self, layout: Layout) -> Ptr<u8> {
-        let chunk = self.get_current(layout);
+    /// Allocate a layout, reporting when no chunk could be allocated for it.
+    #[track_caller]
+    pub(crate) fn try_allocate(&self, layout: Layout) -> Result<Ptr<u8>, AllocError> {
+        let chunk = self.try_get_current(layout)?;
+        let ptr = chunk.alloc_layout(layout);
+
+        Ok(Ptr::new(chunk, ptr))
+    }
+
+    /// Allocate a layout in a free chunk, without making it the current chunk.
+    ///
+    /// Lets a smaller class borrow a spare chunk when it cannot get its own.
+    /// The chunk comes back to the free list once the allocation is gone.
+    pub(crate) fn try_allocate_spare(&self, layout: Layout) -> Option<Ptr<u8>> {
+        let chunk = self.free_list.pop()?;
+        if !chunk.can_fit(layout) {
+            self.free_list.push(chunk).expect("failed to free chunk");
+            return None;
+        }
+
+        let ptr = chunk.alloc_layout(layout);
+        Some(Ptr::new(chunk, ptr))
+    }
+
+    /// Take a chunk able to hold `layout` out of the list, to fill
+    /// outside of it. It is pinned with one reference, and never current.
+    ///
+    /// Give it back with [`ChunkList::release_checkout`].
+    #[track_caller]
+    pub(crate) fn try_checkout(&self, layout: Layout) -> Result<Chunk, AllocError> {
+        let chunk = self
+            .try_pop_or_alloc(layout)
+            .map_err(|error| self.state.refuse_growth(error))?;
+        chunk.add_ref();
+
+        Ok(chunk)
+    }
+
+    /// Give back a chunk from [`ChunkList::try_checkout`], freeing it
+    /// once no other references are left.
+    pub(crate) unsafe fn release_checkout(&self, chunk: Chunk) {
+        if chunk.remove_ref() == 1 {
+            chunk.reset_bump();
+            self.state.on_chunk_reset(chunk);
+            self.free(chunk).expect("failed to free chunk");
+        }
+    }
+
+    /// State of the owning arena.
+    #[inline]
+    pub(crate) fn state(&self) -> StateRef {
+        self.state
+    }
+
+    /// Allocate a layout in an emergency chunk of its own.
+    ///
+    /// Used after a chunk of the full class size could not be allocated.
+    #[track_caller]
+    pub(crate) fn try_allocate_undersized(&self, layout: Layout) -> Result<Ptr<u8>, AllocError> {
+        let chunk = self.try_allocate_emergency(layout)?;
         let ptr = chunk.alloc_layout(layout);
 
-        Ptr::new(chunk, ptr)
+        Ok(Ptr::new(chunk, ptr))
+    }
+
+    /// Allocate a layout, panicking when no chunk could be allocated for it.
+    ///
+    /// Falls back to an emergency chunk before giving up.
+    #[track_caller]
+    pub(crate) fn allocate(&self, layout: Layout) -> Ptr<u8> {
+        self.try_allocate(layout)
+            .or_else(|error| self.try_allocate_undersized(layout).map_err(|_| error))
+            .map_err(|error| self.state.refuse_growth(error))
+            .unwrap_or_else(|error| {
+                fail(
+                    self.state.config.label,
+                    Some(layout),
+                    Some(self.state.config.class_of(self.size)),
+                    None,
+                    error,
+                )
+            })
     }
 }
 
 impl Drop for ChunkList {
     fn drop(&mut self) {
         unsafe {
-            self.free_list.drop();
             if let Some(chunk) = self.head.take() {
                 chunk.drop();
             }
         }
     }
 }
+
+/// The state of a [`ChunkList`] at a checkpoint.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ListMark {
+    current: Option<Chunk>,
+    bump: Option<NonNull<u8>>,
+    refs: u64,
+}
+
+/// Pointer to the [`ChunkList`] a chunk belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub(crate) struct ListRef(NonNull<ChunkList>);
+
+impl ListRef {
+    #[inline]
+    pub fn new(list: &ChunkList) -> Self {
+        Self(NonNull::from(list))
+    }
+}
+
+impl Deref for ListRef {
+    type Target = ChunkList;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.as_ref() }
+    }
+}
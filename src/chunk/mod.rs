@@ -1,10 +1,13 @@
 mod footer;
 mod free_list;
 mod list;
+mod reserve;
+pub(crate) mod sys;
 
 pub use footer::*;
 pub use free_list::*;
 pub use list::*;
+pub(crate) use reserve::Reservation;
 
 use std::{
     alloc::{self, Layout},
@@ -13,41 +16,297 @@ use std::{
     ptr::NonNull,
 };
 
+use crate::{
+    fail::fail,
+    state::{State, StateRef},
+    AllocError, HugePagePolicy,
+};
+
+/// Where the memory of a chunk came from.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backing {
+    /// Allocated through the global allocator.
+    Heap,
+    /// Allocated through the global allocator, advised to use transparent huge pages.
+    HeapAdvised,
+    /// Mapped directly with explicit huge pages.
+    MappedHuge,
+    /// Mapped directly, with the pages bound to a NUMA node.
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    Mapped,
+    /// Allocated through the global allocator, sized for a single layout
+    /// after a chunk of the full class size could not be allocated.
+    Emergency,
+    /// Memory owned by a [`StackArena`](crate::StackArena), never deallocated.
+    Inline,
+    /// Memory handed over by [`Arena::from_raw_parts`](crate::Arena::from_raw_parts),
+    /// never deallocated.
+    External,
+    /// Committed from the arena's [`Reservation`].
+    Reserved,
+}
+
+impl Backing {
+    #[inline]
+    pub(crate) fn is_huge(self) -> bool {
+        self == Backing::MappedHuge
+    }
+
+    /// The kernel was asked to back it with transparent huge pages,
+    /// which it may or may not do.
+    #[inline]
+    pub(crate) fn is_advised(self) -> bool {
+        self == Backing::HeapAdvised
+    }
+}
+
+/// Alignment of the memory for an emergency chunk.
+const EMERGENCY_ALIGN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Chunk(NonNull<ChunkFooter>);
 
 impl Chunk {
+    /// Refer to a chunk by its footer.
+    #[inline]
+    pub(crate) const fn from_footer(footer: NonNull<ChunkFooter>) -> Self {
+        Self(footer)
+    }
+
     /// Attempt to create the memory layout for a chunk in memory.
     /// Returns the layout and footer offset upon success.
-    fn layout(size: usize) -> Option<(Layout, usize)> {
-        let data = Layout::from_size_align(size, size).ok()?;
+    ///
+    /// Chunks are aligned to their size, except where [`sys::chunk_align`] caps it.
+    pub(crate) fn layout(size: usize) -> Option<(Layout, usize)> {
+        let data = Layout::from_size_align(size, sys::chunk_align(size)).ok()?;
+        let footer = Layout::new::<ChunkFooter>();
+        let (layout, footer_offset) = data.extend(footer).ok()?;
+
+        Some((layout.pad_to_align(), footer_offset))
+    }
+
+    /// Memory layout of an emergency chunk holding `size` bytes.
+    ///
+    /// Emergency chunks are only page aligned, whatever their size.
+    pub(crate) fn emergency_layout(size: usize) -> Option<(Layout, usize)> {
+        let data = Layout::from_size_align(size, EMERGENCY_ALIGN).ok()?;
         let footer = Layout::new::<ChunkFooter>();
         let (layout, footer_offset) = data.extend(footer).ok()?;
 
         Some((layout.pad_to_align(), footer_offset))
     }
 
+    /// Memory layout of this chunk, as it was allocated.
+    fn memory_layout(&self) -> Layout {
+        let layout = match self.backing {
+            Backing::Emergency => Chunk::emergency_layout(self.size),
+            _ => Chunk::layout(self.size),
+        };
+
+        layout.expect("this should be impossible").0
+    }
+
+    /// Bytes requested from the backing allocator for this chunk,
+    /// or handed over for adopted chunks.
+    #[inline]
+    pub(crate) fn reserved_bytes(&self) -> usize {
+        match self.backing {
+            Backing::External => self.size + std::mem::size_of::<ChunkFooter>(),
+            _ => self.memory_layout().size(),
+        }
+    }
+
     /// Allocate a new chunk
-    pub(crate) unsafe fn allocate(size: usize, index: usize, next: Option<Chunk>, free_list: FreeList) -> Chunk {
-        let (layout, footer_offset) = Self::layout(size).expect("invalid chunk layout");
+    #[track_caller]
+    pub(crate) unsafe fn try_allocate(
+        size: usize,
+        index: usize,
+        next: Option<Chunk>,
+        list: ListRef,
+        state: StateRef,
+    ) -> Result<Chunk, AllocError> {
+        let Some((layout, footer_offset)) = Self::layout(size) else {
+            return Err(AllocError::too_large(size));
+        };
+
+        #[cfg(feature = "failpoints")]
+        if state.failpoints.should_fail() {
+            return Err(AllocError::new(size));
+        }
 
         // Allocate
-        let start = match NonNull::new(alloc::alloc(layout)) {
-            Some(start) => start,
-            None => alloc::handle_alloc_error(layout),
+        let (start, backing) = Self::allocate_memory(layout, footer_offset, size, &state)
+            .ok_or(AllocError::new(size))?;
+
+        // Bumping relies on the alignment, so a source that broke
+        // its contract has to be caught before the footer is written
+        #[cfg(feature = "failpoints")]
+        let given = state.failpoints.misalign(start);
+        #[cfg(not(feature = "failpoints"))]
+        let given = start;
+        if !(given.as_ptr() as usize).is_multiple_of(layout.align()) {
+            Self::release_memory(start, layout, backing, state);
+            return Err(AllocError::misaligned_source(size, layout.align()));
+        }
+
+        let chunk = Self::init(
+            start,
+            footer_offset,
+            size,
+            index,
+            next,
+            list,
+            backing,
+            state,
+        );
+        if let (Backing::Reserved, Some(reservation)) = (backing, &state.reservation) {
+            let committed = start.add(reservation.page_floor(footer_offset));
+            chunk.committed.set(committed);
+        }
+
+        Ok(chunk)
+    }
+
+    /// Allocate a chunk just big enough for `layout`,
+    /// for when a chunk of the full class size could not be allocated.
+    ///
+    /// Its size is not a power of two, and it never goes on a free list.
+    pub(crate) unsafe fn try_allocate_emergency(
+        layout: Layout,
+        index: usize,
+        next: Option<Chunk>,
+        list: ListRef,
+        state: StateRef,
+    ) -> Result<Chunk, AllocError> {
+        // Rounding the size up to the alignment lets the layout
+        // sit right at the start of the chunk
+        let size = layout.pad_to_align().size().max(layout.align());
+        let memory = Self::emergency_layout(size).filter(|_| layout.align() <= EMERGENCY_ALIGN);
+        let Some((memory, footer_offset)) = memory else {
+            return Err(AllocError::new(size));
         };
 
-        // Get the footer memory and set it
+        #[cfg(feature = "failpoints")]
+        if state.failpoints.should_fail() {
+            return Err(AllocError::new(size));
+        }
+
+        let start = NonNull::new(alloc::alloc(memory)).ok_or(AllocError::new(size))?;
+
+        Ok(Self::init(
+            start,
+            footer_offset,
+            size,
+            index,
+            next,
+            list,
+            Backing::Emergency,
+            state,
+        ))
+    }
+
+    /// Write the footer of a chunk of `size` bytes at `start`, in memory that is not ours.
+    pub(crate) unsafe fn adopt(start: NonNull<u8>, size: usize, list: &ChunkList) -> Chunk {
+        let index = list.len();
+        let next = list.chunks().next();
+
+        Self::init(
+            start,
+            size,
+            size,
+            index,
+            next,
+            ListRef::new(list),
+            Backing::External,
+            list.state(),
+        )
+    }
+
+    /// Write the footer of a chunk into freshly allocated memory.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn init(
+        start: NonNull<u8>,
+        footer_offset: usize,
+        size: usize,
+        index: usize,
+        next: Option<Chunk>,
+        list: ListRef,
+        backing: Backing,
+        state: StateRef,
+    ) -> Self {
         let footer = start.as_ptr().add(footer_offset).cast::<ChunkFooter>();
         let footer = NonNull::new_unchecked(footer);
-        footer
-            .as_ptr()
-            .write(ChunkFooter::new(start, size, index, next, free_list));
+        footer.as_ptr().write(ChunkFooter::new(
+            start, size, index, next, list, backing, state,
+        ));
 
         Self(footer)
     }
 
+    /// Get the memory for a chunk, from the reserved address space if there is any left,
+    /// or following the huge page policy for chunks that are big enough to use them.
+    unsafe fn allocate_memory(
+        layout: Layout,
+        footer_offset: usize,
+        size: usize,
+        state: &State,
+    ) -> Option<(NonNull<u8>, Backing)> {
+        let reservation = state.reservation.as_ref();
+        if let Some(start) = reservation.and_then(|r| r.carve(layout, footer_offset)) {
+            return Some((start, Backing::Reserved));
+        }
+
+        let huge_pages = state.config.huge_pages;
+        let wants_huge = huge_pages != HugePagePolicy::Off && size >= sys::HUGE_PAGE_SIZE;
+        let explicit = wants_huge && huge_pages == HugePagePolicy::Explicit;
+
+        // Injected failures fall back without asking for explicit huge pages
+        #[cfg(feature = "failpoints")]
+        let map_explicit = explicit && !state.failpoints.should_fail_huge_pages();
+        #[cfg(not(feature = "failpoints"))]
+        let map_explicit = explicit;
+
+        #[cfg(all(feature = "numa", target_os = "linux"))]
+        if let Some(node) = state.config.numa_node {
+            if let Some(start) = map_explicit
+                .then(|| sys::map_on_node(layout, node, true))
+                .flatten()
+            {
+                return Some((start, Backing::MappedHuge));
+            }
+            if explicit {
+                state
+                    .huge_page_fallbacks
+                    .set(state.huge_page_fallbacks.get() + 1);
+            }
+
+            // Transparent huge pages are asked for, but not counted
+            let start = sys::map_on_node(layout, node, false)?;
+            if wants_huge {
+                sys::advise_huge(start, layout.size());
+            }
+            return Some((start, Backing::Mapped));
+        }
+
+        if explicit {
+            if let Some(start) = map_explicit.then(|| sys::map_huge(layout)).flatten() {
+                return Some((start, Backing::MappedHuge));
+            }
+            state
+                .huge_page_fallbacks
+                .set(state.huge_page_fallbacks.get() + 1);
+        }
+
+        let start = NonNull::new(alloc::alloc(layout))?;
+
+        if wants_huge && sys::advise_huge(start, layout.size()) {
+            Some((start, Backing::HeapAdvised))
+        } else {
+            Some((start, Backing::Heap))
+        }
+    }
+
     /// Calculate the pointer for a provided layout, if it can fit
     /// Reference: https://fitzgeraldnick.com/2019/11/01/always-bump-downwards.html
     fn calc_pointer(&self, size: usize, align: usize) -> Option<NonNull<u8>> {
@@ -72,23 +331,95 @@ impl Chunk {
     }
 
     /// Allocate a layout within this chunk
+    #[track_caller]
     pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
-        let ptr = self
-            .calc_pointer(layout.size(), layout.align())
-            .expect("cannot allocate!");
+        let Some(ptr) = self.calc_pointer(layout.size(), layout.align()) else {
+            fail(
+                self.state.config.label,
+                Some(layout),
+                Some(self.state.config.class_of(self.size)),
+                Some(*self),
+                "allocation does not fit in its chunk",
+            )
+        };
+        let consumed = self.bump.get().as_ptr() as usize - ptr.as_ptr() as usize;
+        self.state.count_allocation(layout.size(), consumed);
+        #[cfg(feature = "record")]
+        self.state
+            .recorder
+            .record_alloc(*self, layout.size(), layout.align());
+        #[cfg(feature = "trace-export")]
+        self.state.trace.alloc(*self, consumed);
+        #[cfg(feature = "profiling")]
+        self.state
+            .profiled
+            .alloc(*self, ptr.as_ptr(), layout.size(), self.bump.get().as_ptr());
+        self.commit_down_to(ptr);
         self.bump.set(ptr);
 
         ptr
     }
 
+    /// Make sure the memory from `ptr` up is committed, before bumping down to it.
+    #[inline]
+    #[track_caller]
+    pub(crate) fn commit_down_to(&self, ptr: NonNull<u8>) {
+        if ptr < self.committed.get() {
+            self.commit_pages(ptr);
+        }
+    }
+
+    /// Commit the pages of a reserved chunk between `ptr` and the ones already committed.
+    #[cold]
+    #[track_caller]
+    fn commit_pages(&self, ptr: NonNull<u8>) {
+        let reservation = self.state.reservation.as_ref();
+        let reservation = reservation.expect("reserved chunk without a reservation");
+        let offset = reservation.page_floor(ptr.as_ptr() as usize - self.start.as_ptr() as usize);
+        let from = unsafe { self.start.add(offset) };
+        let len = self.committed.get().as_ptr() as usize - from.as_ptr() as usize;
+
+        if !unsafe { sys::commit(from, len) } {
+            fail(
+                self.state.config.label,
+                None,
+                Some(self.state.config.class_of(self.size)),
+                Some(*self),
+                "failed to commit reserved memory",
+            )
+        }
+        self.committed.set(from);
+    }
+
+    /// Decommit the pages of a reserved chunk below the one holding its footer.
+    unsafe fn decommit_pages(&self) {
+        let Some(reservation) = self.state.reservation.as_ref() else {
+            return;
+        };
+
+        let floor = self.start.add(reservation.page_floor(self.size));
+        let committed = self.committed.get();
+        if committed < floor {
+            sys::decommit(
+                committed,
+                floor.as_ptr() as usize - committed.as_ptr() as usize,
+            );
+            self.committed.set(floor);
+        }
+    }
+
     /// Free this chunk.
     pub fn free(&self) -> Result<(), FreeError> {
-        self.free_list.push(*self)
+        self.list.free(*self)
     }
 
     pub(crate) unsafe fn reset_bump(&self) {
         let reset_bump = unsafe { NonNull::new_unchecked(self.start.as_ptr().add(self.size)) };
         self.bump.set(reset_bump);
+
+        if self.backing == Backing::Reserved {
+            self.decommit_pages();
+        }
     }
 
     /// Deallocate this chunk and it's inner chunks
@@ -96,28 +427,50 @@ impl Chunk {
         let mut next_chunk = Some(self);
 
         while let Some(chunk) = next_chunk.take() {
-            println!("Dropping {}-{}", chunk.size, chunk.index);
-            println!(
-                "\tcurrent: {}; free: {}; refs: {}",
-                chunk.is_current(),
-                chunk.is_free(),
-                chunk.refs()
-            );
-            // Ensure there are zero references
-            assert!(
-                chunk.refs() == 0,
-                "attempting to deallocate a chunk that still has references"
-            );
+            #[cfg(feature = "debug-trace")]
+            {
+                println!("Dropping {}-{}", chunk.size, chunk.index);
+                println!(
+                    "\tcurrent: {}; free: {}; refs: {}",
+                    chunk.is_current(),
+                    chunk.is_free(),
+                    chunk.refs()
+                );
+            }
+            // Every handle borrows the arena, so any references
+            // left belong to leaked handles and can be ignored.
 
             // Set the next chunk
-            next_chunk = chunk.next;
+            next_chunk = chunk.next.get();
 
-            // Prepare for deallocation
-            let ptr = chunk.start.as_ptr();
-            let (layout, _) = Chunk::layout(chunk.size).expect("this should be impossible");
+            chunk.deallocate();
+        }
+    }
+
+    /// Deallocate only this chunk, leaving the chunks linked after it alone.
+    pub(crate) unsafe fn deallocate(self) {
+        Self::release_memory(self.start, self.memory_layout(), self.backing, self.state);
+    }
 
-            // Deallocate
-            alloc::dealloc(ptr, layout);
+    /// Give memory back to where it came from.
+    ///
+    /// The state is only reached for reserved chunks, the others
+    /// may outlive their arena, like detached ones.
+    unsafe fn release_memory(ptr: NonNull<u8>, layout: Layout, backing: Backing, state: StateRef) {
+        match backing {
+            Backing::MappedHuge => sys::unmap(ptr, layout),
+            #[cfg(all(feature = "numa", target_os = "linux"))]
+            Backing::Mapped => sys::unmap(ptr, layout),
+            Backing::Heap | Backing::HeapAdvised | Backing::Emergency => {
+                alloc::dealloc(ptr.as_ptr(), layout)
+            }
+            Backing::Reserved => {
+                let reservation = state.reservation.as_ref();
+                reservation
+                    .expect("reserved chunk without a reservation")
+                    .give_back(ptr, layout)
+            }
+            Backing::Inline | Backing::External => {}
         }
     }
 }
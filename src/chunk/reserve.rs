@@ -0,0 +1,195 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ptr::NonNull,
+};
+
+use super::sys;
+
+/// A contiguous range of address space that chunks are carved out of,
+/// see [`ArenaBuilder::reserve_address_space`](crate::ArenaBuilder::reserve_address_space).
+///
+/// Only the footer of a chunk is committed when it is carved, the pages below
+/// it are committed as the bump pointer reaches them, and decommitted again
+/// when the chunk is reset or deallocated.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+    start: NonNull<u8>,
+    len: usize,
+    page_size: usize,
+    /// Offset of the first byte that was never handed out
+    used: Cell<usize>,
+    /// Decommitted ranges of deallocated chunks, by their size
+    recycled: RefCell<HashMap<usize, Vec<NonNull<u8>>>>,
+}
+
+impl Reservation {
+    /// Reserve `len` bytes, rounded up to whole pages.
+    pub fn new(len: usize) -> Option<Self> {
+        let page_size = sys::page_size();
+        let len = len.checked_next_multiple_of(page_size)?;
+        let start = unsafe { sys::reserve(len)? };
+
+        Some(Self {
+            start,
+            len,
+            page_size,
+            used: Cell::new(0),
+            recycled: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Whole pages covering a chunk layout.
+    #[inline]
+    fn pages(&self, layout: Layout) -> usize {
+        layout.size().next_multiple_of(self.page_size)
+    }
+
+    /// Offset of the page holding the byte at `offset` into a chunk.
+    #[inline]
+    pub fn page_floor(&self, offset: usize) -> usize {
+        offset & !(self.page_size - 1)
+    }
+
+    /// Memory for a chunk, or `None` once the reservation is used up.
+    ///
+    /// Only the pages from the one holding the footer on are committed.
+    /// Chunks take whole pages, so chunks smaller than a page waste the rest of it.
+    pub unsafe fn carve(&self, layout: Layout, footer_offset: usize) -> Option<NonNull<u8>> {
+        let len = self.pages(layout);
+
+        let recycled = self
+            .recycled
+            .borrow_mut()
+            .get_mut(&len)
+            .and_then(|ranges| ranges.pop());
+        let ptr = match recycled {
+            Some(ptr) => ptr,
+            None => {
+                let base = self.start.as_ptr() as usize;
+                let align = layout.align().max(self.page_size);
+                let offset = (base + self.used.get()).checked_next_multiple_of(align)? - base;
+                let end = offset.checked_add(len)?;
+                if end > self.len {
+                    return None;
+                }
+
+                self.used.set(end);
+                NonNull::new_unchecked(self.start.as_ptr().add(offset))
+            }
+        };
+
+        let committed = self.page_floor(footer_offset);
+        if sys::commit(ptr.add(committed), len - committed) {
+            Some(ptr)
+        } else {
+            self.recycled.borrow_mut().entry(len).or_default().push(ptr);
+            None
+        }
+    }
+
+    /// Decommit the memory of a chunk from [`Reservation::carve`],
+    /// keeping its range for the next chunk of the same size.
+    pub unsafe fn give_back(&self, ptr: NonNull<u8>, layout: Layout) {
+        let len = self.pages(layout);
+        sys::decommit(ptr, len);
+
+        self.recycled.borrow_mut().entry(len).or_default().push(ptr);
+    }
+
+    /// Start of the reserved range.
+    #[cfg(any(test, feature = "bytemuck"))]
+    pub fn start(&self) -> NonNull<u8> {
+        self.start
+    }
+
+    /// Bytes of the reserved range that chunks were carved out of.
+    #[cfg(any(test, feature = "bytemuck"))]
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        unsafe { sys::release(self.start, self.len) }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reserved_chunks_commit_lazily() {
+    use crate::{Arena, HUGE_PAGE_SIZE};
+
+    let mut arena = Arena::builder().reserve_address_space(1 << 30).build();
+    let (start, page_size) = {
+        let reservation = arena.state.reservation.as_ref().unwrap();
+        (reservation.start().as_ptr(), reservation.page_size)
+    };
+    let resident = |arena: &Arena| {
+        let used = arena.state.reservation.as_ref().unwrap().used();
+        let mut pages = vec![0_u8; used.div_ceil(page_size)];
+        unsafe { libc::mincore(start.cast(), used, pages.as_mut_ptr()) };
+        pages.iter().filter(|&&page| page & 1 == 1).count() * page_size
+    };
+
+    let mut big = arena.alloc_uninit_slice::<u8>(32 << 20);
+    let chunk = big.as_ptr().chunk;
+    assert!(chunk.start.as_ptr() >= start);
+    assert!(chunk.size >= 32 << 20);
+
+    // Only the footer and the touched bytes are resident,
+    // give or take a transparent huge page at either end
+    let touched = 256 << 10;
+    for byte in &mut big[..touched] {
+        byte.write(1);
+    }
+    let slack = 2 * HUGE_PAGE_SIZE + 2 * page_size;
+    assert!(resident(&arena) >= touched);
+    assert!(resident(&arena) <= touched + slack);
+
+    // Chunks that were never bumped only have their footer committed
+    for other in chunk.list.chunks().filter(|&other| other != chunk) {
+        assert_eq!(other.committed.get(), unsafe {
+            other.start.add(other.size)
+        });
+    }
+
+    // Chunks of other classes come from the same range
+    let small = arena.alloc(0_u64);
+    let offset = small.as_raw() as usize - start as usize;
+    assert!(offset < arena.state.reservation.as_ref().unwrap().used());
+    drop(small);
+
+    // Bumping past the pages committed them, resetting the chunk
+    // decommits everything below its footer
+    let footer = unsafe { chunk.start.add(chunk.size) };
+    assert_eq!(chunk.committed.get(), chunk.start);
+    drop(big);
+    assert_eq!(chunk.committed.get(), footer);
+    let footers = arena.stats().chunks * page_size;
+    assert!(resident(&arena) <= footers + slack);
+
+    // The next allocation commits them again
+    let mut big = arena.alloc_uninit_slice::<u8>(32 << 20);
+    assert_eq!(big.as_ptr().chunk, chunk);
+    for byte in &mut big[..touched] {
+        byte.write(2);
+    }
+    assert!(chunk.committed.get() < footer);
+    assert!(resident(&arena) >= touched);
+    assert!(resident(&arena) <= touched + footers + slack);
+
+    drop(big);
+    arena.shrink_to_fit();
+    assert_eq!(resident(&arena), 0);
+
+    // The decommitted range is reused, and reads as zeroes
+    let used = arena.state.reservation.as_ref().unwrap().used();
+    let again = arena.alloc_slice_fill_copy(4, &1_u8);
+    assert_eq!(&*again, &[1; 4]);
+    let big = arena.alloc_uninit_slice::<u8>(32 << 20);
+    assert_eq!(big.as_ptr().chunk, chunk);
+    assert_eq!(arena.state.reservation.as_ref().unwrap().used(), used);
+}
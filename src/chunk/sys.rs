@@ -0,0 +1,406 @@
+//! Platform specific chunk memory.
+
+use std::{alloc::Layout, ptr::NonNull};
+
+/// Size of a huge page.
+pub const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// Size of a wasm memory page.
+#[cfg(target_arch = "wasm32")]
+pub(crate) const WASM_PAGE_SIZE: usize = 64 << 10;
+
+/// Alignment of the memory for a chunk of `size` bytes.
+///
+/// Wasm memory only grows in pages and never shrinks, so bigger chunks are
+/// only page aligned there. Aligning them to their size pads every chunk
+/// out to twice its size, and older allocators could not serve the alignment.
+#[cfg(target_arch = "wasm32")]
+pub(crate) const fn chunk_align(size: usize) -> usize {
+    let align = size_align(size);
+    if align > WASM_PAGE_SIZE {
+        WASM_PAGE_SIZE
+    } else {
+        align
+    }
+}
+
+/// Alignment of the memory for a chunk of `size` bytes.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const fn chunk_align(size: usize) -> usize {
+    size_align(size)
+}
+
+/// The biggest power of two up to `size`, which is `size` itself
+/// for the default size classes.
+const fn size_align(size: usize) -> usize {
+    1 << size.ilog2()
+}
+
+/// Map memory backed by explicit huge pages, aligned to the layout's alignment.
+#[cfg(target_os = "linux")]
+pub(crate) unsafe fn map_huge(layout: Layout) -> Option<NonNull<u8>> {
+    map_aligned(layout, libc::MAP_HUGETLB)
+}
+
+/// Map memory aligned to the layout's alignment, with extra `mmap` flags.
+#[cfg(target_os = "linux")]
+unsafe fn map_aligned(layout: Layout, flags: libc::c_int) -> Option<NonNull<u8>> {
+    // Over-reserve so the mapping can be trimmed to the alignment.
+    let len = layout.size().checked_add(layout.align())?;
+    let ptr = libc::mmap(
+        std::ptr::null_mut(),
+        len,
+        libc::PROT_READ | libc::PROT_WRITE,
+        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+        -1,
+        0,
+    );
+
+    if ptr == libc::MAP_FAILED {
+        return None;
+    }
+
+    let start = ptr as usize;
+    let aligned = (start + layout.align() - 1) & !(layout.align() - 1);
+    let head = aligned - start;
+    let tail = len - head - layout.size();
+
+    if head > 0 {
+        libc::munmap(ptr, head);
+    }
+    if tail > 0 {
+        libc::munmap((aligned + layout.size()) as *mut libc::c_void, tail);
+    }
+
+    NonNull::new(aligned as *mut u8)
+}
+
+/// Map memory backed by large pages, aligned to the layout's alignment.
+///
+/// Large pages cannot be trimmed, so an aligned address is found by reserving
+/// more than needed and releasing it again, retrying if it is taken in between.
+#[cfg(windows)]
+pub(crate) unsafe fn map_huge(layout: Layout) -> Option<NonNull<u8>> {
+    let page = win::GetLargePageMinimum();
+    if page == 0 || !layout.size().is_multiple_of(page) {
+        return None;
+    }
+
+    let align = layout.align().max(page);
+    let len = layout.size().checked_add(align)?;
+    for _ in 0..3 {
+        let probe = win::VirtualAlloc(
+            std::ptr::null_mut(),
+            len,
+            win::MEM_RESERVE,
+            win::PAGE_NOACCESS,
+        );
+        if probe.is_null() {
+            return None;
+        }
+        win::VirtualFree(probe, 0, win::MEM_RELEASE);
+
+        let aligned = (probe as usize).next_multiple_of(align);
+        let ptr = win::VirtualAlloc(
+            aligned as *mut _,
+            layout.size(),
+            win::MEM_RESERVE | win::MEM_COMMIT | win::MEM_LARGE_PAGES,
+            win::PAGE_READWRITE,
+        );
+        if let Some(ptr) = NonNull::new(ptr.cast()) {
+            return Some(ptr);
+        }
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) unsafe fn map_huge(_layout: Layout) -> Option<NonNull<u8>> {
+    None
+}
+
+/// Map memory aligned to the layout's alignment, with its pages bound to a NUMA node.
+///
+/// Fails when the node does not exist, or the pages could not be bound.
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub(crate) unsafe fn map_on_node(layout: Layout, node: u32, huge: bool) -> Option<NonNull<u8>> {
+    /// `MPOL_BIND` from `linux/mempolicy.h`
+    const MPOL_BIND: libc::c_int = 2;
+    /// Words in the node mask, enough for 1024 nodes
+    const MASK_WORDS: usize = 16;
+
+    let bits = libc::c_ulong::BITS as usize;
+    let node = node as usize;
+    if node >= MASK_WORDS * bits {
+        return None;
+    }
+
+    let start = map_aligned(layout, if huge { libc::MAP_HUGETLB } else { 0 })?;
+
+    let mut mask = [0 as libc::c_ulong; MASK_WORDS];
+    mask[node / bits] |= 1 << (node % bits);
+
+    // The pages are untouched, so binding decides where they land.
+    // The kernel reads one bit less than `maxnode`.
+    let bound = libc::syscall(
+        libc::SYS_mbind,
+        start.as_ptr(),
+        layout.size(),
+        MPOL_BIND,
+        mask.as_ptr(),
+        MASK_WORDS * bits + 1,
+        0,
+    ) == 0;
+    if !bound {
+        unmap(start, layout);
+        return None;
+    }
+
+    Some(start)
+}
+
+/// Unmap memory returned by [`map_huge`] or `map_on_node`.
+#[cfg(target_os = "linux")]
+pub(crate) unsafe fn unmap(ptr: NonNull<u8>, layout: Layout) {
+    libc::munmap(ptr.as_ptr().cast(), layout.size());
+}
+
+/// Unmap memory returned by [`map_huge`].
+#[cfg(windows)]
+pub(crate) unsafe fn unmap(ptr: NonNull<u8>, _layout: Layout) {
+    win::VirtualFree(ptr.as_ptr().cast(), 0, win::MEM_RELEASE);
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) unsafe fn unmap(_ptr: NonNull<u8>, _layout: Layout) {
+    unreachable!("memory is never mapped on this platform")
+}
+
+/// Ask for transparent huge pages, returning whether the advice was taken.
+#[cfg(target_os = "linux")]
+pub(crate) unsafe fn advise_huge(ptr: NonNull<u8>, len: usize) -> bool {
+    libc::madvise(ptr.as_ptr().cast(), len, libc::MADV_HUGEPAGE) == 0
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) unsafe fn advise_huge(_ptr: NonNull<u8>, _len: usize) -> bool {
+    false
+}
+
+/// Granularity of committing and decommitting reserved memory.
+#[cfg(unix)]
+pub(crate) fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Granularity of committing and decommitting reserved memory.
+#[cfg(not(unix))]
+pub(crate) fn page_size() -> usize {
+    4096
+}
+
+#[cfg(windows)]
+mod win {
+    use std::ffi::c_void;
+
+    pub const MEM_COMMIT: u32 = 0x1000;
+    pub const MEM_RESERVE: u32 = 0x2000;
+    pub const MEM_DECOMMIT: u32 = 0x4000;
+    pub const MEM_RELEASE: u32 = 0x8000;
+    pub const MEM_LARGE_PAGES: u32 = 0x2000_0000;
+    pub const PAGE_NOACCESS: u32 = 0x01;
+    pub const PAGE_READWRITE: u32 = 0x04;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn VirtualAlloc(
+            address: *mut c_void,
+            size: usize,
+            allocation_type: u32,
+            protect: u32,
+        ) -> *mut c_void;
+        pub fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+        pub fn GetLargePageMinimum() -> usize;
+        pub fn VirtualProtect(
+            address: *mut c_void,
+            size: usize,
+            new_protect: u32,
+            old_protect: *mut u32,
+        ) -> i32;
+        #[cfg(feature = "madvise")]
+        pub fn DiscardVirtualMemory(address: *mut c_void, size: usize) -> u32;
+    }
+}
+
+/// Reserve `len` bytes of address space without any memory behind it.
+#[cfg(unix)]
+pub(crate) unsafe fn reserve(len: usize) -> Option<NonNull<u8>> {
+    let ptr = libc::mmap(
+        std::ptr::null_mut(),
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+        -1,
+        0,
+    );
+
+    if ptr == libc::MAP_FAILED {
+        return None;
+    }
+    NonNull::new(ptr.cast())
+}
+
+#[cfg(windows)]
+pub(crate) unsafe fn reserve(len: usize) -> Option<NonNull<u8>> {
+    let ptr = win::VirtualAlloc(
+        std::ptr::null_mut(),
+        len,
+        win::MEM_RESERVE,
+        win::PAGE_NOACCESS,
+    );
+    NonNull::new(ptr.cast())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) unsafe fn reserve(_len: usize) -> Option<NonNull<u8>> {
+    None
+}
+
+/// Make reserved pages usable. They only take up memory once touched.
+#[cfg(unix)]
+pub(crate) unsafe fn commit(ptr: NonNull<u8>, len: usize) -> bool {
+    libc::mprotect(ptr.as_ptr().cast(), len, libc::PROT_READ | libc::PROT_WRITE) == 0
+}
+
+#[cfg(windows)]
+pub(crate) unsafe fn commit(ptr: NonNull<u8>, len: usize) -> bool {
+    !win::VirtualAlloc(
+        ptr.as_ptr().cast(),
+        len,
+        win::MEM_COMMIT,
+        win::PAGE_READWRITE,
+    )
+    .is_null()
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) unsafe fn commit(_ptr: NonNull<u8>, _len: usize) -> bool {
+    unreachable!("memory is never reserved on this platform")
+}
+
+/// Drop the memory behind committed pages, leaving them reserved.
+#[cfg(unix)]
+pub(crate) unsafe fn decommit(ptr: NonNull<u8>, len: usize) {
+    // Mapping over the range replaces the pages with fresh inaccessible ones
+    libc::mmap(
+        ptr.as_ptr().cast(),
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+        -1,
+        0,
+    );
+}
+
+#[cfg(windows)]
+pub(crate) unsafe fn decommit(ptr: NonNull<u8>, len: usize) {
+    win::VirtualFree(ptr.as_ptr().cast(), len, win::MEM_DECOMMIT);
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) unsafe fn decommit(_ptr: NonNull<u8>, _len: usize) {
+    unreachable!("memory is never reserved on this platform")
+}
+
+/// Give back address space from [`reserve`].
+#[cfg(unix)]
+pub(crate) unsafe fn release(ptr: NonNull<u8>, len: usize) {
+    libc::munmap(ptr.as_ptr().cast(), len);
+}
+
+#[cfg(windows)]
+pub(crate) unsafe fn release(ptr: NonNull<u8>, _len: usize) {
+    win::VirtualFree(ptr.as_ptr().cast(), 0, win::MEM_RELEASE);
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) unsafe fn release(_ptr: NonNull<u8>, _len: usize) {
+    unreachable!("memory is never reserved on this platform")
+}
+
+/// Make committed pages accessible or not, keeping their memory.
+#[cfg(all(feature = "quarantine", unix))]
+pub(crate) unsafe fn protect(ptr: NonNull<u8>, len: usize, accessible: bool) {
+    let protection = if accessible {
+        libc::PROT_READ | libc::PROT_WRITE
+    } else {
+        libc::PROT_NONE
+    };
+    libc::mprotect(ptr.as_ptr().cast(), len, protection);
+}
+
+#[cfg(all(feature = "quarantine", windows))]
+pub(crate) unsafe fn protect(ptr: NonNull<u8>, len: usize, accessible: bool) {
+    let protection = if accessible {
+        win::PAGE_READWRITE
+    } else {
+        win::PAGE_NOACCESS
+    };
+    let mut old = 0;
+    win::VirtualProtect(ptr.as_ptr().cast(), len, protection, &mut old);
+}
+
+#[cfg(all(feature = "quarantine", not(any(unix, windows))))]
+pub(crate) unsafe fn protect(_ptr: NonNull<u8>, _len: usize, _accessible: bool) {
+    unreachable!("memory is never reserved on this platform")
+}
+
+/// The whole pages within `len` bytes at `ptr`, as their start and length.
+#[cfg(all(feature = "madvise", any(unix, windows)))]
+fn inner_pages(ptr: NonNull<u8>, len: usize) -> Option<(*mut u8, usize)> {
+    let page_size = page_size();
+    let start = (ptr.as_ptr() as usize).next_multiple_of(page_size);
+    let end = (ptr.as_ptr() as usize + len) & !(page_size - 1);
+
+    (end > start).then(|| (start as *mut u8, end - start))
+}
+
+/// Drop the memory behind the whole pages within `len` bytes at `ptr`,
+/// keeping them mapped, and return how many bytes that covered.
+///
+/// The pages read as zeroes, or their old contents on Windows, once touched again.
+#[cfg(all(feature = "madvise", unix))]
+pub(crate) unsafe fn discard(ptr: NonNull<u8>, len: usize) -> usize {
+    match inner_pages(ptr, len) {
+        Some((start, len)) if libc::madvise(start.cast(), len, libc::MADV_DONTNEED) == 0 => len,
+        _ => 0,
+    }
+}
+
+#[cfg(all(feature = "madvise", windows))]
+pub(crate) unsafe fn discard(ptr: NonNull<u8>, len: usize) -> usize {
+    match inner_pages(ptr, len) {
+        Some((start, len)) if win::DiscardVirtualMemory(start.cast(), len) == 0 => len,
+        _ => 0,
+    }
+}
+
+#[cfg(all(feature = "madvise", not(any(unix, windows))))]
+pub(crate) unsafe fn discard(_ptr: NonNull<u8>, _len: usize) -> usize {
+    0
+}
+
+/// How many bytes of the pages covering `len` bytes at `ptr` are resident.
+#[cfg(all(feature = "resident", unix))]
+pub(crate) unsafe fn resident(ptr: NonNull<u8>, len: usize) -> usize {
+    let page_size = page_size();
+    let start = ptr.as_ptr() as usize & !(page_size - 1);
+    let end = (ptr.as_ptr() as usize + len).next_multiple_of(page_size);
+
+    let mut pages = vec![0; (end - start) / page_size];
+    if libc::mincore(start as *mut _, end - start, pages.as_mut_ptr()) != 0 {
+        return 0;
+    }
+
+    pages.iter().filter(|&&page| page & 1 == 1).count() * page_size
+}
@@ -0,0 +1,198 @@
+use crate::{ptr::Boxed, Arena};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A fixed size set of bits, stored in the arena.
+pub struct ArenaBitSet<'arena> {
+    words: Boxed<'arena, [u64]>,
+    len: usize,
+}
+
+impl<'arena> ArenaBitSet<'arena> {
+    /// Create a set of `nbits` cleared bits.
+    #[track_caller]
+    pub fn new_in(arena: &'arena Arena, nbits: usize) -> Self {
+        Self {
+            words: arena.alloc_slice_fill_copy(nbits.div_ceil(WORD_BITS), &0),
+            len: nbits,
+        }
+    }
+
+    /// How many bits there are.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if there are no bits at all.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The words holding the bits, lowest bits first.
+    #[inline]
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+
+    #[inline]
+    #[track_caller]
+    fn locate(&self, index: usize) -> (usize, u64) {
+        assert!(
+            index < self.len,
+            "bit {index} is out of range for a set of {} bits",
+            self.len
+        );
+
+        (index / WORD_BITS, 1 << (index % WORD_BITS))
+    }
+
+    /// Set a bit.
+    #[inline]
+    #[track_caller]
+    pub fn set(&mut self, index: usize) {
+        let (word, mask) = self.locate(index);
+        self.words[word] |= mask;
+    }
+
+    /// Clear a bit.
+    #[inline]
+    #[track_caller]
+    pub fn clear(&mut self, index: usize) {
+        let (word, mask) = self.locate(index);
+        self.words[word] &= !mask;
+    }
+
+    /// Checks if a bit is set.
+    #[inline]
+    #[track_caller]
+    pub fn test(&self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        self.words[word] & mask != 0
+    }
+
+    /// How many bits are set.
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Clear the unused bits of the last word.
+    fn trim(&mut self) {
+        let used = self.len % WORD_BITS;
+        if let (Some(last), true) = (self.words.last_mut(), used != 0) {
+            *last &= (1 << used) - 1;
+        }
+    }
+
+    /// Set every bit that is set in `other`.
+    ///
+    /// Bits of `other` beyond the length of this set are ignored.
+    pub fn union_with(&mut self, other: &ArenaBitSet<'_>) {
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other;
+        }
+        self.trim();
+    }
+
+    /// Clear every bit that is not set in `other`.
+    pub fn intersect_with(&mut self, other: &ArenaBitSet<'_>) {
+        let shared = self.words.len().min(other.words.len());
+        for (word, other) in self.words[..shared].iter_mut().zip(other.words.iter()) {
+            *word &= other;
+        }
+        self.words[shared..].fill(0);
+    }
+
+    /// Iterate over the indices of the set bits, in ascending order.
+    pub fn iter(&self) -> Ones<'_> {
+        Ones {
+            words: &self.words,
+            word: 0,
+            bits: self.words.first().copied().unwrap_or(0),
+        }
+    }
+
+    /// Make room for `new_nbits` bits, the new bits are cleared.
+    ///
+    /// The words grow in place when they were the last allocation of their chunk,
+    /// otherwise they are copied into a bigger block.
+    #[track_caller]
+    pub fn grow(&mut self, arena: &'arena Arena, new_nbits: usize) {
+        if new_nbits <= self.len {
+            return;
+        }
+
+        let needed = new_nbits.div_ceil(WORD_BITS);
+        let additional = needed - self.words.len();
+        if additional > 0 && !self.words.try_grow_in_place(additional, arena) {
+            let mut words = arena.alloc_slice_fill_copy(needed, &0);
+            words[..self.words.len()].copy_from_slice(&self.words);
+            self.words = words;
+        }
+
+        self.len = new_nbits;
+    }
+}
+
+impl<'a, 'arena> IntoIterator for &'a ArenaBitSet<'arena> {
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Indices of the set bits of an [`ArenaBitSet`].
+pub struct Ones<'a> {
+    words: &'a [u64],
+    /// Index of the word being scanned
+    word: usize,
+    /// Bits of that word that were not returned yet
+    bits: u64,
+}
+
+impl<'a> Iterator for Ones<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            self.word += 1;
+            self.bits = *self.words.get(self.word)?;
+        }
+
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(self.word * WORD_BITS + bit)
+    }
+}
+
+#[test]
+fn bitset_ops() {
+    let arena = Arena::new();
+    let mut alive = ArenaBitSet::new_in(&arena, 130);
+    for index in [0, 3, 64, 129] {
+        alive.set(index);
+    }
+    alive.clear(3);
+    assert!(alive.test(64) && !alive.test(3));
+    assert_eq!(alive.iter().collect::<Vec<_>>(), [0, 64, 129]);
+
+    let mut moving = ArenaBitSet::new_in(&arena, 70);
+    moving.set(64);
+    moving.set(1);
+    moving.union_with(&alive);
+    assert_eq!(moving.iter().collect::<Vec<_>>(), [0, 1, 64]);
+
+    alive.intersect_with(&moving);
+    assert_eq!(alive.count_ones(), 2);
+
+    alive.grow(&arena, 300);
+    alive.set(299);
+    assert_eq!(alive.len(), 300);
+    assert_eq!(alive.iter().collect::<Vec<_>>(), [0, 64, 299]);
+}
@@ -0,0 +1,209 @@
+use std::mem::MaybeUninit;
+
+use crate::{ptr::Boxed, Arena};
+
+/// Collects values into an arena slice, through [`Extend`].
+///
+/// Values are buffered in an arena allocation that doubles when it fills up.
+/// [`ArenaCollector::finish`] turns the buffer into the final slice without copying.
+/// Values that were collected but never finished are dropped with the collector.
+pub struct ArenaCollector<'chunk, T> {
+    arena: &'chunk Arena,
+    buf: Option<Boxed<'chunk, [MaybeUninit<T>]>>,
+    len: usize,
+}
+
+impl<'chunk, T> ArenaCollector<'chunk, T> {
+    /// Create an empty collector.
+    pub fn new(arena: &'chunk Arena) -> Self {
+        Self {
+            arena,
+            buf: None,
+            len: 0,
+        }
+    }
+
+    /// How many values were collected.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if no values were collected.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many values fit before the buffer has to grow.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.as_ref().map_or(0, |buf| buf.len())
+    }
+
+    /// The values collected so far.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { &*core::ptr::slice_from_raw_parts(self.buffer_ptr(), self.len) }
+    }
+
+    /// The values collected so far, mutably.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { &mut *core::ptr::slice_from_raw_parts_mut(self.buffer_ptr(), self.len) }
+    }
+
+    /// Start of the buffer, dangling if there is none yet.
+    #[inline]
+    pub(crate) fn buffer_ptr(&self) -> *mut T {
+        self.buf
+            .as_ref()
+            .map_or(core::ptr::NonNull::dangling().as_ptr(), |buf| {
+                buf.as_raw().cast()
+            })
+    }
+
+    /// Set the length, without dropping or initializing anything.
+    ///
+    /// # Safety
+    ///
+    /// `len` must not exceed the capacity, and the first `len` values
+    /// must be initialized.
+    #[inline]
+    pub unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.capacity());
+        self.len = len;
+    }
+
+    /// The uninitialized part of the buffer, past the collected values.
+    #[inline]
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        match &mut self.buf {
+            Some(buf) => &mut buf[self.len..],
+            None => &mut [],
+        }
+    }
+
+    /// Make sure `additional` more values fit without growing.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed <= self.capacity() {
+            return;
+        }
+
+        let capacity = needed.max(self.capacity() * 2).max(4);
+        let buf = self.arena.alloc_uninit_slice::<T>(capacity);
+
+        if let Some(old) = &self.buf {
+            // Move the collected values, the old buffer never drops them
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    old.as_raw().cast::<T>(),
+                    buf.as_raw().cast(),
+                    self.len,
+                );
+            }
+        }
+
+        self.buf = Some(buf);
+    }
+
+    /// Add a value.
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+
+        if let Some(buf) = &mut self.buf {
+            buf[self.len].write(value);
+            self.len += 1;
+        }
+    }
+
+    /// Turn the collected values into a slice.
+    pub fn finish(mut self) -> Boxed<'chunk, [T]> {
+        let len = std::mem::take(&mut self.len);
+
+        match self.buf.take() {
+            Some(buf) => unsafe {
+                let values = buf.assume_init_prefix(len);
+                self.arena
+                    .register_drop(values.as_ptr().cast::<T>(), len, values.as_ptr());
+                values
+            },
+            None => self.arena.alloc_slice_fill_with(0, |_| unreachable!()),
+        }
+    }
+}
+
+impl<'chunk, T> Extend<T> for ArenaCollector<'chunk, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, 'chunk, T: Copy + 'a> Extend<&'a T> for ArenaCollector<'chunk, T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+impl<'chunk, T> Drop for ArenaCollector<'chunk, T> {
+    fn drop(&mut self) {
+        if let Some(buf) = &self.buf {
+            unsafe {
+                let values =
+                    core::ptr::slice_from_raw_parts_mut(buf.as_raw().cast::<T>(), self.len);
+                core::ptr::drop_in_place(values);
+            }
+        }
+    }
+}
+
+impl Arena {
+    /// Create a collector that gathers values into a slice in this arena.
+    pub fn collector<T>(&self) -> ArenaCollector<'_, T> {
+        ArenaCollector::new(self)
+    }
+}
+
+#[test]
+fn collect_and_drop() {
+    use std::rc::Rc;
+
+    let arena = Arena::new();
+    let mut collector = arena.collector();
+    collector.extend((0..100).map(|i| i * 2));
+    for i in 100..110 {
+        collector.push(i * 2);
+    }
+
+    let slice = collector.finish();
+    assert_eq!(slice.len(), 110);
+    assert!(slice.iter().enumerate().all(|(i, &v)| v == i * 2));
+
+    // Unfinished collectors drop what they hold
+    let counter = Rc::new(());
+    let mut collector = arena.collector();
+    collector.extend((0..10).map(|_| counter.clone()));
+    drop(collector);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn finished_slices_drop_on_teardown() {
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    let mut collector = arena.collector();
+    collector.extend((0..10).map(|_| counter.clone()));
+    collector.finish().leak();
+    assert_eq!(Rc::strong_count(&counter), 11);
+
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
@@ -0,0 +1,230 @@
+use std::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+use super::ArenaCollector;
+use crate::Arena;
+
+enum Storage<'arena, T, const N: usize> {
+    Inline([MaybeUninit<T>; N]),
+    Spilled(ArenaCollector<'arena, T>),
+}
+
+/// Holds up to `N` values inline, moving them into the arena once there are more.
+pub struct InlineOrArena<'arena, T, const N: usize> {
+    arena: &'arena Arena,
+    /// How many values are inline, the spilled values keep their own count
+    len: usize,
+    storage: Storage<'arena, T, N>,
+}
+
+impl<'arena, T, const N: usize> InlineOrArena<'arena, T, N> {
+    /// Create an empty container, spilling into `arena`.
+    pub fn new(arena: &'arena Arena) -> Self {
+        Self {
+            arena,
+            len: 0,
+            storage: Storage::Inline([const { MaybeUninit::uninit() }; N]),
+        }
+    }
+
+    /// Create a container holding the values of an iterator.
+    pub fn from_iter_in(arena: &'arena Arena, iter: impl IntoIterator<Item = T>) -> Self {
+        let mut values = Self::new(arena);
+        values.extend(iter);
+        values
+    }
+
+    /// Checks if the values moved into the arena.
+    #[inline]
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.storage, Storage::Spilled(_))
+    }
+
+    /// Add a value, spilling into the arena when the inline space is full.
+    pub fn push(&mut self, value: T) {
+        match &mut self.storage {
+            Storage::Inline(values) if self.len < N => {
+                values[self.len].write(value);
+                self.len += 1;
+            }
+            Storage::Inline(values) => {
+                let mut spilled = ArenaCollector::new(self.arena);
+                spilled.reserve((N * 2).max(4));
+
+                // Move the inline values out, they are now owned by the collector
+                let len = std::mem::take(&mut self.len);
+                spilled.extend(
+                    values[..len]
+                        .iter()
+                        .map(|value| unsafe { value.assume_init_read() }),
+                );
+                spilled.push(value);
+
+                self.storage = Storage::Spilled(spilled);
+            }
+            Storage::Spilled(spilled) => spilled.push(value),
+        }
+    }
+
+    /// Start of the values.
+    #[inline]
+    fn as_ptr(&self) -> *mut T {
+        match &self.storage {
+            Storage::Inline(values) => values.as_ptr().cast_mut().cast(),
+            Storage::Spilled(spilled) => spilled.buffer_ptr(),
+        }
+    }
+
+    /// Forget about every value without dropping them.
+    unsafe fn forget_values(&mut self) {
+        match &mut self.storage {
+            Storage::Inline(_) => self.len = 0,
+            Storage::Spilled(spilled) => spilled.set_len(0),
+        }
+    }
+}
+
+impl<'arena, T, const N: usize> Deref for InlineOrArena<'arena, T, N> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match &self.storage {
+            Storage::Inline(values) => unsafe {
+                &*(ptr::addr_of!(values[..self.len]) as *const [T])
+            },
+            Storage::Spilled(spilled) => spilled.as_slice(),
+        }
+    }
+}
+
+impl<'arena, T, const N: usize> DerefMut for InlineOrArena<'arena, T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.storage {
+            Storage::Inline(values) => unsafe {
+                &mut *(ptr::addr_of_mut!(values[..self.len]) as *mut [T])
+            },
+            Storage::Spilled(spilled) => spilled.as_mut_slice(),
+        }
+    }
+}
+
+impl<'arena, T, const N: usize> Extend<T> for InlineOrArena<'arena, T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'arena, T, const N: usize> Drop for InlineOrArena<'arena, T, N> {
+    fn drop(&mut self) {
+        // Spilled values are dropped by the collector
+        if let Storage::Inline(values) = &mut self.storage {
+            unsafe { ptr::drop_in_place(ptr::addr_of_mut!(values[..self.len]) as *mut [T]) }
+        }
+    }
+}
+
+/// Moves the values out of an [`InlineOrArena`].
+pub struct InlineIntoIter<'arena, T, const N: usize> {
+    values: InlineOrArena<'arena, T, N>,
+    index: usize,
+    len: usize,
+}
+
+impl<'arena, T, const N: usize> Iterator for InlineIntoIter<'arena, T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.index == self.len {
+            return None;
+        }
+
+        let value = unsafe { self.values.as_ptr().add(self.index).read() };
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'arena, T, const N: usize> Drop for InlineIntoIter<'arena, T, N> {
+    fn drop(&mut self) {
+        unsafe {
+            let rest = self.values.as_ptr().add(self.index);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(rest, self.len - self.index));
+        }
+    }
+}
+
+impl<'arena, T, const N: usize> IntoIterator for InlineOrArena<'arena, T, N> {
+    type Item = T;
+    type IntoIter = InlineIntoIter<'arena, T, N>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let len = self.len();
+        // The iterator owns the values from now on
+        unsafe { self.forget_values() };
+
+        InlineIntoIter {
+            values: self,
+            index: 0,
+            len,
+        }
+    }
+}
+
+impl<'a, 'arena, T, const N: usize> IntoIterator for &'a InlineOrArena<'arena, T, N> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'arena, T, const N: usize> IntoIterator for &'a mut InlineOrArena<'arena, T, N> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[test]
+fn inline_then_spill() {
+    use std::rc::Rc;
+
+    let arena = Arena::new();
+    let mut values = InlineOrArena::<u32, 3>::from_iter_in(&arena, 0..3);
+    assert!(!values.is_spilled());
+    assert_eq!(&*values, &[0, 1, 2]);
+
+    values.push(3);
+    values.extend(4..10);
+    assert!(values.is_spilled());
+    assert_eq!(values.iter().sum::<u32>(), 45);
+    assert_eq!(
+        values.into_iter().collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+
+    // Both representations drop their values, even when partly iterated
+    let counter = Rc::new(());
+    let inline = InlineOrArena::<_, 4>::from_iter_in(&arena, (0..2).map(|_| counter.clone()));
+    let spilled = InlineOrArena::<_, 4>::from_iter_in(&arena, (0..6).map(|_| counter.clone()));
+    let mut iter = spilled.into_iter();
+    iter.next();
+    assert_eq!(Rc::strong_count(&counter), 8);
+    drop((inline, iter));
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
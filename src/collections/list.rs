@@ -0,0 +1,237 @@
+use std::{cell::Cell, fmt::Debug, marker::PhantomData, ops::Deref};
+
+use crate::{
+    ptr::{Boxed, Ptr},
+    Arena,
+};
+
+/// A node of a [`List`].
+///
+/// Nodes live in the arena, so their addresses are stable
+/// for as long as they are linked into the list.
+pub struct Node<T> {
+    value: T,
+    prev: Cell<Option<Ptr<Node<T>>>>,
+    next: Cell<Option<Ptr<Node<T>>>>,
+}
+
+impl<T> Node<T> {
+    /// Get the value stored in this node.
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Get the previous node in the list.
+    #[inline]
+    pub fn prev(&self) -> Option<&Node<T>> {
+        self.prev.get().map(|ptr| unsafe { &*ptr.as_raw() })
+    }
+
+    /// Get the next node in the list.
+    #[inline]
+    pub fn next(&self) -> Option<&Node<T>> {
+        self.next.get().map(|ptr| unsafe { &*ptr.as_raw() })
+    }
+}
+
+impl<T> Deref for Node<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Debug> Debug for Node<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// An intrusive doubly linked list whose nodes are allocated in an [`Arena`].
+///
+/// Every node holds a reference to its chunk, which is released
+/// once the node is removed or the list is dropped.
+pub struct List<'chunk, T> {
+    head: Cell<Option<Ptr<Node<T>>>>,
+    tail: Cell<Option<Ptr<Node<T>>>>,
+    len: Cell<usize>,
+    _marker: PhantomData<Boxed<'chunk, Node<T>>>,
+}
+
+impl<'chunk, T> List<'chunk, T> {
+    /// Create an empty list.
+    pub const fn new() -> Self {
+        Self {
+            head: Cell::new(None),
+            tail: Cell::new(None),
+            len: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of nodes in the list.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Checks if the list has no nodes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the first node.
+    #[inline]
+    pub fn front(&self) -> Option<&Node<T>> {
+        self.head.get().map(|ptr| unsafe { &*ptr.as_raw() })
+    }
+
+    /// Get the last node.
+    #[inline]
+    pub fn back(&self) -> Option<&Node<T>> {
+        self.tail.get().map(|ptr| unsafe { &*ptr.as_raw() })
+    }
+
+    /// Allocate a node in the arena and link it to the back of the list.
+    pub fn push(&self, arena: &'chunk Arena, value: T) -> &Node<T> {
+        let tail = self.tail.get();
+        let node = arena.alloc(Node {
+            value,
+            prev: Cell::new(tail),
+            next: Cell::new(None),
+        });
+        let ptr = node.into_ptr();
+
+        match tail {
+            Some(tail) => unsafe { tail.deref().next.set(Some(ptr)) },
+            None => self.head.set(Some(ptr)),
+        }
+
+        self.tail.set(Some(ptr));
+        self.len.set(self.len() + 1);
+
+        unsafe { &*ptr.as_raw() }
+    }
+
+    /// Unlink a node from the list, returning its value.
+    ///
+    /// Returns `None` if the node is not part of this list.
+    /// Membership is checked by walking the list.
+    pub fn remove(&mut self, node: *const Node<T>) -> Option<T> {
+        let ptr = self.find(node)?;
+        let (prev, next) = unsafe {
+            let node = ptr.deref();
+            (node.prev.get(), node.next.get())
+        };
+
+        // Adjust the neighbors to point at each other
+        match prev {
+            Some(prev) => unsafe { prev.deref().next.set(next) },
+            None => self.head.set(next),
+        }
+        match next {
+            Some(next) => unsafe { next.deref().prev.set(prev) },
+            None => self.tail.set(prev),
+        }
+        self.len.set(self.len() - 1);
+
+        unsafe {
+            // Move the value out, then release the chunk
+            // reference without dropping the node again.
+            let value = core::ptr::read(&ptr.deref().value);
+            drop(Boxed::from_ptr(ptr).into_mut());
+
+            Some(value)
+        }
+    }
+
+    /// Iterate over the values in the list.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.front() }
+    }
+
+    fn find(&self, node: *const Node<T>) -> Option<Ptr<Node<T>>> {
+        let mut current = self.head.get();
+
+        while let Some(ptr) = current {
+            if core::ptr::eq(ptr.as_raw(), node) {
+                return Some(ptr);
+            }
+            current = unsafe { ptr.deref().next.get() };
+        }
+
+        None
+    }
+}
+
+impl<'chunk, T> Default for List<'chunk, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'chunk, T: Debug> Debug for List<'chunk, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'chunk, T> Drop for List<'chunk, T> {
+    fn drop(&mut self) {
+        let mut current = self.head.take();
+        self.tail.set(None);
+
+        while let Some(ptr) = current {
+            unsafe {
+                current = ptr.deref().next.get();
+                drop(Boxed::from_ptr(ptr));
+            }
+        }
+    }
+}
+
+impl<'a, 'chunk, T> IntoIterator for &'a List<'chunk, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the values of a [`List`].
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.next();
+
+        Some(node.value())
+    }
+}
+
+#[test]
+fn remove_middle() {
+    let arena = Arena::new();
+    let mut list = List::new();
+
+    let nodes: Vec<_> = (0..5)
+        .map(|i| list.push(&arena, i) as *const Node<i32>)
+        .collect();
+    let middle = nodes[2];
+
+    assert_eq!(list.remove(middle), Some(2));
+    assert_eq!(list.remove(middle), None);
+    assert_eq!(list.len(), 4);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), [0, 1, 3, 4]);
+    assert_eq!(list.back().and_then(Node::prev).map(|n| **n), Some(3));
+}
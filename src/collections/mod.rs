@@ -0,0 +1,11 @@
+mod bitset;
+mod collector;
+mod inline;
+mod list;
+mod slice_builder;
+
+pub use bitset::*;
+pub use collector::*;
+pub use inline::*;
+pub use list::*;
+pub use slice_builder::*;
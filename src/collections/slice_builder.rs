@@ -0,0 +1,149 @@
+use std::mem::MaybeUninit;
+
+use crate::{ptr::Boxed, Arena};
+
+/// Fills a fixed length arena slice front to back.
+///
+/// [`SliceBuilder::finish`] fills the slots that were never pushed with
+/// [`Default::default`]. A builder dropped without finishing only drops
+/// the values pushed so far.
+pub struct SliceBuilder<'chunk, T> {
+    arena: &'chunk Arena,
+    buf: Option<Boxed<'chunk, [MaybeUninit<T>]>>,
+    filled: usize,
+}
+
+impl<'chunk, T> SliceBuilder<'chunk, T> {
+    /// Length of the finished slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buf.as_ref().map_or(0, |buf| buf.len())
+    }
+
+    /// Checks if the finished slice is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many values were pushed.
+    #[inline]
+    pub fn filled(&self) -> usize {
+        self.filled
+    }
+
+    /// Checks if every slot was pushed.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.filled == self.len()
+    }
+
+    /// The values pushed so far.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        match &self.buf {
+            Some(buf) => unsafe {
+                &*core::ptr::slice_from_raw_parts(buf.as_raw().cast::<T>(), self.filled)
+            },
+            None => &[],
+        }
+    }
+
+    /// Fill the next slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every slot was already pushed.
+    #[track_caller]
+    pub fn push(&mut self, value: T) {
+        assert!(!self.is_full(), "slice builder is full");
+
+        if let Some(buf) = &mut self.buf {
+            buf[self.filled].write(value);
+            self.filled += 1;
+        }
+    }
+
+    /// Fill the remaining slots with defaults and turn them into a slice.
+    pub fn finish(mut self) -> Boxed<'chunk, [T]>
+    where
+        T: Default,
+    {
+        // A panicking default leaves the builder to drop what was filled
+        while !self.is_full() {
+            self.push(T::default());
+        }
+
+        let buf = self.buf.take().expect("slice builder without a buffer");
+        let len = std::mem::take(&mut self.filled);
+
+        unsafe {
+            let values = buf.assume_init_prefix(len);
+            self.arena
+                .register_drop(values.as_ptr().cast::<T>(), len, values.as_ptr());
+            values
+        }
+    }
+}
+
+impl<'chunk, T> Drop for SliceBuilder<'chunk, T> {
+    fn drop(&mut self) {
+        if let Some(buf) = &self.buf {
+            unsafe {
+                let values =
+                    core::ptr::slice_from_raw_parts_mut(buf.as_raw().cast::<T>(), self.filled);
+                core::ptr::drop_in_place(values);
+            }
+        }
+    }
+}
+
+impl Arena {
+    /// Start building a slice of `len` values, see [`SliceBuilder`].
+    #[track_caller]
+    pub fn alloc_slice_builder<T: Default>(&self, len: usize) -> SliceBuilder<'_, T> {
+        SliceBuilder {
+            arena: self,
+            buf: Some(self.alloc_uninit_slice(len)),
+            filled: 0,
+        }
+    }
+}
+
+#[test]
+fn partial_builders() {
+    use std::rc::Rc;
+
+    let arena = Arena::new();
+    let mut builder = arena.alloc_slice_builder::<u32>(5);
+    for value in [7, 8, 9] {
+        builder.push(value);
+    }
+    assert_eq!(builder.as_slice(), &[7, 8, 9]);
+    assert_eq!(&*builder.finish(), &[7, 8, 9, 0, 0]);
+
+    // Unfinished builders drop only the pushed values
+    let counter = Rc::new(());
+    let mut builder = arena.alloc_slice_builder::<Option<Rc<()>>>(4);
+    builder.push(Some(counter.clone()));
+    builder.push(Some(counter.clone()));
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop(builder);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn built_slices_drop_on_teardown() {
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    let mut builder = arena.alloc_slice_builder::<Option<Rc<()>>>(4);
+    builder.push(Some(counter.clone()));
+    builder.push(Some(counter.clone()));
+    builder.finish().leak();
+    assert_eq!(Rc::strong_count(&counter), 3);
+
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
@@ -0,0 +1,274 @@
+//! Deserializing straight into an arena.
+//!
+//! Types implement [`DeserializeIn`], usually through its derive macro,
+//! and are deserialized with an [`InArena`] seed, or [`DeserializeIn::deserialize_in`].
+//! Strings become [`Boxed<str>`] and sequences [`Boxed<[T]>`](Boxed) in the arena.
+//!
+//! The derive works on structs and enums with at most one lifetime, which is
+//! taken to be the arena's. Fields and variants can be renamed with
+//! `#[arena(rename = "name")]`. Unknown fields are skipped, missing ones are an error.
+
+use std::{fmt, marker::PhantomData};
+
+pub use battle_arena_derive::DeserializeIn;
+#[doc(hidden)]
+pub use serde;
+use serde::de::{DeserializeSeed, Deserializer, Error, SeqAccess, Visitor};
+
+use crate::{ptr::Boxed, Arena};
+
+/// Types that can be deserialized with their contents in an arena.
+pub trait DeserializeIn<'arena>: Sized {
+    /// Deserialize a value, allocating its contents in `arena`.
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error>;
+}
+
+/// Deserializes a `T` in an arena, as a [`DeserializeSeed`].
+pub struct InArena<'arena, T> {
+    arena: &'arena Arena,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'arena, T> InArena<'arena, T> {
+    /// Create a seed allocating in `arena`.
+    #[inline]
+    pub fn new(arena: &'arena Arena) -> Self {
+        Self {
+            arena,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'arena, T> Clone for InArena<'arena, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'arena, T> Copy for InArena<'arena, T> {}
+
+impl<'de, 'arena, T: DeserializeIn<'arena>> DeserializeSeed<'de> for InArena<'arena, T> {
+    type Value = T;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        T::deserialize_in(deserializer, self.arena)
+    }
+}
+
+macro_rules! deserialize_in_place {
+    ($($ty:ty),*) => {
+        $(
+            impl<'arena> DeserializeIn<'arena> for $ty {
+                #[inline]
+                fn deserialize_in<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                    _arena: &'arena Arena,
+                ) -> Result<Self, D::Error> {
+                    serde::Deserialize::deserialize(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+deserialize_in_place!(
+    (),
+    bool,
+    char,
+    f32,
+    f64,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    String
+);
+
+impl<'arena> DeserializeIn<'arena> for Boxed<'arena, str> {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error> {
+        struct StrVisitor<'arena>(&'arena Arena);
+
+        impl<'de, 'arena> Visitor<'de> for StrVisitor<'arena> {
+            type Value = Boxed<'arena, str>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: Error>(self, text: &str) -> Result<Self::Value, E> {
+                Ok(self.0.alloc_str(text))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor(arena))
+    }
+}
+
+impl<'arena, T: DeserializeIn<'arena> + 'arena> DeserializeIn<'arena> for Boxed<'arena, [T]> {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error> {
+        struct SliceVisitor<'arena, T>(InArena<'arena, T>);
+
+        impl<'de, 'arena, T: DeserializeIn<'arena> + 'arena> Visitor<'de> for SliceVisitor<'arena, T> {
+            type Value = Boxed<'arena, [T]>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut collector = self.0.arena.collector();
+                while let Some(value) = seq.next_element_seed(self.0)? {
+                    collector.push(value);
+                }
+
+                Ok(collector.finish())
+            }
+        }
+
+        deserializer.deserialize_seq(SliceVisitor(InArena::new(arena)))
+    }
+}
+
+impl<'arena, T: DeserializeIn<'arena> + 'arena> DeserializeIn<'arena> for Boxed<'arena, T> {
+    #[inline]
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error> {
+        Ok(arena.alloc(T::deserialize_in(deserializer, arena)?))
+    }
+}
+
+impl<'arena, T: DeserializeIn<'arena>> DeserializeIn<'arena> for Option<T> {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error> {
+        struct OptionVisitor<'arena, T>(InArena<'arena, T>);
+
+        impl<'de, 'arena, T: DeserializeIn<'arena>> Visitor<'de> for OptionVisitor<'arena, T> {
+            type Value = Option<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an option")
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, inner: D) -> Result<Self::Value, D::Error> {
+                self.0.deserialize(inner).map(Some)
+            }
+        }
+
+        deserializer.deserialize_option(OptionVisitor(InArena::new(arena)))
+    }
+}
+
+impl<'arena, T: DeserializeIn<'arena>> DeserializeIn<'arena> for Vec<T> {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        arena: &'arena Arena,
+    ) -> Result<Self, D::Error> {
+        struct VecVisitor<'arena, T>(InArena<'arena, T>);
+
+        impl<'de, 'arena, T: DeserializeIn<'arena>> Visitor<'de> for VecVisitor<'arena, T> {
+            type Value = Vec<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(value) = seq.next_element_seed(self.0)? {
+                    values.push(value);
+                }
+
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_seq(VecVisitor(InArena::new(arena)))
+    }
+}
+
+/// Resolves the name or index of a field or variant, for the derive macro.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct Identifier {
+    pub names: &'static [&'static str],
+    /// Whether unknown names are an error, as they are for variants
+    pub strict: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for Identifier {
+    type Value = Option<usize>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Identifier {
+    type Value = Option<usize>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field or variant identifier")
+    }
+
+    fn visit_u64<E: Error>(self, index: u64) -> Result<Self::Value, E> {
+        match usize::try_from(index) {
+            Ok(index) if index < self.names.len() => Ok(Some(index)),
+            _ if self.strict => Err(E::invalid_value(
+                serde::de::Unexpected::Unsigned(index),
+                &"a variant index",
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    fn visit_str<E: Error>(self, name: &str) -> Result<Self::Value, E> {
+        match self.names.iter().position(|&known| known == name) {
+            Some(index) => Ok(Some(index)),
+            None if self.strict => Err(E::unknown_variant(name, self.names)),
+            None => Ok(None),
+        }
+    }
+
+    fn visit_bytes<E: Error>(self, name: &[u8]) -> Result<Self::Value, E> {
+        match std::str::from_utf8(name) {
+            Ok(name) => self.visit_str(name),
+            Err(_) if self.strict => Err(E::invalid_value(
+                serde::de::Unexpected::Bytes(name),
+                &"a variant name",
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+}
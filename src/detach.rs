@@ -0,0 +1,144 @@
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    chunk::{Backing, Chunk},
+    ptr::Boxed,
+    Arena,
+};
+
+/// A value that was taken out of its arena along with its chunk.
+///
+/// It owns the chunk, and deallocates it when dropped,
+/// even after the arena it came from is gone.
+///
+/// Created by [`Arena::detach`].
+pub struct DetachedChunk<T> {
+    value: NonNull<T>,
+    chunk: Chunk,
+}
+
+// The chunk is only touched to deallocate it, and nothing else points into it
+unsafe impl<T: Send> Send for DetachedChunk<T> {}
+unsafe impl<T: Sync> Sync for DetachedChunk<T> {}
+
+impl<T> Deref for DetachedChunk<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for DetachedChunk<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T: Debug> Debug for DetachedChunk<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<T> Drop for DetachedChunk<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.value.as_ptr());
+            self.chunk.deallocate();
+        }
+    }
+}
+
+impl Arena {
+    /// Take a value out of the arena along with its chunk,
+    /// so it can be sent to and dropped on another thread.
+    ///
+    /// The value has to be the only one left in its chunk, and the chunk
+    /// has to come from the backing allocator, not a reservation or a region
+    /// handed over by [`Arena::from_raw_parts`]. Values with a cleanup function from
+    /// [`Arena::alloc_with_dtor`] stay too. Otherwise the handle is given back.
+    /// The arena allocates a new chunk in place of the detached one when it needs to.
+    ///
+    /// Unlike the other methods that take chunks away, such as [`Arena::shrink_to_fit`],
+    /// this takes `&self`: the handle borrows the arena, so it could never be passed in
+    /// alongside `&mut self`. The chunk is only taken when the handle holds its one
+    /// reference, so no other handle points into it.
+    pub fn detach<'arena, T: Send>(
+        &'arena self,
+        handle: Boxed<'arena, T>,
+    ) -> Result<DetachedChunk<T>, Boxed<'arena, T>> {
+        let chunk = handle.as_ptr().chunk;
+        let owned = !matches!(
+            chunk.backing,
+            Backing::Inline | Backing::External | Backing::Reserved
+        );
+        // A cleanup function could not follow the value out of the arena
+        if chunk.state != self.state_ref()
+            || chunk.refs() != 1
+            || !owned
+            || self.state.drops.has_dtors(chunk)
+        {
+            return Err(handle);
+        }
+
+        // Forget everything the arena knows about the chunk
+        let state = &*self.state;
+        if state.config.teardown_drops {
+            state.drops.release_chunk(chunk);
+        }
+        #[cfg(feature = "tagging")]
+        state.tags.release_chunk(chunk);
+        #[cfg(feature = "leak-tracking")]
+        state
+            .leaks
+            .release_range(chunk.start.as_ptr() as usize, chunk.size);
+        chunk.list.detach(chunk);
+
+        let value = handle.into_ptr().as_raw();
+
+        Ok(DetachedChunk {
+            value: unsafe { NonNull::new_unchecked(value) },
+            chunk,
+        })
+    }
+}
+
+#[test]
+fn detached_values_cross_threads() {
+    use std::sync::mpsc;
+
+    let arena = Arena::new();
+    let message = arena.alloc(vec![1, 2, 3]);
+    let chunk = message.as_ptr().chunk;
+    let chunks = arena.stats().chunks;
+
+    // Another value in the chunk keeps it in the arena
+    let other = arena.alloc(0_u8);
+    let message = arena.detach(message).unwrap_err();
+    drop(other);
+
+    let detached = arena.detach(message).unwrap();
+    assert_eq!(arena.stats().chunks, chunks - 1);
+    assert!(arena.list_for_size(1).chunks().all(|c| c != chunk));
+    arena.validate().unwrap();
+
+    // The arena goes on without it
+    assert_eq!(*arena.alloc(7_u8), 7);
+    drop(arena);
+
+    let (sender, receiver) = mpsc::channel();
+    let worker = std::thread::spawn(move || {
+        let mut message: DetachedChunk<Vec<i32>> = receiver.recv().unwrap();
+        message.push(4);
+        message.iter().sum::<i32>()
+    });
+    sender.send(detached).unwrap();
+    assert_eq!(worker.join().unwrap(), 10);
+}
@@ -0,0 +1,238 @@
+use std::{cell::Cell, cell::RefCell, collections::HashMap, mem, ptr};
+
+use crate::chunk::Chunk;
+
+/// Drop glue for `len` values of a type, starting at a pointer.
+pub(crate) type DropFn = unsafe fn(*mut u8, usize);
+
+pub(crate) unsafe fn drop_glue<T>(ptr: *mut u8, len: usize) {
+    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(ptr.cast::<T>(), len));
+}
+
+/// Drops the values written so far if a fill unwinds.
+pub(crate) struct PrefixGuard<T> {
+    pub start: *mut T,
+    pub len: usize,
+}
+
+impl<T> Drop for PrefixGuard<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.start, self.len)) }
+    }
+}
+
+/// Tells apart the allocations registered at the same address,
+/// which zero sized values share with each other and their neighbours.
+///
+/// Made from the pointer the handle holds, so it also covers the length
+/// of slices, even zero sized ones. The type is compared by name, since
+/// the address of its drop glue can differ between codegen units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DropKey {
+    /// The handle's pointer, with its length or vtable
+    raw: [usize; 2],
+    ty: &'static str,
+}
+
+impl DropKey {
+    pub fn of<T: ?Sized>(ptr: *mut T) -> Self {
+        let mut raw = [0; 2];
+        debug_assert!(mem::size_of::<*mut T>() <= mem::size_of_val(&raw));
+        // Only the bits are compared, the pointer is never read back
+        unsafe { raw.as_mut_ptr().cast::<*mut T>().write(ptr) };
+
+        Self {
+            raw,
+            ty: std::any::type_name::<T>(),
+        }
+    }
+}
+
+/// Cleanup function of a value from [`Arena::alloc_with_dtor`](crate::Arena::alloc_with_dtor).
+#[derive(Debug)]
+pub(crate) struct Dtor {
+    /// Registration order, shared with the drop glue
+    seq: u64,
+    key: DropKey,
+    ptr: *mut u8,
+    /// The `fn(&mut T)`, called through `call`
+    dtor: *const (),
+    call: unsafe fn(*mut u8, *const ()),
+}
+
+impl Dtor {
+    /// Run the cleanup function, before the value is dropped.
+    pub unsafe fn run(self) {
+        (self.call)(self.ptr, self.dtor)
+    }
+}
+
+unsafe fn call_dtor<T>(ptr: *mut u8, dtor: *const ()) {
+    let dtor = mem::transmute::<*const (), fn(&mut T)>(dtor);
+    dtor(&mut *ptr.cast::<T>())
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Registration order
+    seq: u64,
+    key: DropKey,
+    ptr: *mut u8,
+    len: usize,
+    drop: DropFn,
+}
+
+/// Drop glue of live allocations, so it can run when the arena is torn down.
+#[derive(Debug, Default)]
+pub(crate) struct DropRegistry {
+    next_seq: Cell<u64>,
+    entries: RefCell<HashMap<Chunk, Vec<Entry>>>,
+    /// Cleanup functions, kept whether or not destructors run on teardown
+    dtors: RefCell<HashMap<Chunk, Vec<Dtor>>>,
+    /// How many cleanup functions there are, so handles skip looking when none are
+    dtor_count: Cell<usize>,
+}
+
+impl DropRegistry {
+    /// Remember the drop glue for `len` values of `T` at `ptr`,
+    /// owned by the handle with `key`.
+    pub fn register<T>(&self, chunk: Chunk, ptr: *mut T, len: usize, key: DropKey) {
+        if !mem::needs_drop::<T>() {
+            return;
+        }
+
+        let seq = self.next_seq();
+
+        self.entries
+            .borrow_mut()
+            .entry(chunk)
+            .or_default()
+            .push(Entry {
+                seq,
+                key,
+                ptr: ptr.cast(),
+                len,
+                drop: drop_glue::<T>,
+            });
+    }
+
+    fn next_seq(&self) -> u64 {
+        let seq = self.next_seq.get();
+        self.next_seq.set(seq + 1);
+        seq
+    }
+
+    /// Remember a cleanup function to run right before the value at `ptr` drops.
+    pub fn register_dtor<T>(&self, chunk: Chunk, ptr: *mut T, dtor: fn(&mut T)) {
+        let dtor = Dtor {
+            seq: self.next_seq(),
+            key: DropKey::of(ptr),
+            ptr: ptr.cast(),
+            dtor: dtor as *const (),
+            call: call_dtor::<T>,
+        };
+
+        self.dtors.borrow_mut().entry(chunk).or_default().push(dtor);
+        self.dtor_count.set(self.dtor_count.get() + 1);
+    }
+
+    /// Take the cleanup function of the value a handle holding `ptr` is dropping.
+    #[inline]
+    pub fn take_dtor<T: ?Sized>(&self, chunk: Chunk, ptr: *mut T) -> Option<Dtor> {
+        if self.dtor_count.get() == 0 {
+            return None;
+        }
+
+        let key = DropKey::of(ptr);
+        let mut dtors = self.dtors.borrow_mut();
+        let held = dtors.get_mut(&chunk)?;
+        let index = held.iter().position(|dtor| dtor.key == key)?;
+        self.dtor_count.set(self.dtor_count.get() - 1);
+
+        Some(held.swap_remove(index))
+    }
+
+    /// Checks if a value in `chunk` has a cleanup function.
+    pub fn has_dtors(&self, chunk: Chunk) -> bool {
+        self.dtor_count.get() != 0
+            && self
+                .dtors
+                .borrow()
+                .get(&chunk)
+                .is_some_and(|held| !held.is_empty())
+    }
+
+    /// Forget the allocation of the handle with `key`, which dropped its value.
+    pub fn unregister(&self, chunk: Chunk, key: DropKey) {
+        let mut entries = self.entries.borrow_mut();
+
+        if let Some(held) = entries.get_mut(&chunk) {
+            if let Some(index) = held.iter().position(|entry| entry.key == key) {
+                held.swap_remove(index);
+            }
+        }
+    }
+
+    /// Follow a slice that moved within its chunk.
+    pub fn relocate<T>(&self, chunk: Chunk, old: *mut [T], new: *mut [T]) {
+        let mut entries = self.entries.borrow_mut();
+        let old = DropKey::of(old);
+
+        if let Some(entry) = entries
+            .get_mut(&chunk)
+            .and_then(|held| held.iter_mut().find(|entry| entry.key == old))
+        {
+            entry.key = DropKey::of(new);
+            entry.ptr = new.cast();
+            entry.len = new.len();
+        }
+    }
+
+    /// Forget every allocation of a chunk that was reset.
+    pub fn release_chunk(&self, chunk: Chunk) {
+        self.entries.borrow_mut().remove(&chunk);
+        self.release_dtors(chunk);
+    }
+
+    /// Forget the cleanup functions of a chunk that was reset.
+    pub fn release_dtors(&self, chunk: Chunk) {
+        if self.dtor_count.get() == 0 {
+            return;
+        }
+
+        if let Some(held) = self.dtors.borrow_mut().remove(&chunk) {
+            self.dtor_count.set(self.dtor_count.get() - held.len());
+        }
+    }
+
+    /// Run the drop glue of every remaining allocation, newest first.
+    ///
+    /// Cleanup functions are registered after the drop glue of their value,
+    /// so each runs right before it.
+    pub fn run(&self) {
+        let mut pending: Vec<Entry> = self.entries.take().into_values().flatten().collect();
+        pending.sort_unstable_by_key(|entry| entry.seq);
+        let mut dtors: Vec<Dtor> = self.dtors.take().into_values().flatten().collect();
+        dtors.sort_unstable_by_key(|dtor| dtor.seq);
+        self.dtor_count.set(0);
+
+        loop {
+            let dtor_first = match (pending.last(), dtors.last()) {
+                (Some(entry), Some(dtor)) => dtor.seq > entry.seq,
+                (None, Some(_)) => true,
+                (_, None) => false,
+            };
+
+            if dtor_first {
+                let Some(dtor) = dtors.pop() else {
+                    unreachable!()
+                };
+                unsafe { dtor.run() }
+            } else if let Some(entry) = pending.pop() {
+                unsafe { (entry.drop)(entry.ptr, entry.len) }
+            } else {
+                break;
+            }
+        }
+    }
+}
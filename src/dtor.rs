@@ -0,0 +1,83 @@
+use crate::{ptr::Boxed, Arena};
+
+impl Arena {
+    /// Allocate a value along with a function that cleans it up,
+    /// such as an FFI handle and the function that frees it.
+    ///
+    /// `dtor` runs once when the handle drops (or on teardown, with
+    /// [`ArenaBuilder::drop_on_teardown`](crate::ArenaBuilder::drop_on_teardown)),
+    /// right before the value's own destructor.
+    #[track_caller]
+    pub fn alloc_with_dtor<T>(&self, value: T, dtor: fn(&mut T)) -> Boxed<'_, T> {
+        let boxed = self.alloc(value);
+        let ptr = boxed.as_ptr();
+        self.state
+            .drops
+            .register_dtor(ptr.chunk, ptr.as_raw(), dtor);
+
+        boxed
+    }
+}
+
+#[test]
+fn dtor_runs_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FREED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Handle(u32);
+
+    fn free(handle: &mut Handle) {
+        FREED.fetch_add(handle.0 as usize, Ordering::Relaxed);
+    }
+
+    let arena = Arena::new();
+    let mut handle = arena.alloc_with_dtor(Handle(1), free);
+    handle.0 += 1;
+    assert_eq!(handle.0, 2);
+    assert_eq!(FREED.load(Ordering::Relaxed), 0);
+    drop(handle);
+    assert_eq!(FREED.load(Ordering::Relaxed), 2);
+
+    // Teardown counts too, but never twice
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    let _ = arena.alloc_with_dtor(Handle(10), free);
+    arena.alloc_with_dtor(Handle(100), free).leak();
+    drop(arena);
+    assert_eq!(FREED.load(Ordering::Relaxed), 112);
+}
+
+#[test]
+fn dtor_runs_before_drop() {
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Logged(Rc<RefCell<Vec<&'static str>>>);
+
+    impl Drop for Logged {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push("drop");
+        }
+    }
+
+    fn cleanup(value: &mut Logged) {
+        value.0.borrow_mut().push("dtor");
+    }
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let arena = Arena::new();
+    drop(arena.alloc_with_dtor(Logged(log.clone()), cleanup));
+    assert_eq!(*log.borrow(), ["dtor", "drop"]);
+
+    // A value later put in the same slot has no cleanup function
+    drop(arena.alloc(Logged(log.clone())));
+    assert_eq!(*log.borrow(), ["dtor", "drop", "drop"]);
+
+    // On teardown, each runs right before its own value drops
+    log.borrow_mut().clear();
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    arena.alloc(Logged(log.clone())).leak();
+    arena.alloc_with_dtor(Logged(log.clone()), cleanup).leak();
+    arena.alloc(Logged(log.clone())).leak();
+    drop(arena);
+    assert_eq!(*log.borrow(), ["drop", "dtor", "drop", "drop"]);
+}
@@ -0,0 +1,285 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt::Debug,
+    marker::PhantomData,
+    mem,
+    ptr::NonNull,
+};
+
+use crate::{
+    chunk::{Chunk, ChunkList, ListRef},
+    drops::{drop_glue, DropFn},
+    fail::fail,
+    Arena,
+};
+
+/// A handle to a value allocated in an epoch, see [`Arena::alloc_epoch`].
+///
+/// It does not borrow the arena, the value is reached through
+/// [`Arena::get_epoch`], which refuses handles from expired epochs.
+pub struct EpochHandle<T> {
+    ptr: NonNull<T>,
+    epoch: u64,
+    generation: u64,
+    /// ID of the arena it came from
+    arena: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> EpochHandle<T> {
+    /// The epoch the value was allocated in.
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+}
+
+impl<T> Clone for EpochHandle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for EpochHandle<T> {}
+
+impl<T> Debug for EpochHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EpochHandle")
+            .field("ptr", &self.ptr)
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+/// Chunks opened in one epoch, with the drop glue of their values.
+#[derive(Debug)]
+struct EpochChunks {
+    epoch: u64,
+    chunks: Vec<Chunk>,
+    drops: Vec<(*mut u8, DropFn)>,
+}
+
+/// Allocations that live for a number of epochs.
+#[derive(Debug, Default)]
+pub(crate) struct Epochs {
+    current: Cell<u64>,
+    /// Bumped when every epoch expires early, so older handles are refused
+    generation: Cell<u64>,
+    /// Epochs with allocations, oldest first
+    live: RefCell<VecDeque<EpochChunks>>,
+}
+
+impl Epochs {
+    /// Bump allocate in a chunk of `list` dedicated to the current epoch.
+    #[track_caller]
+    fn allocate(&self, list: &ChunkList, layout: Layout) -> (Chunk, NonNull<u8>) {
+        let current = self.current.get();
+        let mut live = self.live.borrow_mut();
+        if live.back().is_none_or(|last| last.epoch != current) {
+            live.push_back(EpochChunks {
+                epoch: current,
+                chunks: Vec::new(),
+                drops: Vec::new(),
+            });
+        }
+        let Some(open) = live.back_mut() else {
+            unreachable!()
+        };
+
+        let fits = |chunk: &&Chunk| chunk.list == ListRef::new(list) && chunk.can_fit(layout);
+        let chunk = match open.chunks.iter().rev().find(fits) {
+            Some(&chunk) => chunk,
+            None => {
+                let chunk = list.try_checkout(layout).unwrap_or_else(|error| {
+                    fail(
+                        chunk_label(list),
+                        Some(layout),
+                        Some(list.state().config.class_of(list.chunk_size())),
+                        None,
+                        error,
+                    )
+                });
+                open.chunks.push(chunk);
+                chunk
+            }
+        };
+
+        (chunk, chunk.alloc_layout(layout))
+    }
+
+    /// Remember to drop a value when its epoch expires.
+    fn register<T>(&self, ptr: *mut T) {
+        if mem::needs_drop::<T>() {
+            if let Some(open) = self.live.borrow_mut().back_mut() {
+                open.drops.push((ptr.cast(), drop_glue::<T>));
+            }
+        }
+    }
+
+    /// Drop the values of every epoch before `epoch`
+    /// and give their chunks back to their lists.
+    pub fn expire_before(&self, epoch: u64) {
+        loop {
+            // Destructors may allocate in a newer epoch, so do not hold the borrow
+            let expired = {
+                let mut live = self.live.borrow_mut();
+                match live.front() {
+                    Some(oldest) if oldest.epoch < epoch => live.pop_front(),
+                    _ => None,
+                }
+            };
+            let Some(expired) = expired else {
+                break;
+            };
+
+            unsafe {
+                for &(ptr, drop) in expired.drops.iter().rev() {
+                    drop(ptr, 1);
+                }
+                for chunk in expired.chunks {
+                    chunk.list.release_checkout(chunk);
+                }
+            }
+        }
+    }
+
+    /// Expire every epoch, refusing every handle made so far.
+    pub fn expire_all(&self) {
+        self.expire_before(u64::MAX);
+        self.generation.set(self.generation.get() + 1);
+    }
+}
+
+fn chunk_label(list: &ChunkList) -> Option<&'static str> {
+    list.state().config.label
+}
+
+impl Arena {
+    /// The current epoch, starting at zero.
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.state.epochs.current.get()
+    }
+
+    /// Allocate a value that lives until its epoch expires,
+    /// [`ArenaBuilder::keep_epochs`](crate::ArenaBuilder::keep_epochs) epochs from now.
+    ///
+    /// Values of an epoch go into chunks dedicated to it, which are given back
+    /// all at once when the epoch expires, dropping the values.
+    #[track_caller]
+    pub fn alloc_epoch<T>(&self, value: T) -> EpochHandle<T> {
+        let layout = self.apply_min_align(Layout::new::<T>());
+        let list = self.list_for_size(layout.size().max(layout.align()));
+        let epochs = &self.state.epochs;
+
+        let (_, ptr) = epochs.allocate(list, layout);
+        let ptr = ptr.cast::<T>();
+        unsafe { ptr.as_ptr().write(value) };
+        epochs.register(ptr.as_ptr());
+
+        EpochHandle {
+            ptr,
+            epoch: epochs.current.get(),
+            generation: epochs.generation.get(),
+            arena: self.state.id,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Checks if a handle's value is still alive.
+    pub fn is_epoch_live<T>(&self, handle: &EpochHandle<T>) -> bool {
+        let epochs = &self.state.epochs;
+
+        handle.arena == self.state.id
+            && handle.generation == epochs.generation.get()
+            && handle.epoch + self.keep_epochs() > epochs.current.get()
+    }
+
+    /// Get the value behind a handle, unless its epoch expired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle belongs to another arena.
+    pub fn get_epoch<T>(&self, handle: &EpochHandle<T>) -> Option<&T> {
+        assert!(
+            handle.arena == self.state.id,
+            "epoch handle belongs to another arena"
+        );
+
+        if self.is_epoch_live(handle) {
+            Some(unsafe { handle.ptr.as_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Get the value behind a handle mutably, unless its epoch expired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle belongs to another arena.
+    pub fn get_epoch_mut<T>(&mut self, handle: &EpochHandle<T>) -> Option<&mut T> {
+        let mut ptr = handle.ptr;
+        self.get_epoch(handle)?;
+
+        Some(unsafe { ptr.as_mut() })
+    }
+
+    /// Move on to the next epoch, reclaiming the chunks of the epoch that expired.
+    pub fn advance_epoch(&mut self) {
+        let epochs = &self.state.epochs;
+        let current = epochs.current.get() + 1;
+        epochs.current.set(current);
+
+        epochs.expire_before((current + 1).saturating_sub(self.keep_epochs()));
+    }
+
+    /// How many epochs values live for.
+    #[inline]
+    fn keep_epochs(&self) -> u64 {
+        self.state.config.keep_epochs.max(1) as u64
+    }
+}
+
+#[test]
+fn values_live_for_epochs() {
+    use std::rc::Rc;
+
+    let mut arena = Arena::builder().keep_epochs(3).build();
+    let counter = Rc::new(());
+    let first = arena.alloc_epoch((counter.clone(), 1_u32));
+    let boxed = arena.alloc(0_u64);
+    drop(boxed);
+
+    for frame in 1..3 {
+        arena.advance_epoch();
+        assert_eq!(arena.get_epoch(&first).unwrap().1, 1);
+        let later = arena.alloc_epoch(frame);
+        *arena.get_epoch_mut(&later).unwrap() += 10;
+        assert_eq!(arena.get_epoch(&later), Some(&(frame + 10)));
+        arena.validate().unwrap();
+    }
+
+    // The third frame ends the first epoch
+    arena.advance_epoch();
+    assert!(arena.get_epoch(&first).is_none());
+    assert_eq!(Rc::strong_count(&counter), 1);
+    arena.validate().unwrap();
+
+    // Resetting ends every epoch early
+    let kept = arena.alloc_epoch(counter.clone());
+    arena.reset_classes_below(usize::MAX);
+    assert!(!arena.is_epoch_live(&kept));
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    let leftover = arena.alloc_epoch(counter.clone());
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    // A new arena, possibly at the same address, does not take old handles
+    let arena = Arena::builder().keep_epochs(3).build();
+    assert!(!arena.is_epoch_live(&leftover));
+}
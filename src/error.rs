@@ -0,0 +1,268 @@
+use std::{alloc::Layout, fmt::Display};
+
+use thiserror::Error;
+
+/// An allocation could not be made, see [`AllocError::kind`] for why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    size: usize,
+    reason: AllocFailure,
+}
+
+/// What kind of failure an [`AllocError`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocErrorKind {
+    /// The layout could not be built, such as when raising it to the minimum alignment,
+    /// or its alignment is more than its chunk can provide.
+    InvalidLayout,
+    /// A chunk could not be allocated for it.
+    ChunkAllocation,
+    /// The allocation is too big for any chunk.
+    TooLarge,
+    /// A panic interrupted an earlier allocation, see [`Arena::is_poisoned`](crate::Arena::is_poisoned).
+    Poisoned,
+    /// A custom size class is not bigger than the one before it,
+    /// see [`ArenaBuilder::size_classes`](crate::ArenaBuilder::size_classes).
+    InvalidSizeClass,
+}
+
+/// Why an allocation failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AllocFailure {
+    /// The backing allocator refused
+    OutOfMemory,
+    /// A [`NoGrowthGuard`](crate::NoGrowthGuard) refused
+    GrowthForbidden,
+    /// The memory came back aligned to less than `align`
+    MisalignedSource { align: usize },
+    /// The layout could not be built
+    InvalidLayout,
+    /// The layout needs more than the chunk base alignment `align`
+    UnsupportedAlign { align: usize },
+    /// No chunk can hold `size` bytes
+    TooLarge,
+    /// The arena is poisoned
+    Poisoned,
+    /// Size class `index` of `size` bytes is not bigger than the one before it
+    ShrinkingClass { index: usize },
+}
+
+impl Display for AllocError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.size;
+        match self.reason {
+            AllocFailure::OutOfMemory => write!(f, "failed to allocate a chunk of {size} bytes"),
+            AllocFailure::GrowthForbidden => write!(
+                f,
+                "failed to allocate a chunk of {size} bytes inside a no-growth scope"
+            ),
+            AllocFailure::MisalignedSource { align } => write!(
+                f,
+                "failed to allocate a chunk of {size} bytes, \
+                 the chunk memory was not aligned to {align} bytes"
+            ),
+            AllocFailure::InvalidLayout => f.write_str("invalid layout"),
+            AllocFailure::UnsupportedAlign { align } => write!(
+                f,
+                "invalid layout, chunks of {size} bytes are only aligned to {align} bytes"
+            ),
+            AllocFailure::TooLarge => write!(f, "{size} bytes do not fit in any chunk"),
+            AllocFailure::Poisoned => {
+                f.write_str("arena poisoned by a panic during an earlier allocation")
+            }
+            AllocFailure::ShrinkingClass { index } => write!(
+                f,
+                "size classes must keep growing, class {index} only holds {size} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+impl AllocError {
+    const fn with_reason(size: usize, reason: AllocFailure) -> Self {
+        Self { size, reason }
+    }
+
+    pub(crate) const fn new(chunk_size: usize) -> Self {
+        Self::with_reason(chunk_size, AllocFailure::OutOfMemory)
+    }
+
+    pub(crate) const fn growth_forbidden(chunk_size: usize) -> Self {
+        Self::with_reason(chunk_size, AllocFailure::GrowthForbidden)
+    }
+
+    pub(crate) const fn misaligned_source(chunk_size: usize, align: usize) -> Self {
+        Self::with_reason(chunk_size, AllocFailure::MisalignedSource { align })
+    }
+
+    pub(crate) const fn invalid_layout() -> Self {
+        Self::with_reason(0, AllocFailure::InvalidLayout)
+    }
+
+    pub(crate) const fn unsupported_align(chunk_size: usize, align: usize) -> Self {
+        Self::with_reason(chunk_size, AllocFailure::UnsupportedAlign { align })
+    }
+
+    pub(crate) const fn too_large(size: usize) -> Self {
+        Self::with_reason(size, AllocFailure::TooLarge)
+    }
+
+    pub(crate) const fn poisoned() -> Self {
+        Self::with_reason(0, AllocFailure::Poisoned)
+    }
+
+    pub(crate) const fn shrinking_class(index: usize, chunk_size: usize) -> Self {
+        Self::with_reason(chunk_size, AllocFailure::ShrinkingClass { index })
+    }
+
+    /// What kind of failure this is.
+    #[inline]
+    pub const fn kind(&self) -> AllocErrorKind {
+        match self.reason {
+            AllocFailure::OutOfMemory
+            | AllocFailure::GrowthForbidden
+            | AllocFailure::MisalignedSource { .. } => AllocErrorKind::ChunkAllocation,
+            AllocFailure::InvalidLayout | AllocFailure::UnsupportedAlign { .. } => {
+                AllocErrorKind::InvalidLayout
+            }
+            AllocFailure::TooLarge => AllocErrorKind::TooLarge,
+            AllocFailure::Poisoned => AllocErrorKind::Poisoned,
+            AllocFailure::ShrinkingClass { .. } => AllocErrorKind::InvalidSizeClass,
+        }
+    }
+
+    /// Checks if the chunk was refused because of a [`NoGrowthGuard`](crate::NoGrowthGuard),
+    /// rather than by the backing allocator.
+    #[inline]
+    pub const fn is_growth_forbidden(&self) -> bool {
+        matches!(self.reason, AllocFailure::GrowthForbidden)
+    }
+
+    /// Checks if the memory for the chunk came back aligned to less than
+    /// the chunk needs, which breaks the contract of its source.
+    #[inline]
+    pub const fn is_misaligned_source(&self) -> bool {
+        matches!(self.reason, AllocFailure::MisalignedSource { .. })
+    }
+
+    /// Size of the chunk that could not be allocated or aligned to the layout,
+    /// of the size class that does not grow, or of the allocation that is too large.
+    /// Zero for the other kinds.
+    #[inline]
+    pub const fn chunk_size(&self) -> usize {
+        self.size
+    }
+}
+
+/// A broken invariant found by [`Arena::validate`](crate::Arena::validate).
+///
+/// Chunks are identified by their size class and their index within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum CorruptionError {
+    #[error("class {class}: the chunk stack loops or holds more than {len} chunks")]
+    ChunkStackLoops { class: usize, len: usize },
+    #[error("class {class}: {found} chunks are linked, but {len} are counted")]
+    ChunkCount {
+        class: usize,
+        len: usize,
+        found: usize,
+    },
+    #[error("class {class}: chunk {index} has a broken back link")]
+    BrokenLink { class: usize, index: usize },
+    #[error("class {class}: chunk {index} belongs to another list")]
+    WrongList { class: usize, index: usize },
+    #[error("class {class}: the free list loops or holds chunks outside the chunk stack")]
+    FreeListLoops { class: usize },
+    #[error("class {class}: {found} chunks are on the free list, but {len} are counted")]
+    FreeCount {
+        class: usize,
+        len: usize,
+        found: usize,
+    },
+    #[error("class {class}: chunk {index} is on the free list without its free bit")]
+    FreeNotMarked { class: usize, index: usize },
+    #[error("class {class}: chunk {index} has its free bit but is not on the free list")]
+    MarkedNotListed { class: usize, index: usize },
+    #[error("class {class}: free chunk {index} still has {refs} references")]
+    FreeWithRefs {
+        class: usize,
+        index: usize,
+        refs: u64,
+    },
+    #[error("class {class}: free chunk {index} was not reset")]
+    FreeNotReset { class: usize, index: usize },
+    #[error("class {class}: chunk {index} is both current and free")]
+    CurrentAndFree { class: usize, index: usize },
+    #[error("class {class}: chunk {index} disagrees with the list about being current")]
+    CurrentMismatch { class: usize, index: usize },
+    #[error("class {class}: chunk {index} has no references but is neither current nor free")]
+    Orphaned { class: usize, index: usize },
+}
+
+/// A [`StackArena`](crate::StackArena) has no room left for an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error(
+    "stack arena has {remaining} bytes left, {} bytes aligned to {} were requested",
+    .requested.size(),
+    .requested.align()
+)]
+pub struct StackFullError {
+    requested: Layout,
+    remaining: usize,
+}
+
+impl StackFullError {
+    pub(crate) const fn new(requested: Layout, remaining: usize) -> Self {
+        Self {
+            requested,
+            remaining,
+        }
+    }
+
+    /// Layout of the allocation that did not fit.
+    #[inline]
+    pub const fn requested(&self) -> Layout {
+        self.requested
+    }
+
+    /// Bytes that were left in the buffer, before alignment.
+    #[inline]
+    pub const fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+/// A [`RingArena`](crate::RingArena) has no room left before its oldest live value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error(
+    "ring arena of {capacity} bytes is full, {} bytes aligned to {} were requested",
+    .requested.size(),
+    .requested.align()
+)]
+pub struct RingFullError {
+    requested: Layout,
+    capacity: usize,
+}
+
+impl RingFullError {
+    pub(crate) const fn new(requested: Layout, capacity: usize) -> Self {
+        Self {
+            requested,
+            capacity,
+        }
+    }
+
+    /// Layout of the allocation that did not fit.
+    #[inline]
+    pub const fn requested(&self) -> Layout {
+        self.requested
+    }
+
+    /// Size of the ring.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
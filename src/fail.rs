@@ -0,0 +1,130 @@
+use std::{alloc::Layout, fmt::Display, fmt::Write};
+
+use crate::chunk::Chunk;
+
+/// Panic with a message describing the allocation that failed,
+/// the size class it was routed to, as its index and chunk size,
+/// and the chunk involved.
+#[cold]
+#[track_caller]
+pub(crate) fn fail(
+    label: Option<&str>,
+    layout: Option<Layout>,
+    class: Option<(usize, usize)>,
+    chunk: Option<Chunk>,
+    reason: impl Display,
+) -> ! {
+    let mut message = match label {
+        Some(label) => format!("battle-arena[{label}]: {reason}"),
+        None => format!("battle-arena: {reason}"),
+    };
+
+    if let Some(layout) = layout {
+        let _ = write!(
+            message,
+            "; requested size {}, align {}",
+            layout.size(),
+            layout.align()
+        );
+    }
+
+    if let Some((index, chunk_size)) = class {
+        let _ = write!(message, "; class {index} ({chunk_size} byte chunks)");
+    }
+
+    if let Some(chunk) = chunk {
+        let used = chunk.size - chunk.remaining();
+        let _ = write!(
+            message,
+            "; chunk {} at {:p} ({used} of {} bytes used, refs {}, flags {:#x})",
+            chunk.index,
+            chunk,
+            chunk.size,
+            chunk.refs(),
+            chunk.flags()
+        );
+    }
+
+    panic!("{message}")
+}
+
+#[cfg(test)]
+use std::panic::AssertUnwindSafe;
+
+#[test]
+fn slice_overflow_message() {
+    let arena = crate::Arena::new();
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_default::<u64>(usize::MAX);
+    }))
+    .unwrap_err();
+
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("invalid layout for a slice of"));
+    assert!(message.contains("requested size 8, align 8"));
+}
+
+#[test]
+fn chunk_overflow_message() {
+    let arena = crate::Arena::new();
+    let value = arena.alloc(0_u8);
+    let chunk = value.as_ptr().chunk();
+    let layout = Layout::new::<[u8; 512]>();
+
+    let error =
+        std::panic::catch_unwind(AssertUnwindSafe(|| chunk.alloc_layout(layout))).unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("requested size 512, align 1"));
+    assert!(message.contains("class 0 (256 byte chunks)"));
+    assert!(message.contains("1 of 256 bytes used"));
+}
+
+#[test]
+fn aligned_validation() {
+    let arena = crate::Arena::new();
+
+    for align in [0, 3] {
+        let error = std::panic::catch_unwind(AssertUnwindSafe(|| arena.alloc_aligned(8, align)))
+            .unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.contains(&format!("alignment must be a power of two, got {align}")));
+    }
+
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| arena.alloc_aligned(usize::MAX, 16)))
+        .unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("overflows when padded to alignment 16"));
+
+    let value = arena.alloc_aligned(24, 16);
+    assert_eq!(value.len(), 24);
+    assert!((value.as_raw() as *const u8 as usize).is_multiple_of(16));
+}
+
+#[test]
+fn overflowing_slice_lengths() {
+    use crate::{checked_array_size, StackArena};
+
+    assert_eq!(checked_array_size::<u16>(usize::MAX), None);
+    assert_eq!(checked_array_size::<u8>(usize::MAX), None);
+    assert_eq!(checked_array_size::<u32>(4), Some(16));
+    assert_eq!(checked_array_size::<()>(usize::MAX), Some(0));
+
+    let arena = crate::Arena::new();
+    for len in [usize::MAX, usize::MAX / 16 + 1] {
+        let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.alloc_uninit_slice::<u64>(len);
+        }))
+        .unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.contains(&format!("invalid layout for a slice of {len} elements")));
+    }
+
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_with(usize::MAX / 2, |_| 0_u32);
+    }))
+    .unwrap_err();
+    assert!(error.downcast_ref::<String>().is_some());
+
+    let stack = StackArena::<256>::new();
+    assert!(stack.alloc_slice_fill_with(usize::MAX, |_| 0_u16).is_err());
+}
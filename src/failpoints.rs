@@ -0,0 +1,188 @@
+use std::{cell::Cell, ptr::NonNull};
+
+use crate::Arena;
+
+/// Chunk allocations that are made to fail on purpose.
+#[derive(Debug, Default)]
+pub(crate) struct FailPoints {
+    /// How many of the next chunk allocations fail
+    chunks: Cell<usize>,
+    /// How many of the next chunks get misaligned memory
+    misaligned: Cell<usize>,
+    /// How many of the next explicit huge page mappings fail
+    huge_pages: Cell<usize>,
+}
+
+impl FailPoints {
+    /// Checks if the chunk allocation being made should fail,
+    /// using up one injected failure if so.
+    pub fn should_fail(&self) -> bool {
+        match self.chunks.get() {
+            0 => false,
+            n => {
+                self.chunks.set(n - 1);
+                true
+            }
+        }
+    }
+
+    /// Checks if the explicit huge page mapping being made should fail,
+    /// using up one injected failure if so.
+    pub fn should_fail_huge_pages(&self) -> bool {
+        match self.huge_pages.get() {
+            0 => false,
+            n => {
+                self.huge_pages.set(n - 1);
+                true
+            }
+        }
+    }
+
+    /// The memory a chunk gets, a byte off if a misaligned source
+    /// was injected, using it up.
+    pub fn misalign(&self, start: NonNull<u8>) -> NonNull<u8> {
+        match self.misaligned.get() {
+            0 => start,
+            n => {
+                self.misaligned.set(n - 1);
+                unsafe { start.add(1) }
+            }
+        }
+    }
+}
+
+impl Arena {
+    /// Make the next `n` chunk allocations fail,
+    /// as if the backing allocator ran out of memory.
+    ///
+    /// Emergency chunks count as chunk allocations too, so allocations
+    /// that need a new chunk panic like they would on a real failure
+    /// once both attempts fail.
+    pub fn fail_next_chunk_allocations(&self, n: usize) {
+        self.state.failpoints.chunks.set(n);
+    }
+
+    /// Make the memory of the next `n` chunks come back misaligned,
+    /// as if the chunk source broke its contract.
+    pub fn misalign_next_chunk_allocations(&self, n: usize) {
+        self.state.failpoints.misaligned.set(n);
+    }
+
+    /// Make the next `n` explicit huge page mappings fail,
+    /// as if the system had no huge pages to spare.
+    pub fn fail_next_huge_page_mappings(&self, n: usize) {
+        self.state.failpoints.huge_pages.set(n);
+    }
+}
+
+#[test]
+fn injected_chunk_failure() {
+    use std::panic::AssertUnwindSafe;
+
+    let arena = Arena::new();
+    // The first class starts out with four chunks
+    let values: Vec<_> = (0..4)
+        .map(|_| arena.alloc_slice_fill_copy(200, &0_u8))
+        .collect();
+
+    arena.fail_next_chunk_allocations(2);
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_copy(200, &0_u8);
+    }))
+    .unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("failed to allocate a chunk of 256 bytes"));
+
+    // Only two failures were injected, the existing values are untouched
+    assert!(values.iter().all(|value| value.iter().all(|&b| b == 0)));
+    assert_eq!(arena.alloc_slice_fill_copy(200, &1_u8).len(), 200);
+}
+
+#[test]
+fn degraded_chunk_allocations() {
+    let arena = Arena::new();
+    let values: Vec<_> = (0..4)
+        .map(|_| arena.alloc_slice_fill_copy(200, &0_u8))
+        .collect();
+
+    // The full chunk fails, an emergency chunk holds just the value
+    arena.fail_next_chunk_allocations(1);
+    let value = arena.alloc_slice_fill_copy(200, &1_u8);
+    assert_eq!(value.chunk_remaining(), 0);
+    assert_eq!(arena.stats().emergency_chunks, 1);
+    let chunks = arena.stats().chunks;
+
+    // It is deallocated with its value, instead of being reused
+    drop(value);
+    assert_eq!(arena.stats().emergency_chunks, 0);
+    assert_eq!(arena.stats().chunks, chunks - 1);
+
+    // A spare chunk of a bigger class comes first
+    arena.reserve_bytes(1024);
+    arena.fail_next_chunk_allocations(1);
+    let value = arena.alloc_slice_fill_copy(200, &2_u8);
+    assert!(value.chunk_remaining() >= 512 - 200);
+    assert_eq!(arena.stats().emergency_chunks, 0);
+    drop(values);
+}
+
+#[test]
+fn misaligned_chunk_memory() {
+    use std::{alloc::Layout, panic::AssertUnwindSafe};
+
+    use crate::chunk::ChunkList;
+
+    let mut arena = Arena::new();
+    let list = ChunkList::empty(1 << 14, arena.state_ref());
+    arena.misalign_next_chunk_allocations(1);
+    let error = list.try_allocate(Layout::new::<u64>()).unwrap_err();
+    assert!(error.is_misaligned_source());
+    assert!(error.to_string().contains("not aligned to 16384 bytes"));
+    drop(list);
+
+    // Infallible allocations panic instead of degrading
+    arena.misalign_next_chunk_allocations(1);
+    let panic = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_copy(10_000, &0_u8);
+    }))
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("was not aligned to"), "{message}");
+
+    arena.clear_poison();
+    assert_eq!(arena.alloc_slice_fill_copy(10_000, &1_u8).len(), 10_000);
+}
+
+#[test]
+fn forced_huge_page_fallbacks() {
+    use crate::{HugePagePolicy, HUGE_PAGE_SIZE};
+
+    let arena = Arena::builder()
+        .huge_pages(HugePagePolicy::Explicit)
+        .build();
+    arena.fail_next_huge_page_mappings(usize::MAX);
+    drop(arena.alloc_slice_fill_copy(HUGE_PAGE_SIZE, &1_u8));
+
+    // Every chunk big enough for huge pages fell back
+    let wanted: usize = unsafe { &*arena.chunks.get() }
+        .iter()
+        .filter(|list| list.chunk_size() >= HUGE_PAGE_SIZE)
+        .map(|list| list.len())
+        .sum();
+    let stats = arena.stats();
+    assert!(wanted > 0);
+    assert_eq!(
+        (stats.huge_page_chunks, stats.huge_page_fallbacks),
+        (0, wanted)
+    );
+
+    // Only the injected failures fall back
+    let arena = Arena::builder()
+        .huge_pages(HugePagePolicy::Explicit)
+        .build();
+    arena.fail_next_huge_page_mappings(1);
+    drop(arena.alloc_slice_fill_copy(HUGE_PAGE_SIZE, &1_u8));
+    let stats = arena.stats();
+    assert!(stats.huge_page_fallbacks >= 1);
+    assert_eq!(stats.huge_page_chunks + stats.huge_page_fallbacks, wanted);
+}
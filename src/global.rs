@@ -0,0 +1,84 @@
+use std::cell::OnceCell;
+
+use critical_section::Mutex;
+
+use crate::{StackArena, StackFullError};
+
+/// A [`StackArena`] that can live in a `static`, shared between
+/// thread mode and interrupt handlers.
+///
+/// Every access happens inside a critical section, and no handle can leave it.
+/// The buffer is fixed, so allocating never reaches for the heap and takes
+/// bounded time, even from an interrupt handler.
+pub struct GlobalArena<const N: usize> {
+    /// Set up on first use, so the arena can be built in a const
+    arena: Mutex<OnceCell<StackArena<N>>>,
+}
+
+// The arena is only reached inside a critical section,
+// and only `Send` values leave it
+unsafe impl<const N: usize> Sync for GlobalArena<N> {}
+
+impl<const N: usize> GlobalArena<N> {
+    /// Create an empty global arena.
+    pub const fn new() -> Self {
+        Self {
+            arena: Mutex::new(OnceCell::new()),
+        }
+    }
+
+    /// Run `f` with the arena, inside a critical section.
+    ///
+    /// Nothing borrowed from the arena can be returned from `f`,
+    /// so its memory is reused once `f` drops its handles.
+    pub fn with<R>(&self, f: impl FnOnce(&StackArena<N>) -> R) -> R {
+        critical_section::with(|cs| f(self.arena.borrow(cs).get_or_init(StackArena::new)))
+    }
+
+    /// Move a value into the arena for good, so it can be used
+    /// outside of a critical section. Its memory is never reused.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_static<T: Send>(
+        &'static self,
+        value: T,
+    ) -> Result<&'static mut T, StackFullError> {
+        self.with(|arena| {
+            let value: *mut T = arena.alloc(value)?.leak();
+
+            // Leaked values keep their memory for as long as the arena lives
+            Ok(unsafe { &mut *value })
+        })
+    }
+}
+
+impl<const N: usize> Default for GlobalArena<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn global_arena_is_shared() {
+    static ARENA: GlobalArena<1024> = GlobalArena::new();
+
+    let threads: Vec<_> = (0..4_u64)
+        .map(|n| std::thread::spawn(move || ARENA.alloc_static(n).unwrap()))
+        .collect();
+    let mut values: Vec<u64> = threads
+        .into_iter()
+        .map(|thread| *thread.join().unwrap())
+        .collect();
+    values.sort();
+    assert_eq!(values, [0, 1, 2, 3]);
+
+    // Scoped allocations are given back, static ones are not
+    let remaining = ARENA.with(|arena| arena.remaining());
+    ARENA.with(|arena| {
+        let scratch = arena.alloc_slice_copy(&[1_u8; 64]).unwrap();
+        assert!(arena.remaining() <= remaining - 64);
+        drop(scratch);
+    });
+    assert_eq!(ARENA.with(|arena| arena.remaining()), remaining);
+
+    assert!(ARENA.alloc_static([0_u8; 2048]).is_err());
+}
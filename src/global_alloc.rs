@@ -0,0 +1,255 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    mem::{align_of, size_of},
+    ptr::{self, null_mut},
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Size of each chunk [`ArenaGlobal`] takes from the system allocator.
+const GLOBAL_CHUNK_SIZE: usize = 1 << 20;
+
+/// Allocations bigger than this go straight to the system allocator.
+const GLOBAL_LARGE: usize = GLOBAL_CHUNK_SIZE / 4;
+
+/// Start of a chunk, the allocations follow it.
+struct Header {
+    /// Address the next allocation goes at, bumping upwards
+    bump: AtomicUsize,
+    /// Address right after the chunk
+    end: usize,
+}
+
+/// A [`GlobalAlloc`] bumping through chunks and never freeing them,
+/// for short-lived programs whose run time goes into allocating.
+/// Put it in a `#[global_allocator]` static to use it.
+///
+/// It cannot be built on [`Arena`](crate::Arena), which allocates through the
+/// global allocator itself and is not thread safe. Instead, threads bump through
+/// a shared chunk with atomics, and take a lock to replace it once it is full.
+///
+/// Deallocating does nothing, except for the newest allocation whose memory
+/// is handed out again. Everything else is only given back when the process exits,
+/// so the memory of a long-lived process grows without bound. Reallocating
+/// the newest allocation grows or shrinks it in place, anything else is copied
+/// into a new allocation and the old one is leaked, so a growing `Vec` leaks
+/// every buffer it outgrew once something else was allocated. Allocations
+/// over a quarter of a chunk go to the system allocator, and are freed as usual.
+#[derive(Debug)]
+pub struct ArenaGlobal {
+    /// Chunk allocations bump through
+    current: AtomicPtr<Header>,
+    /// Held while replacing the current chunk
+    refill: Mutex<()>,
+    /// How many chunks were taken from the system allocator
+    chunks: AtomicUsize,
+}
+
+impl ArenaGlobal {
+    /// Create an allocator without any chunks yet.
+    pub const fn new() -> Self {
+        Self {
+            current: AtomicPtr::new(null_mut()),
+            refill: Mutex::new(()),
+            chunks: AtomicUsize::new(0),
+        }
+    }
+
+    /// How many chunks were taken from the system allocator.
+    #[inline]
+    pub fn chunks(&self) -> usize {
+        self.chunks.load(Ordering::Relaxed)
+    }
+
+    /// Checks if allocations of this layout skip the chunks.
+    #[inline]
+    fn is_large(layout: Layout) -> bool {
+        layout.size().saturating_add(layout.align()) > GLOBAL_LARGE
+    }
+
+    /// Bump the layout out of `chunk`.
+    fn bump(chunk: &Header, layout: Layout) -> Option<*mut u8> {
+        let mut start = 0;
+        chunk
+            .bump
+            // Acquiring pairs with the release of bytes given back at the tip
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bump| {
+                start = bump.next_multiple_of(layout.align());
+                let end = start + layout.size();
+                (end <= chunk.end).then_some(end)
+            })
+            .ok()?;
+
+        Some(start as *mut u8)
+    }
+
+    /// Replace the current chunk unless another thread already did since `seen`.
+    fn refill(&self, seen: *mut Header) -> Option<()> {
+        let _refill = self
+            .refill
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        if self.current.load(Ordering::Acquire) != seen {
+            return Some(());
+        }
+
+        let layout = Layout::from_size_align(GLOBAL_CHUNK_SIZE, align_of::<Header>()).ok()?;
+        let start = unsafe { System.alloc(layout) };
+        if start.is_null() {
+            return None;
+        }
+
+        let header = start.cast::<Header>();
+        unsafe {
+            header.write(Header {
+                bump: AtomicUsize::new(start as usize + size_of::<Header>()),
+                end: start as usize + GLOBAL_CHUNK_SIZE,
+            })
+        };
+        self.chunks.fetch_add(1, Ordering::Relaxed);
+        self.current.store(header, Ordering::Release);
+
+        Some(())
+    }
+
+    /// The current chunk, if `ptr` was bumped out of it.
+    fn chunk_of(&self, ptr: *mut u8) -> Option<&Header> {
+        let chunk = unsafe { self.current.load(Ordering::Acquire).as_ref()? };
+        let data = chunk as *const Header as usize + size_of::<Header>();
+
+        (data..chunk.end).contains(&(ptr as usize)).then_some(chunk)
+    }
+}
+
+impl Default for ArenaGlobal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for ArenaGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if Self::is_large(layout) {
+            return System.alloc(layout);
+        }
+
+        loop {
+            let current = self.current.load(Ordering::Acquire);
+            if let Some(ptr) = current.as_ref().and_then(|chunk| Self::bump(chunk, layout)) {
+                return ptr;
+            }
+            if self.refill(current).is_none() {
+                return null_mut();
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if Self::is_large(layout) {
+            return System.dealloc(ptr, layout);
+        }
+
+        // Only the newest allocation can be taken back
+        if let Some(chunk) = self.chunk_of(ptr) {
+            let end = ptr as usize + layout.size();
+            // Releasing makes the last writes to the bytes happen before
+            // whichever thread bumps them next
+            let _ = chunk.bump.compare_exchange(
+                end,
+                ptr as usize,
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if Self::is_large(layout) && Self::is_large(new_layout) {
+            return System.realloc(ptr, layout, new_size);
+        }
+
+        // The newest allocation can change size in place
+        if !Self::is_large(layout) && !Self::is_large(new_layout) {
+            if let Some(chunk) = self.chunk_of(ptr) {
+                let end = ptr as usize + layout.size();
+                let new_end = ptr as usize + new_size;
+                if new_end <= chunk.end
+                    && chunk
+                        .bump
+                        .compare_exchange(end, new_end, Ordering::AcqRel, Ordering::Relaxed)
+                        .is_ok()
+                {
+                    return ptr;
+                }
+            }
+        }
+
+        let new = self.alloc(new_layout);
+        if !new.is_null() {
+            ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+
+        new
+    }
+}
+
+#[test]
+fn global_bumps_and_reuses_the_tip() {
+    static GLOBAL: ArenaGlobal = ArenaGlobal::new();
+    let layout = Layout::new::<[u64; 4]>();
+
+    unsafe {
+        let first = GLOBAL.alloc(layout);
+        let second = GLOBAL.alloc(layout);
+        assert_eq!(second as usize, first as usize + 32);
+        assert_eq!(GLOBAL.chunks(), 1);
+
+        // Only the newest allocation is taken back
+        GLOBAL.dealloc(second, layout);
+        let third = GLOBAL.alloc(layout);
+        assert_eq!(third, second);
+        GLOBAL.dealloc(first, layout);
+        assert_eq!(GLOBAL.alloc(layout) as usize, third as usize + 32);
+
+        // The newest allocation grows in place, older ones move
+        let newest = GLOBAL.alloc(layout);
+        let grown = GLOBAL.realloc(newest, layout, 64);
+        assert_eq!(grown, newest);
+        third.write(7);
+        let moved = GLOBAL.realloc(third, layout, 64);
+        assert_ne!(moved, third);
+        assert_eq!(moved.read(), 7);
+
+        // Big allocations skip the chunks
+        let big = Layout::from_size_align(GLOBAL_CHUNK_SIZE, 8).unwrap();
+        let ptr = GLOBAL.alloc(big);
+        ptr.write_bytes(1, big.size());
+        GLOBAL.dealloc(ptr, big);
+        assert_eq!(GLOBAL.chunks(), 1);
+    }
+
+    // Threads share the chunks, and each gets its own memory
+    let mut addresses: Vec<_> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let layout = Layout::new::<[u8; 1000]>();
+                    (0..500)
+                        .map(|_| unsafe { GLOBAL.alloc(layout) } as usize)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect()
+    });
+    addresses.sort();
+    assert!(addresses.windows(2).all(|pair| pair[1] - pair[0] >= 1000));
+    assert!(GLOBAL.chunks() >= 2);
+}
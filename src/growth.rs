@@ -0,0 +1,120 @@
+use crate::{state::State, Arena};
+
+/// Forbids the arena from growing while it is alive.
+///
+/// Created by [`Arena::no_growth_scope`].
+#[derive(Debug)]
+#[must_use = "the scope ends as soon as the guard drops"]
+pub struct NoGrowthGuard<'arena> {
+    state: &'arena State,
+}
+
+impl Drop for NoGrowthGuard<'_> {
+    fn drop(&mut self) {
+        self.state.no_growth.set(self.state.no_growth.get() - 1);
+    }
+}
+
+impl Arena {
+    /// Forbid allocating new chunks or size classes until the guard drops.
+    ///
+    /// Allocations still bump into the chunks that already exist, so reserve
+    /// what is needed beforehand, with [`Arena::reserve_for`] for example.
+    /// When no chunk can hold an allocation, not even a spare one of a bigger
+    /// class, it panics instead of reaching the backing allocator,
+    /// or fails like an out of memory error in release builds
+    /// (see [`ArenaBuilder::no_growth_panics`](crate::ArenaBuilder::no_growth_panics)).
+    ///
+    /// Scopes can be nested.
+    pub fn no_growth_scope(&self) -> NoGrowthGuard<'_> {
+        let state = &*self.state;
+        state.no_growth.set(state.no_growth.get() + 1);
+
+        NoGrowthGuard { state }
+    }
+
+    /// Checks if a no-growth scope is alive.
+    #[inline]
+    pub fn is_growth_forbidden(&self) -> bool {
+        self.state.no_growth.get() > 0
+    }
+}
+
+#[test]
+fn growth_inside_scope() {
+    use std::{alloc::Layout, panic::AssertUnwindSafe};
+
+    let arena = Arena::builder().no_growth_panics(true).build();
+    arena.reserve_for(Layout::new::<[u8; 200]>(), 2);
+
+    let scope = arena.no_growth_scope();
+    let nested = arena.no_growth_scope();
+    drop(nested);
+    assert!(arena.is_growth_forbidden());
+
+    // The reserved chunks are fine to use
+    let values: Vec<_> = crate::testing::assert_no_chunk_growth(&arena, || {
+        (0..6).map(|_| arena.alloc([0_u8; 200])).collect()
+    });
+
+    for size in [200, 4096] {
+        let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.alloc_slice_fill_copy(size, &0_u8);
+        }))
+        .unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.contains("inside a no-growth scope"), "{message}");
+    }
+
+    drop(scope);
+    assert!(!arena.is_growth_forbidden());
+    arena.alloc_slice_fill_copy(4096, &0_u8);
+    drop(values);
+}
+
+#[test]
+fn growth_refused_as_error() {
+    use std::panic::AssertUnwindSafe;
+
+    let arena = Arena::builder().no_growth_panics(false).build();
+    let _value = arena.alloc(0_u8);
+    let _scope = arena.no_growth_scope();
+
+    let list = arena.list_for_size(1);
+    while list
+        .try_allocate(std::alloc::Layout::new::<[u8; 200]>())
+        .is_ok()
+    {}
+    let error = list
+        .try_allocate(std::alloc::Layout::new::<[u8; 200]>())
+        .unwrap_err();
+    assert!(error.is_growth_forbidden());
+
+    // The infallible APIs report it when nothing else can hold the value
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc([0_u8; 200]);
+    }))
+    .unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("failed to allocate a chunk of 256 bytes inside a no-growth scope"));
+}
+
+#[test]
+fn growth_falls_back_on_spare_chunks() {
+    use std::alloc::Layout;
+
+    let arena = Arena::builder().no_growth_panics(true).build();
+    arena.reserve_for(Layout::new::<[u8; 4096]>(), 1);
+    let _scope = arena.no_growth_scope();
+
+    // One more value than the class holds goes to a spare chunk of a bigger class
+    let list = arena.list_for_size(200);
+    let (chunks, per_chunk) = (list.len(), list.chunk_size() / 200);
+    let values: Vec<_> = (0..=chunks * per_chunk)
+        .map(|_| arena.alloc([0_u8; 200]))
+        .collect();
+
+    assert_eq!(list.len(), chunks);
+    let spare = values.last().unwrap().as_ptr().chunk;
+    assert!(spare.size > list.chunk_size());
+}
@@ -0,0 +1,216 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{chunk::Chunk, Arena};
+
+type Hook = Box<dyn FnOnce()>;
+type RecycleHook = Box<dyn FnMut(ChunkRecycleInfo)>;
+
+/// Describes a chunk whose memory is about to be reused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRecycleInfo {
+    /// Size class of the chunk.
+    pub class: usize,
+    /// Size of the chunk in bytes.
+    pub chunk_size: usize,
+    /// Index of the chunk within its class.
+    pub chunk: usize,
+    /// Addresses that no longer hold the previous values.
+    pub range: Range<usize>,
+}
+
+/// Callbacks that run when a chunk is recycled.
+#[derive(Default)]
+pub(crate) struct RecycleHooks {
+    hooks: RefCell<Vec<RecycleHook>>,
+    /// Set while the hooks run
+    running: Cell<bool>,
+}
+
+impl std::fmt::Debug for RecycleHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecycleHooks")
+            .field("len", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl RecycleHooks {
+    pub fn push(&self, hook: RecycleHook) {
+        self.hooks.borrow_mut().push(hook);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.hooks.borrow().is_empty()
+    }
+
+    /// Checks if a hook is running right now.
+    #[inline]
+    pub fn running(&self) -> bool {
+        self.running.get()
+    }
+
+    /// Tell every hook the chunk is being recycled.
+    pub fn run(&self, chunk: Chunk) {
+        let mut hooks = self.hooks.borrow_mut();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let start = chunk.start.as_ptr() as usize;
+        let info = ChunkRecycleInfo {
+            class: chunk.state.config.class_for_size(chunk.size),
+            chunk_size: chunk.size,
+            chunk: chunk.index,
+            range: start..start + chunk.size,
+        };
+
+        self.running.set(true);
+        let _running = Running(&self.running);
+        for hook in hooks.iter_mut() {
+            hook(info.clone());
+        }
+    }
+}
+
+/// Clears the running flag once the hooks are done, even if one panicked.
+struct Running<'a>(&'a Cell<bool>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Callbacks that run when the arena drops.
+#[derive(Default)]
+pub(crate) struct DropHooks {
+    hooks: RefCell<Vec<Hook>>,
+}
+
+impl std::fmt::Debug for DropHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DropHooks")
+            .field("len", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl DropHooks {
+    pub fn push(&self, hook: Hook) {
+        self.hooks.borrow_mut().push(hook);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.hooks.borrow().is_empty()
+    }
+
+    /// Run every hook, newest first, returning the payload of the first one that panicked.
+    pub fn run(&self) -> Option<Box<dyn Any + Send>> {
+        let mut first_panic = None;
+
+        // Hooks may register more hooks, so pop them one at a time
+        loop {
+            let Some(hook) = self.hooks.borrow_mut().pop() else {
+                break;
+            };
+
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(hook)) {
+                first_panic.get_or_insert(payload);
+            }
+        }
+
+        first_panic
+    }
+}
+
+impl Arena {
+    /// Run `f` when the arena drops, before any value or chunk is released.
+    ///
+    /// Callbacks run in reverse registration order. A panicking callback does
+    /// not stop the others or the teardown, the first panic is resumed once the
+    /// teardown is done, unless the arena is dropped while already panicking.
+    pub fn on_drop(&self, f: impl FnOnce() + 'static) {
+        self.state.hooks.push(Box::new(f));
+    }
+
+    /// Run `f` whenever a chunk is recycled, so caches keyed by addresses in it
+    /// can be invalidated.
+    ///
+    /// A chunk is recycled when its last value is released and its bump
+    /// pointer is reset, right before it goes back to the free list.
+    /// The callback must not allocate from this arena or register more callbacks.
+    pub fn on_chunk_recycle(&self, f: impl FnMut(ChunkRecycleInfo) + 'static) {
+        self.state.recycle_hooks.push(Box::new(f));
+    }
+}
+
+#[test]
+fn drop_hooks_order_and_panics() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let arena = Arena::new();
+    for i in 0..3 {
+        let order = order.clone();
+        arena.on_drop(move || order.borrow_mut().push(i));
+    }
+    drop(arena);
+    assert_eq!(*order.borrow(), [2, 1, 0]);
+
+    let arena = Arena::new();
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    let first = ran.clone();
+    arena.on_drop(move || first.borrow_mut().push("first"));
+    arena.on_drop(|| panic!("hook failed"));
+    let last = ran.clone();
+    arena.on_drop(move || last.borrow_mut().push("last"));
+
+    let error = panic::catch_unwind(AssertUnwindSafe(|| drop(arena))).unwrap_err();
+    assert_eq!(error.downcast_ref::<&str>(), Some(&"hook failed"));
+    assert_eq!(*ran.borrow(), ["last", "first"]);
+}
+
+#[test]
+fn recycle_hook_ranges() {
+    use std::rc::Rc;
+
+    let arena = Arena::new();
+    let recycled = Rc::new(RefCell::new(Vec::new()));
+    let seen = recycled.clone();
+    arena.on_chunk_recycle(move |info| seen.borrow_mut().push(info));
+
+    let value = arena.alloc(5_u32);
+    let address = value.as_raw() as usize;
+    drop(value);
+
+    let recycled = recycled.borrow();
+    assert_eq!(recycled.len(), 1);
+    assert_eq!(recycled[0].class, 0);
+    assert_eq!(recycled[0].chunk_size, 256);
+    assert!(recycled[0].range.contains(&address));
+}
+
+#[test]
+fn panicking_recycle_hook() {
+    let arena = Arena::new();
+    let panicked = Cell::new(false);
+    arena.on_chunk_recycle(move |_| {
+        if !panicked.replace(true) {
+            panic!("hook failed")
+        }
+    });
+
+    let value = arena.alloc(5_u32);
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(value))).is_err());
+    assert!(!arena.state.recycle_hooks.running());
+    assert_eq!(*arena.alloc(6_u32), 6);
+}
@@ -0,0 +1,158 @@
+use std::{cell::RefCell, fmt::Display, time::Duration};
+
+use crate::Arena;
+
+/// Linear sub-buckets per power of two, values within a bucket
+/// are at most 1/8 apart.
+const SUB_BUCKETS: u64 = 8;
+const SUB_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// Distribution of the time spent allocating chunks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyReport {
+    /// How many chunk allocations were timed.
+    pub samples: u64,
+    /// Fastest chunk allocation.
+    pub min: Duration,
+    /// Slowest chunk allocation.
+    pub max: Duration,
+    /// Median, rounded up to its bucket.
+    pub p50: Duration,
+    /// 90th percentile, rounded up to its bucket.
+    pub p90: Duration,
+    /// 99th percentile, rounded up to its bucket.
+    pub p99: Duration,
+    /// 99.9th percentile, rounded up to its bucket.
+    pub p999: Duration,
+}
+
+impl Display for LatencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} chunk allocations: min {:?}, p50 {:?}, p90 {:?}, p99 {:?}, p99.9 {:?}, max {:?}",
+            self.samples, self.min, self.p50, self.p90, self.p99, self.p999, self.max
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Histogram {
+    counts: [u64; BUCKETS],
+    samples: u64,
+    min: u64,
+    max: u64,
+}
+
+/// Log-linear histogram of chunk allocation times, in nanoseconds.
+#[derive(Debug)]
+pub(crate) struct LatencyHistogram(RefCell<Histogram>);
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self(RefCell::new(Histogram {
+            counts: [0; BUCKETS],
+            samples: 0,
+            min: u64::MAX,
+            max: 0,
+        }))
+    }
+}
+
+/// Bucket holding a value.
+fn bucket(value: u64) -> usize {
+    if value < SUB_BUCKETS {
+        return value as usize;
+    }
+
+    let exp = 63 - value.leading_zeros();
+    let sub = (value >> (exp - SUB_BITS)) & (SUB_BUCKETS - 1);
+    (SUB_BUCKETS + (exp - SUB_BITS) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+/// Largest value that lands in a bucket.
+fn bucket_high(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+
+    let shift = (index - SUB_BUCKETS) / SUB_BUCKETS;
+    let sub = (index - SUB_BUCKETS) % SUB_BUCKETS;
+    let low = (SUB_BUCKETS + sub) << shift;
+    low + ((1 << shift) - 1)
+}
+
+impl LatencyHistogram {
+    /// Record how long a chunk allocation took.
+    pub fn record(&self, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        let mut histogram = self.0.borrow_mut();
+
+        histogram.counts[bucket(nanos)] += 1;
+        histogram.samples += 1;
+        histogram.min = histogram.min.min(nanos);
+        histogram.max = histogram.max.max(nanos);
+    }
+
+    pub fn report(&self) -> LatencyReport {
+        let histogram = self.0.borrow();
+        if histogram.samples == 0 {
+            return LatencyReport::default();
+        }
+
+        let percentile = |quantile: f64| {
+            let rank = ((histogram.samples as f64 * quantile).ceil() as u64).max(1);
+            let mut seen = 0;
+
+            for (index, &count) in histogram.counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    let high = bucket_high(index).clamp(histogram.min, histogram.max);
+                    return Duration::from_nanos(high);
+                }
+            }
+
+            Duration::from_nanos(histogram.max)
+        };
+
+        LatencyReport {
+            samples: histogram.samples,
+            min: Duration::from_nanos(histogram.min),
+            max: Duration::from_nanos(histogram.max),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+        }
+    }
+}
+
+impl Arena {
+    /// Distribution of the time spent allocating new chunks.
+    ///
+    /// Bump allocations within a chunk are not timed.
+    pub fn alloc_latency_report(&self) -> LatencyReport {
+        self.state.latency.report()
+    }
+}
+
+#[test]
+fn chunk_allocations_timed() {
+    let arena = Arena::new();
+    assert_eq!(arena.alloc_latency_report().samples, 0);
+
+    let values: Vec<_> = (0..16)
+        .map(|_| arena.alloc_slice_fill_copy(200, &0_u8))
+        .collect();
+
+    let report = arena.alloc_latency_report();
+    assert!(report.samples >= 16);
+    assert!(report.min <= report.p50 && report.p50 <= report.p99 && report.p99 <= report.max);
+    drop(values);
+
+    for value in [0, 7, 8, 9, 1000, u64::MAX] {
+        assert!(bucket_high(bucket(value)) >= value);
+    }
+}
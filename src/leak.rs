@@ -0,0 +1,224 @@
+use std::{
+    alloc::Layout, any::TypeId, cell::RefCell, cmp::Reverse, collections::HashMap, fmt::Display,
+    panic::Location, ptr::NonNull,
+};
+
+use crate::{
+    chunk::Chunk,
+    ptr::{Ptr, Ref},
+    Arena,
+};
+
+/// Allocations made at one location that are still alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeakReport {
+    /// The label of the arena the allocations live in.
+    pub label: Option<&'static str>,
+    /// Where the allocations were made.
+    pub location: &'static Location<'static>,
+    /// How many of them are alive.
+    pub count: usize,
+    /// How many bytes they take up.
+    pub bytes: usize,
+}
+
+impl Display for LeakReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = self.label {
+            write!(f, "[{label}] ")?;
+        }
+
+        write!(
+            f,
+            "{}: {} allocations, {} bytes",
+            self.location, self.count, self.bytes
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    location: &'static Location<'static>,
+    layout: Layout,
+    /// Live handles pointing at the allocation
+    handles: usize,
+    /// Type of the value and the chunk it is in, for [`Arena::iter_live`]
+    typed: Option<(TypeId, Chunk)>,
+}
+
+/// Live allocations, keyed by address.
+#[derive(Debug, Default)]
+pub(crate) struct LeakTable {
+    entries: RefCell<HashMap<usize, Entry>>,
+}
+
+impl LeakTable {
+    /// Remember where an allocation came from.
+    pub fn record(&self, address: usize, layout: Layout, location: &'static Location<'static>) {
+        self.entries.borrow_mut().insert(
+            address,
+            Entry {
+                location,
+                layout,
+                handles: 0,
+                typed: None,
+            },
+        );
+    }
+
+    /// Remember the type of the value at `address`.
+    pub fn record_type(&self, address: usize, type_id: TypeId, chunk: Chunk) {
+        if let Some(entry) = self.entries.borrow_mut().get_mut(&address) {
+            entry.typed = Some((type_id, chunk));
+        }
+    }
+
+    /// Follow an allocation that moved or changed size.
+    pub fn relocate(&self, old: usize, new: usize, layout: Layout) {
+        let mut entries = self.entries.borrow_mut();
+
+        if let Some(mut entry) = entries.remove(&old) {
+            entry.layout = layout;
+            entries.insert(new, entry);
+        }
+    }
+
+    /// Forget every allocation within `len` bytes from `start`.
+    pub fn release_range(&self, start: usize, len: usize) {
+        self.entries
+            .borrow_mut()
+            .retain(|&address, _| !(start..start + len).contains(&address));
+    }
+
+    /// A handle to the allocation at `address` was created.
+    pub fn add_handle(&self, address: usize) {
+        if let Some(entry) = self.entries.borrow_mut().get_mut(&address) {
+            entry.handles += 1;
+        }
+    }
+
+    /// A handle to the allocation at `address` was dropped,
+    /// forgetting the allocation once none are left.
+    pub fn remove_handle(&self, address: usize) {
+        let mut entries = self.entries.borrow_mut();
+
+        if let Some(entry) = entries.get_mut(&address) {
+            entry.handles -= 1;
+            if entry.handles == 0 {
+                entries.remove(&address);
+            }
+        }
+    }
+}
+
+impl Arena {
+    /// List the locations of allocations that are still alive,
+    /// largest first.
+    pub fn leak_report(&self) -> Vec<LeakReport> {
+        let mut reports: Vec<LeakReport> = Vec::new();
+
+        for entry in self.state.leaks.entries.borrow().values() {
+            match reports
+                .iter_mut()
+                .find(|report| report.location == entry.location)
+            {
+                Some(report) => {
+                    report.count += 1;
+                    report.bytes += entry.layout.size();
+                }
+                None => reports.push(LeakReport {
+                    label: self.label(),
+                    location: entry.location,
+                    count: 1,
+                    bytes: entry.layout.size(),
+                }),
+            }
+        }
+
+        reports.sort_by_key(|report| Reverse(report.bytes));
+        reports
+    }
+
+    /// Allocate a value whose type is recorded, so [`Arena::iter_live`] can find it.
+    ///
+    /// The value is shared from the start, and like every shared value, never dropped.
+    #[track_caller]
+    pub fn alloc_tracked<T: 'static>(&self, value: T) -> Ref<'_, T> {
+        let value = self.alloc(value).into_ref();
+        let ptr = value.as_ptr();
+        self.state
+            .leaks
+            .record_type(ptr.as_raw() as usize, TypeId::of::<T>(), ptr.chunk);
+
+        value
+    }
+
+    /// Make a new handle to every live value of type `T`
+    /// allocated with [`Arena::alloc_tracked`], in no particular order.
+    pub fn iter_live<T: 'static>(&self) -> impl Iterator<Item = Ref<'_, T>> {
+        let type_id = TypeId::of::<T>();
+        let live: Vec<(usize, Chunk)> = self
+            .state
+            .leaks
+            .entries
+            .borrow()
+            .iter()
+            .filter_map(|(&address, entry)| match entry.typed {
+                Some((id, chunk)) if id == type_id => Some((address, chunk)),
+                _ => None,
+            })
+            .collect();
+
+        // Every handle is made up front, so the values stay alive while iterating
+        let handles: Vec<Ref<'_, T>> = live
+            .into_iter()
+            .map(|(address, chunk)| unsafe {
+                let ptr = NonNull::new_unchecked(address as *mut T);
+                Ref::new(Ptr::new(chunk, ptr))
+            })
+            .collect();
+
+        handles.into_iter()
+    }
+}
+
+#[test]
+fn report_locations() {
+    let arena = Arena::new();
+    let first = arena.alloc(1_u32);
+    let line = line!() + 1;
+    let second = arena.alloc_str("leaked");
+    let clone = first.into_ref();
+    let _ = clone.clone();
+
+    let report = arena.leak_report();
+    assert_eq!(report.len(), 2);
+    assert_eq!(report[0].location.line(), line);
+    assert_eq!(report[0].bytes, 6);
+    assert!(report[0].location.file().ends_with("leak.rs"));
+
+    drop(second);
+    drop(clone);
+    assert!(arena.leak_report().is_empty());
+}
+
+#[test]
+fn iterate_live_values_by_type() {
+    let arena = Arena::new();
+    let numbers: Vec<_> = (0..4_u32).map(|n| arena.alloc_tracked(n)).collect();
+    let _text = arena.alloc_tracked("text");
+    let _wide = arena.alloc_tracked(7_u64);
+    let _untracked = arena.alloc(9_u32);
+
+    let mut live: Vec<u32> = arena.iter_live::<u32>().map(|n| *n).collect();
+    live.sort();
+    assert_eq!(live, [0, 1, 2, 3]);
+    assert_eq!(*arena.iter_live::<u64>().next().unwrap(), 7);
+
+    // Yielded handles count like any other
+    let first = arena.iter_live::<u32>().next().unwrap();
+    drop(numbers);
+    assert_eq!(arena.iter_live::<u32>().count(), 1);
+    drop(first);
+    assert_eq!(arena.iter_live::<u32>().count(), 0);
+}
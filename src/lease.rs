@@ -0,0 +1,133 @@
+use std::{alloc::Layout, marker::PhantomData, mem::MaybeUninit, slice};
+
+use crate::{
+    chunk::{Chunk, ChunkList},
+    fail::fail,
+    Arena,
+};
+
+/// A whole chunk taken out of an arena, to sub-allocate in by hand,
+/// see [`Arena::checkout_chunk`].
+///
+/// The arena does not allocate in the chunk while it is leased. Dropping the
+/// lease gives the chunk back to its free list, and leaking it pins the chunk
+/// like a leaked handle.
+#[derive(Debug)]
+pub struct ChunkLease<'a> {
+    chunk: Chunk,
+    list: &'a ChunkList,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> ChunkLease<'a> {
+    /// Size of the data region of the chunk.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.chunk.size
+    }
+
+    /// Checks if the data region is empty, which it never is.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.chunk.size == 0
+    }
+
+    /// The data region of the chunk.
+    ///
+    /// Its contents are whatever the chunk held before.
+    #[inline]
+    pub fn as_uninit_slice(&mut self) -> &mut [MaybeUninit<u8>] {
+        let start = self.chunk.start.as_ptr().cast::<MaybeUninit<u8>>();
+
+        unsafe { slice::from_raw_parts_mut(start, self.chunk.size) }
+    }
+}
+
+impl Drop for ChunkLease<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "leak-tracking")]
+        self.list
+            .state()
+            .leaks
+            .remove_handle(self.chunk.start.as_ptr() as usize);
+
+        unsafe { self.list.release_checkout(self.chunk) }
+    }
+}
+
+impl Arena {
+    /// Take a whole chunk of at least `min_size` bytes out of the arena,
+    /// to sub-allocate in with a scheme of its own.
+    ///
+    /// The chunk is neither current nor free while it is leased,
+    /// and goes back to the free list of its class when the lease drops.
+    #[track_caller]
+    pub fn checkout_chunk(&self, min_size: usize) -> ChunkLease<'_> {
+        let list = self.list_for_size(min_size);
+        let layout = Layout::array::<u8>(list.chunk_size()).unwrap();
+        let chunk = list.try_checkout(layout).unwrap_or_else(|error| {
+            fail(
+                self.label(),
+                Some(layout),
+                Some(self.state.config.class_of(list.chunk_size())),
+                None,
+                error,
+            )
+        });
+
+        #[cfg(feature = "leak-tracking")]
+        {
+            let address = chunk.start.as_ptr() as usize;
+            self.state
+                .leaks
+                .record(address, layout, std::panic::Location::caller());
+            self.state.leaks.add_handle(address);
+        }
+
+        ChunkLease {
+            chunk,
+            list,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[test]
+fn leased_chunks_come_back() {
+    let arena = Arena::new();
+    let mut lease = arena.checkout_chunk(3000);
+    assert!(lease.len() >= 3000);
+
+    // The arena allocates elsewhere while the chunk is leased
+    let region = lease.as_uninit_slice();
+    region.fill(MaybeUninit::new(7));
+    let range = region.as_ptr() as usize..region.as_ptr() as usize + region.len();
+    let values: Vec<_> = (0..8)
+        .map(|_| arena.alloc_slice_fill_copy(3000, &1_u8))
+        .collect();
+    for value in &values {
+        assert!(!range.contains(&(value.as_raw().cast::<u8>() as usize)));
+    }
+    let region = lease.as_uninit_slice();
+    assert!(region.iter().all(|byte| unsafe { byte.assume_init() } == 7));
+
+    // Dropping the lease frees the chunk for the next allocation that needs one
+    let chunks = arena.stats().chunks;
+    drop(lease);
+    arena.validate().unwrap();
+    let reused = arena.alloc_slice_fill_copy(3000, &2_u8);
+    assert!(range.contains(&(reused.as_raw().cast::<u8>() as usize)));
+    assert_eq!(arena.stats().chunks, chunks);
+}
+
+#[cfg(feature = "leak-tracking")]
+#[test]
+fn leaked_leases_are_reported() {
+    let arena = Arena::new();
+    std::mem::forget(arena.checkout_chunk(100));
+
+    let report = arena.leak_report();
+    assert_eq!(report.len(), 1);
+    assert!(report[0].location.file().ends_with("lease.rs"));
+    arena.validate().unwrap();
+}
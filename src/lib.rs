@@ -1,10 +1,118 @@
-use std::{alloc::Layout, cell::UnsafeCell};
+use std::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    fmt::Debug,
+    io::{self, Read},
+    mem::{self, MaybeUninit},
+};
 
+use builder::Config;
 use chunk::ChunkList;
-use ptr::{Boxed, Ptr};
+use drops::{DropKey, PrefixGuard};
+use fail::fail;
+use ptr::{Boxed, Ptr, Ref, RefMut};
+use state::{State, StateRef};
 
+mod adopt;
+mod arena_alloc;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "bytes")]
+mod buf;
+mod builder;
+mod checkpoint;
 mod chunk;
+pub mod collections;
+#[cfg(feature = "serde")]
+pub mod de;
+mod detach;
+mod drops;
+mod dtor;
+mod epoch;
+mod error;
+mod fail;
+#[cfg(feature = "failpoints")]
+mod failpoints;
+#[cfg(feature = "critical-section")]
+mod global;
+mod global_alloc;
+mod growth;
+mod hooks;
+#[cfg(feature = "latency-hist")]
+mod latency;
+#[cfg(feature = "leak-tracking")]
+mod leak;
+mod lease;
+#[cfg(feature = "bytemuck")]
+mod offset;
+#[cfg(feature = "bytemuck")]
+mod persist;
+mod pod;
+mod profile;
+#[cfg(feature = "profiling")]
+mod profiling;
 pub mod ptr;
+#[cfg(feature = "quarantine")]
+mod quarantine;
+#[cfg(feature = "record")]
+mod record;
+mod ring;
+mod stack;
+mod state;
+mod stats;
+mod sync;
+#[cfg(feature = "tagging")]
+mod tag;
+#[cfg(feature = "tokio")]
+mod task;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "trace-export")]
+mod trace;
+mod typed;
+mod warnings;
+
+pub use arena_alloc::*;
+#[cfg(feature = "bytes")]
+pub use buf::*;
+pub use builder::*;
+pub use checkpoint::*;
+pub use chunk::sys::HUGE_PAGE_SIZE;
+pub use detach::*;
+pub use epoch::*;
+pub use error::*;
+#[cfg(feature = "critical-section")]
+pub use global::*;
+pub use global_alloc::*;
+pub use growth::*;
+pub use hooks::ChunkRecycleInfo;
+#[cfg(feature = "latency-hist")]
+pub use latency::*;
+#[cfg(feature = "leak-tracking")]
+pub use leak::*;
+pub use lease::*;
+#[cfg(feature = "bytemuck")]
+pub use offset::*;
+pub use pod::*;
+pub use profile::*;
+#[cfg(feature = "profiling")]
+pub use profiling::*;
+#[cfg(feature = "quarantine")]
+pub use quarantine::QUARANTINE_POISON;
+#[cfg(feature = "record")]
+pub use record::*;
+pub use ring::*;
+pub use stack::*;
+pub use stats::*;
+pub use sync::*;
+#[cfg(feature = "tagging")]
+pub use tag::*;
+#[cfg(feature = "tokio")]
+pub use task::*;
+#[cfg(feature = "trace-export")]
+pub use trace::TRACE_CAPACITY;
+pub use typed::*;
+pub use warnings::*;
 
 /// Minimum block size, must be a power of 2.
 pub const MIN_BLOCK_SIZE: usize = 256;
@@ -13,100 +121,834 @@ pub const MIN_BLOCK_SIZE: usize = 256;
 pub const MIN_BLOCK_POW: u32 = MIN_BLOCK_SIZE.trailing_zeros();
 
 #[inline]
-const fn index_to_chunk_size(index: usize) -> usize {
+pub(crate) const fn index_to_chunk_size(index: usize) -> usize {
     1 << (index + MIN_BLOCK_POW as usize)
 }
 
 #[inline]
-const fn size_to_index(size: usize) -> usize {
+pub(crate) const fn size_to_index(size: usize) -> usize {
     size.next_power_of_two()
         .trailing_zeros()
         .saturating_sub(MIN_BLOCK_POW) as usize
 }
 
-#[derive(Debug)]
+/// Size in bytes of `len` values of `T`,
+/// or `None` if it does not fit in an allocation.
+#[inline]
+pub(crate) const fn checked_array_size<T>(len: usize) -> Option<usize> {
+    match mem::size_of::<T>().checked_mul(len) {
+        Some(size) if size <= isize::MAX as usize => Some(size),
+        _ => None,
+    }
+}
+
+/// Layout of `len` values of `T`, see [`checked_array_size`].
+#[inline]
+pub(crate) const fn array_layout<T>(len: usize) -> Option<Layout> {
+    match checked_array_size::<T>(len) {
+        // The size is a multiple of the alignment and at most `isize::MAX`
+        Some(size) => {
+            Some(unsafe { Layout::from_size_align_unchecked(size, mem::align_of::<T>()) })
+        }
+        None => None,
+    }
+}
+
 pub struct Arena {
     /// The basic idea is every index corresponds to a power of two.
     /// This index can be used to calculate it's corresponding
     /// power of two, which is the chunk size of the chunks
     /// in the chunk list stored at the index.
-    chunks: UnsafeCell<Vec<ChunkList>>,
+    ///
+    /// Lists are boxed because their chunks point back at them.
+    #[allow(clippy::vec_box)]
+    chunks: UnsafeCell<Vec<Box<ChunkList>>>,
+    /// State shared with every chunk.
+    state: Box<State>,
+}
+
+impl Debug for Arena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Arena")
+            .field("label", &self.label())
+            .field("chunks", unsafe { &*self.chunks.get() })
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Arena {
     /// Create a new empty arena
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Configure a new arena
+    pub fn builder() -> ArenaBuilder {
+        ArenaBuilder::new()
+    }
+
+    /// Create a new empty arena whose chunks are bound to a NUMA node,
+    /// see [`ArenaBuilder::numa_node`].
+    #[cfg(feature = "numa")]
+    pub fn with_numa_node(node: u32) -> Self {
+        Self::builder().numa_node(node).build()
+    }
+
+    pub(crate) fn with_config(config: Config) -> Self {
         Self {
             chunks: UnsafeCell::new(Vec::new()),
+            state: Box::new(State::new(config)),
         }
     }
 
+    /// The label given by [`ArenaBuilder::label`], if any.
+    #[inline]
+    pub fn label(&self) -> Option<&'static str> {
+        self.state.config.label
+    }
+
+    /// Checks if a panic interrupted an allocation,
+    /// after which every allocation panics.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.poisoned.get()
+    }
+
+    /// Allow allocating again after the arena was poisoned.
+    ///
+    /// Only do this once you know the arena is still consistent,
+    /// the memory of the interrupted allocation stays in use until teardown.
+    pub fn clear_poison(&mut self) {
+        self.state.poisoned.set(false);
+    }
+
+    /// Leak this arena, so handles to its values can be `'static`.
+    ///
+    /// This cannot be undone, short of [`Arena::from_leaked`]: chunks are never
+    /// deallocated, and drop hooks and teardown drops never run. Nothing is reported
+    /// about the values still in it when the process exits.
+    /// The arena is still not `Sync`, so its handles stay on the thread that leaked it.
+    pub fn into_leaked(self) -> &'static Arena {
+        Box::leak(Box::new(self))
+    }
+
+    /// Take back an arena leaked by [`Arena::into_leaked`], mostly for tests.
+    ///
+    /// # Safety
+    ///
+    /// The arena must come from [`Arena::into_leaked`], and none of its handles
+    /// or references to its values may be used once the box is dropped.
+    pub unsafe fn from_leaked(arena: &'static Arena) -> Box<Arena> {
+        Box::from_raw(arena as *const Arena as *mut Arena)
+    }
+
+    #[inline]
+    pub(crate) fn state_ref(&self) -> StateRef {
+        StateRef::new(&self.state)
+    }
+
+    #[track_caller]
     pub(crate) fn allocate(&self, layout: Layout) -> Ptr<u8> {
-        let list = self.list_for_size(layout.size());
-        let ptr = list.allocate(layout);
+        // Matching keeps the caller's location, a closure would not
+        match self.try_allocate(layout) {
+            Ok(ptr) => ptr,
+            Err(error) => self.fail_allocation(layout, error),
+        }
+    }
+
+    /// Like [`Arena::allocate`], returning every failure instead of panicking.
+    #[track_caller]
+    pub(crate) fn try_allocate(&self, layout: Layout) -> Result<Ptr<u8>, AllocError> {
+        let layout = self.try_apply_min_align(layout)?;
+
+        // Chunks are aligned to their size, so big alignments need big chunks
+        let size = layout.size().max(layout.align());
+        self.try_allocate_in(self.try_list_for_size(size)?, layout)
+    }
+
+    /// Panic with a failure of [`Arena::try_allocate`].
+    #[cold]
+    #[track_caller]
+    fn fail_allocation(&self, layout: Layout, error: AllocError) -> ! {
+        // Only failed chunk allocations have a class to blame
+        let class = (error.kind() == AllocErrorKind::ChunkAllocation).then(|| {
+            let padded = self.try_apply_min_align(layout).unwrap_or(layout);
+            let index = self
+                .state
+                .config
+                .class_for_size(padded.size().max(padded.align()));
+
+            (index, self.state.config.class_size(index))
+        });
+
+        fail(self.label(), Some(layout), class, None, error)
+    }
+
+    /// Layout of a slice of `len` values, failing if its size overflows.
+    #[track_caller]
+    fn slice_layout<T>(&self, len: usize) -> Layout {
+        array_layout::<T>(len).unwrap_or_else(|| {
+            fail(
+                self.label(),
+                Some(Layout::new::<T>()),
+                None,
+                None,
+                format_args!("invalid layout for a slice of {len} elements"),
+            )
+        })
+    }
+
+    /// Raise the alignment of a layout to the configured minimum.
+    #[inline]
+    fn apply_min_align(&self, layout: Layout) -> Layout {
+        self.try_apply_min_align(layout).unwrap_or_else(|_| {
+            fail(
+                self.label(),
+                Some(layout),
+                None,
+                None,
+                format_args!("invalid minimum alignment {}", self.state.config.min_align),
+            )
+        })
+    }
+
+    /// Raise the alignment of a layout to the configured minimum, if it stays valid.
+    #[inline]
+    fn try_apply_min_align(&self, layout: Layout) -> Result<Layout, AllocError> {
+        match self.state.config.min_align {
+            0 | 1 => Ok(layout),
+            align => layout
+                .align_to(align)
+                .map_err(|_| AllocError::invalid_layout()),
+        }
+    }
+
+    /// Allocate a layout within a specific chunk list.
+    #[track_caller]
+    fn allocate_in(&self, list: &ChunkList, layout: Layout) -> Ptr<u8> {
+        self.try_allocate_in(list, layout).unwrap_or_else(|error| {
+            // Poisoning has nothing to do with the class
+            let class = (error.kind() != AllocErrorKind::Poisoned)
+                .then(|| self.state.config.class_of(list.chunk_size()));
+
+            fail(self.label(), Some(layout), class, None, error)
+        })
+    }
+
+    /// Like [`Arena::allocate_in`], returning every failure instead of panicking.
+    #[track_caller]
+    fn try_allocate_in(&self, list: &ChunkList, layout: Layout) -> Result<Ptr<u8>, AllocError> {
+        debug_assert!(
+            !self.state.recycle_hooks.running(),
+            "chunk recycle callbacks must not allocate from their arena"
+        );
+
+        if self.is_poisoned() {
+            return Err(AllocError::poisoned());
+        }
+
+        // Chunk memory is only guaranteed to be aligned to `chunk_align`, so a bigger
+        // alignment would only fit by luck. Only classes picked by hand, as with
+        // `alloc_layout_in_class`, get here with one: `try_allocate` sizes chunks
+        // to at least the alignment.
+        let chunk_align = chunk::sys::chunk_align(list.chunk_size());
+        if layout.align() > chunk_align {
+            return Err(AllocError::unsupported_align(
+                list.chunk_size(),
+                chunk_align,
+            ));
+        }
+
+        let ptr = list
+            .try_allocate(layout)
+            .or_else(|error| {
+                // A broken chunk source is a bug, not a shortage
+                if error.is_misaligned_source() {
+                    return Err(error);
+                }
+                self.allocate_degraded(list, layout).ok_or(error)
+            })
+            .map_err(|error| self.state.refuse_growth(error))?;
 
-        ptr
+        #[cfg(feature = "tagging")]
+        self.state
+            .tags
+            .record(ptr.chunk, ptr.as_raw(), layout.size());
+
+        #[cfg(feature = "leak-tracking")]
+        self.state.leaks.record(
+            ptr.as_raw() as usize,
+            layout,
+            std::panic::Location::caller(),
+        );
+
+        self.check_warnings(layout);
+
+        Ok(ptr)
+    }
+
+    /// Allocate a layout after `list` failed to allocate a chunk for it.
+    ///
+    /// A spare chunk of a larger class is used when there is one,
+    /// otherwise an emergency chunk sized just for the layout.
+    #[track_caller]
+    fn allocate_degraded(&self, list: &ChunkList, layout: Layout) -> Option<Ptr<u8>> {
+        let chunks = unsafe { &*self.chunks.get() };
+        let class = self.state.config.class_for_size(list.chunk_size());
+
+        chunks
+            .iter()
+            .skip(class + 1)
+            .find_map(|larger| larger.try_allocate_spare(layout))
+            .or_else(|| list.try_allocate_undersized(layout).ok())
     }
 
     /// Allocate a layout in the arena
+    #[track_caller]
     pub fn alloc_layout(&self, layout: Layout) -> Boxed<'_, [u8]> {
-        let ptr = self.allocate(layout);
+        match self.try_alloc_layout(layout) {
+            Ok(boxed) => boxed,
+            Err(error) => self.fail_allocation(layout, error),
+        }
+    }
+
+    /// Allocate a layout in the arena, returning an error instead of panicking,
+    /// see [`Arena::try_alloc`].
+    #[track_caller]
+    pub fn try_alloc_layout(&self, layout: Layout) -> Result<Boxed<'_, [u8]>, AllocError> {
+        let ptr = self.try_allocate(layout)?;
+
+        Ok(unsafe { Boxed::new(ptr.slice(layout.size())) })
+    }
+
+    /// Allocate `size` bytes aligned to `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or if `size` overflows
+    /// when rounded up to `align`.
+    #[track_caller]
+    pub fn alloc_aligned(&self, size: usize, align: usize) -> Boxed<'_, [u8]> {
+        if !align.is_power_of_two() {
+            fail(
+                self.label(),
+                None,
+                None,
+                None,
+                format_args!("alignment must be a power of two, got {align}"),
+            )
+        }
+
+        let layout = Layout::from_size_align(size, align).unwrap_or_else(|_| {
+            fail(
+                self.label(),
+                None,
+                None,
+                None,
+                format_args!("size {size} overflows when padded to alignment {align}"),
+            )
+        });
+
+        self.alloc_layout(layout)
+    }
+
+    /// Allocate a layout in the chunks of a specific size class,
+    /// instead of the class the layout would round up to.
+    ///
+    /// This lets several values share a larger chunk.
+    /// The class must be large enough to hold the layout.
+    #[track_caller]
+    pub fn alloc_layout_in_class(&self, layout: Layout, class_index: usize) -> Boxed<'_, [u8]> {
+        let config = &self.state.config;
+        if class_index < config.class_for_size(layout.size()) {
+            fail(
+                self.label(),
+                Some(layout),
+                Some((class_index, config.class_size(class_index))),
+                None,
+                "size class is too small for the layout",
+            )
+        }
+
+        let layout = self.apply_min_align(layout);
+        let ptr = self.allocate_in(self.list_for_index(class_index), layout);
         unsafe { Boxed::new(ptr.slice(layout.size())) }
     }
 
     /// Allocate a value in the arena
+    #[track_caller]
     pub fn alloc<T>(&self, value: T) -> Boxed<'_, T> {
-        let layout = Layout::new::<T>();
-        let ptr = self.allocate(layout).cast::<T>();
+        match self.try_alloc(value) {
+            Ok(boxed) => boxed,
+            Err(error) => self.fail_allocation(Layout::new::<T>(), error),
+        }
+    }
+
+    /// Allocate a value in the arena, returning an error instead of panicking
+    /// when the arena is poisoned, or no chunk can be found or allocated for it.
+    ///
+    /// The value is dropped on failure.
+    #[track_caller]
+    pub fn try_alloc<T>(&self, value: T) -> Result<Boxed<'_, T>, AllocError> {
+        let ptr = self.try_allocate(Layout::new::<T>())?.cast::<T>();
+
+        unsafe {
+            ptr.write(value);
+            self.register_drop(ptr, 1, ptr);
+            Ok(Boxed::new(ptr))
+        }
+    }
+
+    /// Allocate a value at the start of at least `min_size` bytes aligned to `align`,
+    /// so no other allocation shares them, such as a cache line another thread writes to.
+    ///
+    /// The padding stays reserved until the chunk is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or if `min_size`
+    /// is smaller than the value.
+    #[track_caller]
+    pub fn alloc_padded<T>(&self, value: T, align: usize, min_size: usize) -> Boxed<'_, T> {
+        if min_size < mem::size_of::<T>() {
+            fail(
+                self.label(),
+                None,
+                None,
+                None,
+                format_args!(
+                    "padded size {min_size} is smaller than the value's {}",
+                    mem::size_of::<T>()
+                ),
+            )
+        }
+
+        let padded = self.alloc_aligned(min_size, align.max(mem::align_of::<T>()));
+        let ptr = padded.into_ptr().cast::<T>();
 
         unsafe {
             ptr.write(value);
+            self.register_drop(ptr, 1, ptr);
+            Boxed::from_ptr(ptr)
+        }
+    }
+
+    /// Drop a value in place, and hand its memory back as an empty slot
+    /// for a new value, without touching the bump pointer.
+    ///
+    /// The slot keeps the chunk alive, like the value did.
+    /// If the destructor panics, the slot is leaked.
+    pub fn recycle<'arena, T>(
+        &'arena self,
+        old: Boxed<'arena, T>,
+    ) -> Boxed<'arena, MaybeUninit<T>> {
+        let ptr = old.into_ptr();
+        unsafe {
+            self.forget_drop(ptr);
+            std::ptr::drop_in_place(ptr.as_raw());
+
+            Boxed::from_ptr(ptr.cast())
+        }
+    }
+
+    /// Like [`Arena::recycle`], for a value of another type.
+    ///
+    /// Gives the value back untouched when `U` is bigger
+    /// or more aligned than `T`.
+    pub fn recycle_as<'arena, T, U>(
+        &'arena self,
+        old: Boxed<'arena, T>,
+    ) -> Result<Boxed<'arena, MaybeUninit<U>>, Boxed<'arena, T>> {
+        if mem::size_of::<U>() > mem::size_of::<T>() || mem::align_of::<U>() > mem::align_of::<T>()
+        {
+            return Err(old);
+        }
+
+        let slot = self.recycle(old).into_ptr();
+
+        Ok(unsafe { Boxed::from_ptr(slot.cast()) })
+    }
+
+    /// Allocate a `Copy` value for the rest of the arena's life, as a plain reference.
+    ///
+    /// This is the cheapest way to allocate: there is no handle to drop,
+    /// and no reference counting beyond pinning the chunk the first time
+    /// it holds such a value. The pinned chunk is not reused until the arena drops.
+    #[track_caller]
+    pub fn alloc_copy<T: Copy>(&self, value: T) -> &T {
+        let ptr = self.allocate(Layout::new::<T>()).cast::<T>();
+
+        unsafe {
+            ptr.write(value);
+            ptr.chunk.pin();
+            &*ptr.as_raw()
+        }
+    }
+
+    /// Allocate `n` values made by `f`, each behind its own handle.
+    ///
+    /// The size class is picked once for the whole batch, big enough to pack
+    /// the values next to each other (up to 64 KiB chunks).
+    /// When a chunk fills up, the rest of the batch moves on to the next one.
+    #[track_caller]
+    pub fn alloc_n<T, F: FnMut(usize) -> T>(&self, n: usize, mut f: F) -> Vec<Boxed<'_, T>> {
+        const MAX_BATCH_CHUNK: usize = 64 << 10;
+
+        // The batch would still pick, and make, a size class
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let layout = self.apply_min_align(Layout::new::<T>());
+        let size = layout.size().max(layout.align());
+        let batch = size
+            .saturating_mul(n)
+            .clamp(size, MAX_BATCH_CHUNK.max(size));
+        let list = self.list_for_size(batch);
+
+        (0..n)
+            .map(|index| {
+                let value = f(index);
+                let ptr = self.allocate_in(list, layout).cast::<T>();
+
+                unsafe {
+                    ptr.write(value);
+                    self.register_drop(ptr, 1, ptr);
+                    Boxed::new(ptr)
+                }
+            })
+            .collect()
+    }
+
+    /// Allocate two values next to each other, with a handle to each.
+    ///
+    /// The size class is picked to hold both, so they share a chunk unless
+    /// the current one fills up in between.
+    /// Both handles are independent, either can be dropped first.
+    /// Like any [`RefMut`], they do not drop the values.
+    #[track_caller]
+    pub fn alloc_pair<A, B>(&self, a: A, b: B) -> (RefMut<'_, A>, RefMut<'_, B>) {
+        let first = self.apply_min_align(Layout::new::<A>());
+        let second = self.apply_min_align(Layout::new::<B>());
+        // The second value may need padding below the first
+        let size = first.size() + second.size() + second.align();
+        let list = self.list_for_size(size.max(first.align()));
+
+        // Dropping the first handle drops its value again if the second allocation fails
+        let first = unsafe {
+            let ptr = self.allocate_in(list, first).cast::<A>();
+            ptr.write(a);
+            self.register_drop(ptr, 1, ptr);
             Boxed::new(ptr)
+        };
+        let second = unsafe {
+            let ptr = self.allocate_in(list, second).cast::<B>();
+            ptr.write(b);
+            self.register_drop(ptr, 1, ptr);
+            Boxed::new(ptr)
+        };
+
+        (first.into_mut(), second.into_mut())
+    }
+
+    /// Allocate a node of a recursive structure, as a shared handle.
+    ///
+    /// Every handle from `alloc_node` borrows the arena for the same
+    /// `'arena` lifetime, so a node can hold handles to its children
+    /// (like `Option<Ref<'arena, Node<'arena>>>`) and be allocated itself.
+    /// Build children before their parent, then drop the root (or the arena)
+    /// to free the whole structure.
+    #[track_caller]
+    pub fn alloc_node<'arena, T: 'arena>(&'arena self, value: T) -> Ref<'arena, T> {
+        self.alloc(value).into_ref()
+    }
+
+    /// Allocate a closure as a `dyn FnMut()` trait object.
+    #[track_caller]
+    pub fn alloc_fn<'chunk, F: FnMut() + 'chunk>(
+        &'chunk self,
+        f: F,
+    ) -> Boxed<'chunk, dyn FnMut() + 'chunk> {
+        let ptr = self.allocate(Layout::new::<F>()).cast::<F>();
+
+        unsafe {
+            ptr.write(f);
+            let closure = ptr.map_raw(|raw| raw as *mut (dyn FnMut() + 'chunk));
+            self.register_drop(ptr, 1, closure);
+            Boxed::new(closure)
         }
     }
 
+    #[track_caller]
     pub fn alloc_slice_fill_with<T>(
         &self,
         len: usize,
         mut f: impl FnMut(usize) -> T,
     ) -> Boxed<'_, [T]> {
-        let layout = Layout::array::<T>(len).expect("invalid slice layout");
+        let layout = self.slice_layout::<T>(len);
         let ptr = self.allocate(layout).cast::<T>();
         unsafe {
+            // A panicking closure only drops what it made,
+            // the memory stays bumped until the chunk is reset
+            let mut prefix = PrefixGuard {
+                start: ptr.as_raw(),
+                len: 0,
+            };
             for i in 0..len {
                 ptr.add(i).write(f(i));
+                prefix.len += 1;
+            }
+            mem::forget(prefix);
+            let slice = ptr.slice(len);
+            self.register_drop(ptr, len, slice);
+
+            Boxed::new(slice)
+        }
+    }
+
+    /// Allocate an array, filling it in place with `f(index)`.
+    ///
+    /// Unlike `alloc([value; N])`, the array is never built on the stack first,
+    /// so big arrays cannot overflow it.
+    #[track_caller]
+    pub fn alloc_array<T, const N: usize>(
+        &self,
+        mut f: impl FnMut(usize) -> T,
+    ) -> Boxed<'_, [T; N]> {
+        let ptr = self.allocate(Layout::new::<[T; N]>()).cast::<T>();
+        unsafe {
+            // A panicking closure only drops what it made,
+            // the memory stays bumped until the chunk is reset
+            let mut prefix = PrefixGuard {
+                start: ptr.as_raw(),
+                len: 0,
+            };
+            for i in 0..N {
+                ptr.add(i).write(f(i));
+                prefix.len += 1;
+            }
+            mem::forget(prefix);
+            let array = ptr.cast::<[T; N]>();
+            self.register_drop(ptr, N, array);
+
+            Boxed::new(array)
+        }
+    }
+
+    /// Like [`Arena::alloc_slice_fill_with`], also claiming the rest of the
+    /// chunk's free space as uninitialized bytes, to pack values into by hand.
+    ///
+    /// The rest keeps the chunk alive like any other handle,
+    /// and is empty when the slice filled the chunk exactly.
+    #[track_caller]
+    pub fn alloc_slice_and_rest<T>(
+        &self,
+        len: usize,
+        f: impl FnMut(usize) -> T,
+    ) -> (Boxed<'_, [T]>, Boxed<'_, [MaybeUninit<u8>]>) {
+        let slice = self.alloc_slice_fill_with(len, f);
+        let chunk = slice.as_ptr().chunk;
+
+        // Spare and emergency chunks are not bumped by their list, they get an empty rest
+        if !chunk.is_current() {
+            let rest = unsafe { Boxed::new(Ptr::new(chunk, chunk.bump.get()).cast().slice(0)) };
+            return (slice, rest);
+        }
+
+        // The rest is counted and reported like any other allocation
+        let layout = self.apply_min_align(Layout::array::<u8>(chunk.remaining()).unwrap());
+        let ptr = self.allocate_in(&chunk.list, layout);
+        let rest = unsafe { Boxed::new(ptr.cast().slice(layout.size())) };
+
+        (slice, rest)
+    }
+
+    /// Like [`Arena::alloc_slice_fill_with`], splitting the slice between
+    /// `threads` scoped threads that each fill their own part.
+    ///
+    /// The slice is allocated once on the calling thread, and only returned
+    /// after every thread finished. A panic in `f` is resumed on this thread.
+    #[track_caller]
+    pub fn alloc_slice_fill_with_threads<T: Send, F: Fn(usize) -> T + Sync>(
+        &self,
+        len: usize,
+        threads: usize,
+        f: F,
+    ) -> Boxed<'_, [T]> {
+        let mut slice = self.alloc_uninit_slice::<T>(len);
+        let per_thread = len.div_ceil(threads.max(1)).max(1);
+
+        // A panicking closure drops what its thread made, and every part the
+        // other threads finished is dropped before the panic is resumed.
+        // The slice itself is given back when it drops
+        let start = slice.as_raw().cast::<T>();
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = slice
+                .chunks_mut(per_thread)
+                .enumerate()
+                .map(|(part, values)| {
+                    let f = &f;
+                    scope.spawn(move || {
+                        let offset = part * per_thread;
+                        let mut prefix = PrefixGuard {
+                            start: values.as_mut_ptr().cast::<T>(),
+                            len: 0,
+                        };
+                        for (i, value) in values.iter_mut().enumerate() {
+                            value.write(f(offset + i));
+                            prefix.len += 1;
+                        }
+                        mem::forget(prefix);
+                    })
+                })
+                .collect();
+
+            let mut finished = Vec::new();
+            let mut panic = None;
+            for (part, thread) in threads.into_iter().enumerate() {
+                match thread.join() {
+                    Ok(()) => finished.push(part),
+                    Err(payload) => panic = panic.or(Some(payload)),
+                }
+            }
+
+            if let Some(payload) = panic {
+                for part in finished {
+                    let offset = part * per_thread;
+                    let filled = per_thread.min(len - offset);
+                    drop(PrefixGuard {
+                        start: unsafe { start.add(offset) },
+                        len: filled,
+                    });
+                }
+                std::panic::resume_unwind(payload);
             }
+        });
 
-            Boxed::new(ptr.slice(len))
+        unsafe {
+            let values = slice.assume_init_prefix(len);
+            self.register_drop(values.as_ptr().cast::<T>(), len, values.as_ptr());
+            values
         }
     }
 
+    /// Allocate a slice holding `f` applied to every value of `src`.
+    #[track_caller]
+    pub fn alloc_map_slice<T, U>(&self, src: &[T], mut f: impl FnMut(&T) -> U) -> Boxed<'_, [U]> {
+        self.alloc_slice_fill_with(src.len(), |i| f(&src[i]))
+    }
+
+    /// Like [`Arena::alloc_map_slice`], also passing the index of each value.
+    #[track_caller]
+    pub fn alloc_map_slice_indexed<T, U>(
+        &self,
+        src: &[T],
+        mut f: impl FnMut(usize, &T) -> U,
+    ) -> Boxed<'_, [U]> {
+        self.alloc_slice_fill_with(src.len(), |i| f(i, &src[i]))
+    }
+
+    /// Allocate a slice of `len` uninitialized values.
+    ///
+    /// Nothing is written to the slots, not even zeroes. **Their contents are
+    /// arbitrary**, usually whatever values lived there before the chunk was reused.
+    #[track_caller]
+    pub fn alloc_uninit_slice<T>(&self, len: usize) -> Boxed<'_, [MaybeUninit<T>]> {
+        let layout = self.slice_layout::<T>(len);
+        let ptr = self.allocate(layout).cast::<MaybeUninit<T>>();
+
+        unsafe { Boxed::new(ptr.slice(len)) }
+    }
+
     #[inline]
+    #[track_caller]
     pub fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> Boxed<'_, [T]> {
-        self.alloc_slice_fill_with(source.len(), |i| source[i])
+        // Copy types can be moved in one memcpy instead of element by element
+        let layout = Layout::for_value(source);
+        let ptr = self.allocate(layout).cast::<T>();
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(source.as_ptr(), ptr.as_raw(), source.len());
+            Boxed::new(ptr.slice(source.len()))
+        }
+    }
+
+    /// Copy `bytes` into a slice of `T`, aligned for it whatever the alignment of `bytes`.
+    ///
+    /// Fails if the length of `bytes` is not a multiple of the size of `T`,
+    /// or `T` is zero sized.
+    #[cfg(feature = "bytemuck")]
+    #[track_caller]
+    pub fn alloc_pod_slice_from_bytes<T: bytemuck::Pod>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Boxed<'_, [T]>, bytemuck::PodCastError> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return Err(bytemuck::PodCastError::SizeMismatch);
+        }
+        if !bytes.len().is_multiple_of(size) {
+            return Err(bytemuck::PodCastError::OutputSliceWouldHaveSlop);
+        }
+
+        let len = bytes.len() / size;
+        let slice = self.alloc_uninit_slice::<T>(len);
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), slice.as_raw().cast(), bytes.len());
+            Ok(slice.assume_init_prefix(len))
+        }
+    }
+
+    /// Allocate a slice of references, typically to other values of this arena,
+    /// such as the children of a syntax tree node.
+    ///
+    /// The slice and the references share the arena's lifetime, so leaked values
+    /// can be gathered without going through a `Vec` on the heap first.
+    /// Iterators that know their exact length fill a single allocation.
+    #[track_caller]
+    pub fn alloc_slice_of_refs<'a, T: ?Sized>(
+        &'a self,
+        iter: impl IntoIterator<Item = &'a T>,
+    ) -> Boxed<'a, [&'a T]> {
+        let mut collector = self.collector::<&'a T>();
+        Extend::<&'a T>::extend(&mut collector, iter);
+
+        collector.finish()
     }
 
     #[inline]
+    #[track_caller]
     pub fn alloc_slice_clone<T: Clone>(&self, source: &[T]) -> Boxed<'_, [T]> {
         self.alloc_slice_fill_with(source.len(), |i| source[i].clone())
     }
 
     #[inline]
+    #[track_caller]
     pub fn alloc_slice_fill_copy<T: Copy>(&self, len: usize, value: &T) -> Boxed<'_, [T]> {
         self.alloc_slice_fill_with(len, |_| *value)
     }
 
     #[inline]
+    #[track_caller]
     pub fn alloc_slice_fill_clone<T: Clone>(&self, len: usize, value: &T) -> Boxed<'_, [T]> {
         self.alloc_slice_fill_with(len, |_| value.clone())
     }
 
     #[inline]
+    #[track_caller]
     pub fn alloc_slice_fill_default<T: Default>(&self, len: usize) -> Boxed<'_, [T]> {
         self.alloc_slice_fill_with(len, |_| T::default())
     }
 
     #[inline]
+    #[track_caller]
     pub fn alloc_str(&self, source: &str) -> Boxed<'_, str> {
         let string = self.alloc_slice_copy(source.as_bytes());
         let (chunk, raw) = {
@@ -119,28 +961,267 @@ impl Arena {
         }
     }
 
+    /// Read exactly `len` bytes from a reader straight into the arena.
+    ///
+    /// The bytes are zeroed before reading, since [`Read`] cannot take uninitialized memory.
+    /// On a short read the error is returned and the allocation is released.
+    #[track_caller]
+    pub fn alloc_slice_from_reader<R: Read>(
+        &self,
+        reader: &mut R,
+        len: usize,
+    ) -> io::Result<Boxed<'_, [u8]>> {
+        let mut bytes = self.alloc_slice_fill_copy(len, &0_u8);
+        reader.read_exact(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Remember the drop glue of `len` values at `ptr`, owned by the handle
+    /// holding `handle`, if destructors run on teardown.
+    #[inline]
+    fn register_drop<T, H: ?Sized>(&self, ptr: Ptr<T>, len: usize, handle: Ptr<H>) {
+        if self.state.config.teardown_drops {
+            let key = DropKey::of(handle.as_raw());
+            self.state.drops.register(ptr.chunk, ptr.as_raw(), len, key);
+        }
+    }
+
+    /// Forget the drop glue of the value at `ptr`, if destructors run on teardown.
+    #[inline]
+    fn forget_drop<T>(&self, ptr: Ptr<T>) {
+        if self.state.config.teardown_drops {
+            let key = DropKey::of(ptr.as_raw());
+            self.state.drops.unregister(ptr.chunk, key);
+        }
+    }
+
     /// Returns the maximum chunk size in this arena.
     pub fn max_size(&self) -> usize {
         unsafe {
             let chunks = &*self.chunks.get();
-            chunks.len().checked_sub(1).map_or(0, index_to_chunk_size)
+            chunks
+                .len()
+                .checked_sub(1)
+                .map_or(0, |index| self.state.config.class_size(index))
+        }
+    }
+
+    /// Check the internal bookkeeping of every size class,
+    /// returning the first broken invariant found.
+    ///
+    /// This walks every chunk, so it is meant for tests and fuzzing.
+    pub fn validate(&self) -> Result<(), CorruptionError> {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        chunks
+            .iter()
+            .enumerate()
+            .try_for_each(|(class, list)| list.validate(class))
+    }
+
+    /// Give the memory behind the pages of every free chunk back to the OS,
+    /// with `madvise(MADV_DONTNEED)` or `DiscardVirtualMemory`, and return
+    /// how many bytes that was.
+    ///
+    /// Unlike [`Arena::shrink_to_fit`], the chunks stay allocated, and their pages
+    /// are backed again when they are reused. Only the whole pages of a chunk are
+    /// given back, so chunks smaller than a page are left alone, and so are footers.
+    #[cfg(feature = "madvise")]
+    pub fn release_free_pages(&self) -> usize {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        chunks.iter().map(|list| list.release_free_pages()).sum()
+    }
+
+    /// Bytes of the arena's chunks that are backed by memory, rather than
+    /// just reserved, as the kernel reports with `mincore`.
+    ///
+    /// Pages are counted whole, including the parts that neighbour a chunk.
+    /// This walks every chunk, and asks the kernel about every page.
+    #[cfg(all(feature = "resident", unix))]
+    pub fn resident_bytes(&self) -> usize {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        chunks.iter().map(|list| list.resident_bytes()).sum()
+    }
+
+    /// Take a snapshot of the memory usage of this arena.
+    pub fn stats(&self) -> ArenaStats {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        chunks.iter().fold(
+            ArenaStats {
+                label: self.label(),
+                classes: chunks.len(),
+                huge_page_fallbacks: self.state.huge_page_fallbacks.get(),
+                ..ArenaStats::default()
+            },
+            |stats, list| ArenaStats {
+                chunks: stats.chunks + list.len(),
+                reserved_bytes: stats.reserved_bytes + list.reserved_bytes(),
+                huge_page_chunks: stats.huge_page_chunks + list.huge_chunks(),
+                advised_chunks: stats.advised_chunks + list.advised_chunks(),
+                trimmed_chunks: stats.trimmed_chunks + list.trimmed_chunks(),
+                emergency_chunks: stats.emergency_chunks + list.emergency_chunks(),
+                ..stats
+            },
+        )
+    }
+
+    /// Run `f`, and report what the allocations it made took up,
+    /// whether it allocated through this arena directly or through nested measurements.
+    pub fn measure<R>(&self, f: impl FnOnce() -> R) -> (R, AllocationDelta) {
+        let state = &*self.state;
+        let snapshot = || {
+            (
+                state.allocations.get(),
+                state.requested_bytes.get(),
+                state.consumed_bytes.get(),
+                state.chunk_allocations.get(),
+            )
+        };
+
+        let before = snapshot();
+        let result = f();
+        let after = snapshot();
+
+        let delta = AllocationDelta {
+            allocations: after.0 - before.0,
+            requested_bytes: after.1.wrapping_sub(before.1),
+            consumed_bytes: after.2.wrapping_sub(before.2),
+            chunks: after.3 - before.3,
+        };
+        (result, delta)
+    }
+
+    /// Make sure the size class able to hold `size` bytes exists,
+    /// along with every smaller class.
+    pub fn reserve_bytes(&self, size: usize) {
+        self.list_for_size(size);
+    }
+
+    /// Allocate `additional` chunks in the size class used for `layout`,
+    /// so that many allocations of it can be made without growing.
+    pub fn reserve_for(&self, layout: Layout, additional: usize) {
+        self.list_for_size(layout.size()).reserve(additional);
+    }
+
+    /// Deallocate every chunk that holds no allocations,
+    /// then drop the empty size classes at the top.
+    pub fn shrink_to_fit(&mut self) {
+        for list in self.chunks.get_mut().iter() {
+            list.release_free();
+        }
+
+        self.compact_classes();
+    }
+
+    /// Drop the trailing size classes that have no chunks,
+    /// and give back the spare capacity of the class list.
+    pub fn compact_classes(&mut self) {
+        let chunks = self.chunks.get_mut();
+
+        // Only lists without chunks can be dropped safely
+        while chunks.last().is_some_and(|list| list.len() == 0) {
+            chunks.pop();
+        }
+
+        chunks.shrink_to_fit();
+    }
+
+    /// Reclaim every chunk in the size classes with chunks smaller than `max_chunk_size`.
+    ///
+    /// Bigger classes are left alone, along with the values in them.
+    /// Values of leaked handles in the reset classes are forgotten without being dropped.
+    /// Every epoch ends early, dropping the values allocated in them.
+    pub fn reset_classes_below(&mut self, max_chunk_size: usize) {
+        // Epoch and quarantined chunks would be reclaimed twice
+        self.state.epochs.expire_all();
+        #[cfg(feature = "quarantine")]
+        self.state.quarantine.release_all();
+
+        let chunks = self.chunks.get_mut();
+
+        for list in chunks
+            .iter()
+            .take_while(|list| list.chunk_size() < max_chunk_size)
+        {
+            // Every handle borrows the arena, so none can be used anymore
+            unsafe { list.reset() };
         }
     }
 
-    /// Reserves the next `n` chunk lists.
-    pub fn reserve_next(&self, n: usize) {
+    /// Appends `n` chunk lists after the current largest class.
+    ///
+    /// The new lists always start at index `chunks.len()`, so this
+    /// cannot be used to reserve a specific class. Use [`Arena::reserve_bytes`]
+    /// or [`Arena::reserve_for`] for that.
+    #[track_caller]
+    pub(crate) fn reserve_next(&self, n: usize) {
+        let start = unsafe { &*self.chunks.get() }.len();
+        let last = start + n - 1;
+
+        if let Err(error) = self.try_reserve_next(n) {
+            let class = (last, self.state.config.class_size(last));
+            fail(self.label(), None, Some(class), None, error)
+        }
+    }
+
+    /// Like [`Arena::reserve_next`], returning allocation failures instead of panicking.
+    ///
+    /// Classes created before the failure are dropped again, along with their chunks.
+    #[track_caller]
+    pub(crate) fn try_reserve_next(&self, n: usize) -> Result<(), AllocError> {
         let chunks = unsafe { &mut *self.chunks.get() };
 
         let start = chunks.len();
         let end = start + n;
 
+        let config = &self.state.config;
+
+        // Custom size classes could otherwise break the lookup
+        let mut previous = start
+            .checked_sub(1)
+            .map_or(0, |index| config.class_size(index));
+        for index in start..end {
+            let size = config.class_size(index);
+            if size <= previous {
+                return Err(AllocError::shrinking_class(index, size));
+            }
+            previous = size;
+        }
+
+        // New classes come with chunks, there is nothing to fall back on
+        let size = (start..end).map(|index| config.class_size(index)).sum();
+        self.state
+            .check_growth(size)
+            .map_err(|error| self.state.refuse_growth(error))?;
+
         chunks.reserve_exact(n);
-        chunks.extend((start..end).map(|index| ChunkList::new(index_to_chunk_size(index))))
+        for index in start..end {
+            match ChunkList::try_new(config.class_size(index), self.state_ref()) {
+                Ok(list) => chunks.push(list),
+                Err(error) => {
+                    // Nothing was allocated in them yet
+                    chunks.truncate(start);
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(())
     }
 
     /// Find a chunk list for a size, or allocate one for it and the sizes leading up to it.
+    #[track_caller]
     pub(crate) fn list_for_size(&self, size: usize) -> &ChunkList {
-        let index = size_to_index(size);
+        self.list_for_index(self.state.config.class_for_size(size))
+    }
+
+    /// Find the chunk list for a size class, or allocate one for it and the classes leading up to it.
+    #[track_caller]
+    pub(crate) fn list_for_index(&self, index: usize) -> &ChunkList {
         let chunks = unsafe { &*self.chunks.get() };
         let length = chunks.len();
 
@@ -155,9 +1236,279 @@ impl Arena {
 
         &chunks[index]
     }
-}
 
-#[test]
+    /// Like [`Arena::list_for_size`], returning allocation failures instead of panicking.
+    #[track_caller]
+    fn try_list_for_size(&self, size: usize) -> Result<&ChunkList, AllocError> {
+        // Chunk sizes past this overflow on their way to a power of two
+        if size > 1 << (usize::BITS - 2) {
+            return Err(AllocError::too_large(size));
+        }
+
+        let index = self.state.config.class_for_size(size);
+        let chunks = unsafe { &*self.chunks.get() };
+        if index >= chunks.len() {
+            self.try_reserve_next(index + 1 - chunks.len())?;
+        }
+
+        Ok(&chunks[index])
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let panicked = self.state.hooks.run();
+        self.state.epochs.expire_all();
+
+        if self.state.config.teardown_drops {
+            self.state.drops.run();
+        }
+
+        // Resuming while already unwinding would abort
+        if let Some(payload) = panicked {
+            if !std::thread::panicking() {
+                std::panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+#[test]
+fn teardown_drops_leaked() {
+    let tracker = testing::DropTracker::new();
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    arena.alloc(tracker.token()).leak();
+    drop(arena.alloc(tracker.token()));
+    assert_eq!(tracker.alive(), 1);
+
+    drop(arena);
+    assert_eq!(tracker.alive(), 0);
+}
+
+#[test]
+fn teardown_drops_recycled_values() {
+    use std::rc::Rc;
+
+    let shared = Rc::new(());
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    let old = arena.alloc(shared.clone());
+    arena.recycle(old).init_with(shared.clone()).leak();
+    arena
+        .alloc(std::mem::MaybeUninit::uninit())
+        .init_with(shared.clone())
+        .into_ref()
+        .leak();
+    assert_eq!(Rc::strong_count(&shared), 3);
+
+    drop(arena);
+    assert_eq!(Rc::strong_count(&shared), 1);
+}
+
+#[test]
+fn teardown_tells_zero_sized_values_apart() {
+    thread_local! {
+        static DROPS: std::cell::Cell<[usize; 2]> = const { std::cell::Cell::new([0; 2]) };
+    }
+
+    struct Counted<const N: usize>;
+
+    impl<const N: usize> Drop for Counted<N> {
+        fn drop(&mut self) {
+            DROPS.with(|drops| {
+                let mut counts = drops.get();
+                counts[N] += 1;
+                drops.set(counts);
+            });
+        }
+    }
+
+    let arena = Arena::builder().drop_on_teardown(true).build();
+
+    // Only the second value is dropped by its handle
+    let first = arena.alloc(Counted::<0>);
+    let second = arena.alloc(Counted::<1>);
+    assert_eq!(first.as_raw().cast::<u8>(), second.as_raw().cast());
+    first.leak();
+    drop(second);
+    assert_eq!(DROPS.get(), [0, 1]);
+    drop(arena);
+    assert_eq!(DROPS.get(), [1, 1]);
+
+    // Slices of the same type only differ by their length
+    let arena = Arena::builder().drop_on_teardown(true).build();
+    let three = arena.alloc_slice_fill_with(3, |_| Counted::<0>);
+    let one = arena.alloc_slice_fill_with(1, |_| Counted::<0>);
+    assert_eq!(three.as_raw().cast::<u8>(), one.as_raw().cast());
+    three.leak();
+    drop(one);
+    assert_eq!(DROPS.get(), [2, 1]);
+    drop(arena);
+    assert_eq!(DROPS.get(), [5, 1]);
+}
+
+#[test]
+fn leaked_arena_is_static() {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static NAMES: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
+    let tracker = testing::DropTracker::new();
+    let arena = Arena::builder()
+        .drop_on_teardown(true)
+        .build()
+        .into_leaked();
+    let value: Boxed<'static, testing::DropToken> = arena.alloc(tracker.token());
+    NAMES.with_borrow_mut(|names| names.push(arena.alloc_str("static").into_ref().leak()));
+    assert_eq!(NAMES.with_borrow(|names| names[0]), "static");
+    arena.alloc(tracker.token()).leak();
+
+    drop(value);
+    NAMES.with_borrow_mut(Vec::clear);
+    assert_eq!(tracker.alive(), 1);
+
+    drop(unsafe { Arena::from_leaked(arena) });
+    assert_eq!(tracker.alive(), 0);
+}
+
+#[test]
+fn arrays_are_filled_in_place() {
+    let arena = Arena::new();
+    let small = arena.alloc_array::<u64, 1024>(|index| index as u64 * 3);
+    assert!(small
+        .iter()
+        .enumerate()
+        .all(|(index, &value)| value == index as u64 * 3));
+
+    // Half a megabyte would not fit on this stack
+    std::thread::Builder::new()
+        .stack_size(128 << 10)
+        .spawn(|| {
+            let arena = Arena::new();
+            let big = arena.alloc_array::<u64, { 1 << 16 }>(|index| index as u64);
+            assert_eq!(big[(1 << 16) - 1], (1 << 16) - 1);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn recycled_slots_are_reused() {
+    let arena = Arena::new();
+    let tracker = testing::DropTracker::new();
+    let mut state = arena.alloc((0_u64, tracker.token()));
+    let (address, remaining) = (state.as_raw(), state.chunk_remaining());
+
+    for step in 1..100 {
+        state = arena.recycle(state).init_with((step, tracker.token()));
+    }
+    assert_eq!((state.as_raw(), state.0), (address, 99));
+    assert_eq!(state.chunk_remaining(), remaining);
+    assert_eq!(tracker.alive(), 1);
+
+    let small = arena.recycle_as::<_, u32>(state).unwrap().init_with(5);
+    assert_eq!(small.as_raw().cast(), address);
+    assert_eq!(tracker.alive(), 0);
+    assert!(arena.recycle_as::<_, [u64; 4]>(small).is_err());
+}
+
+#[test]
+fn retention_trims() {
+    let arena = Arena::builder().max_free_chunks_per_class(1).build();
+
+    // Fill five chunks, then release them all
+    let values: Vec<_> = (0..5).map(|_| arena.alloc([0_u8; 200])).collect();
+    assert_eq!(arena.stats().chunks, 5);
+    drop(values);
+
+    // One stays current, one is kept free, the rest are gone
+    let stats = arena.stats();
+    assert_eq!(stats.chunks, 2);
+    assert_eq!(stats.trimmed_chunks, 3);
+}
+
+#[test]
+fn huge_page_fallback() {
+    let arena = Arena::builder()
+        .huge_pages(HugePagePolicy::Explicit)
+        .build();
+    let big = arena.alloc_slice_fill_copy(HUGE_PAGE_SIZE, &1_u8);
+    assert!(big.iter().all(|&b| b == 1));
+
+    // Every chunk big enough for huge pages either got explicit ones,
+    // or fell back to asking for transparent ones
+    let wanted: usize = unsafe { &*arena.chunks.get() }
+        .iter()
+        .filter(|list| list.chunk_size() >= HUGE_PAGE_SIZE)
+        .map(|list| list.len())
+        .sum();
+    let stats = arena.stats();
+    assert!(wanted > 0);
+    assert!(stats.huge_page_fallbacks <= wanted);
+    assert!(stats.huge_page_chunks >= wanted - stats.huge_page_fallbacks);
+    #[cfg(not(any(target_os = "linux", windows)))]
+    assert_eq!(
+        (stats.huge_page_chunks, stats.huge_page_fallbacks),
+        (0, wanted)
+    );
+
+    // Transparent huge pages are only advice, nothing falls back
+    // and nothing is reported as huge
+    let arena = Arena::builder()
+        .huge_pages(HugePagePolicy::Transparent)
+        .build();
+    drop(arena.alloc_slice_fill_copy(HUGE_PAGE_SIZE, &1_u8));
+    let stats = arena.stats();
+    assert_eq!((stats.huge_page_chunks, stats.huge_page_fallbacks), (0, 0));
+    assert!(stats.advised_chunks <= wanted);
+    #[cfg(not(target_os = "linux"))]
+    assert_eq!(stats.advised_chunks, 0);
+
+    let stats = Arena::new().stats();
+    assert_eq!(
+        (
+            stats.huge_page_chunks,
+            stats.advised_chunks,
+            stats.huge_page_fallbacks
+        ),
+        (0, 0, 0)
+    );
+}
+
+#[test]
+fn reserve_next_appends() {
+    let arena = Arena::new();
+    arena.reserve_next(2);
+    assert_eq!(arena.max_size(), MIN_BLOCK_SIZE * 2);
+
+    // Appends after the largest class, regardless of what exists
+    arena.reserve_next(1);
+    assert_eq!(arena.max_size(), MIN_BLOCK_SIZE * 4);
+
+    arena.reserve_bytes(MIN_BLOCK_SIZE);
+    assert_eq!(arena.max_size(), MIN_BLOCK_SIZE * 4);
+}
+
+#[test]
+fn shrink_compacts_classes() {
+    let mut arena = Arena::new();
+    drop(arena.alloc_slice_fill_copy(MIN_BLOCK_SIZE * 64, &0_u8));
+
+    let capacity = arena.chunks.get_mut().capacity();
+    assert_eq!(arena.max_size(), MIN_BLOCK_SIZE * 64);
+
+    arena.shrink_to_fit();
+    assert_eq!(arena.max_size(), 0);
+    assert!(arena.chunks.get_mut().capacity() < capacity);
+    assert_eq!(arena.stats().chunks, 0);
+
+    // The arena still works afterwards
+    assert_eq!(*arena.alloc(7_u8), 7);
+}
+
+#[test]
 fn pow() {
     let arena = Arena::new();
     for _ in 0..2 {
@@ -166,3 +1517,818 @@ fn pow() {
             .collect();
     }
 }
+
+#[test]
+fn alloc_in_class_packs() {
+    let arena = Arena::new();
+    let layout = Layout::from_size_align(3072, 8).unwrap();
+    let class = size_to_index(16 * 1024);
+
+    let values: Vec<_> = (0..3)
+        .map(|_| arena.alloc_layout_in_class(layout, class))
+        .collect();
+
+    assert!(values[0].same_chunk(&values[1]));
+    assert!(values[1].same_chunk(&values[2]));
+}
+
+#[test]
+fn linear_size_classes() {
+    // 64 byte steps up to 1 KiB, powers of two above
+    fn slab(index: usize) -> usize {
+        match index {
+            0..=11 => 256 + 64 * index,
+            _ => 1024 << (index - 12),
+        }
+    }
+
+    let arena = Arena::builder().size_classes(slab).build();
+    let value = arena.alloc([1_u8; 257]);
+    assert_eq!(value.placement().class, 1);
+    assert_eq!(arena.max_size(), 320);
+    assert_eq!(value.chunk_remaining(), 320 - 257);
+
+    assert_eq!(arena.alloc([2_u8; 960]).placement().class, 11);
+    assert_eq!(arena.alloc([3_u8; 1025]).placement().class, 13);
+    assert_eq!(arena.max_size(), 2048);
+    assert!(arena.validate().is_ok());
+
+    let error = std::panic::catch_unwind(|| Arena::builder().size_classes(|_| 512)).unwrap_err();
+    assert!(error
+        .downcast_ref::<&str>()
+        .unwrap()
+        .contains("keep growing"));
+}
+
+#[test]
+fn shrinking_size_classes_fail() {
+    fn dip(index: usize) -> usize {
+        match index {
+            0..=2 => 256 << index,
+            3 => 512,
+            _ => 4096 << (index - 4),
+        }
+    }
+
+    let arena = Arena::builder().size_classes(dip).build();
+    let error = arena.try_alloc([0_u8; 2000]).unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::InvalidSizeClass);
+    assert_eq!(error.chunk_size(), 512);
+    assert!(error.to_string().contains("keep growing"));
+
+    // Nothing was created, and the classes below still work
+    assert_eq!(arena.max_size(), 0);
+    assert_eq!(*arena.alloc(1_u8), 1);
+}
+
+#[test]
+fn label_in_reports() {
+    let arena = Arena::builder().label("physics").build();
+    assert_eq!(arena.label(), Some("physics"));
+    assert!(arena.stats().to_string().starts_with("physics: "));
+    assert!(format!("{arena:?}").contains("\"physics\""));
+
+    let error = std::panic::catch_unwind(|| {
+        let arena = Arena::builder().label("physics").build();
+        arena.alloc_slice_fill_default::<u64>(usize::MAX);
+    })
+    .unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("battle-arena[physics]: "));
+}
+
+#[test]
+fn min_align_floor() {
+    for align in [8, 16, 64] {
+        let arena = Arena::builder().min_align(align).build();
+        let is_aligned = |ptr: *const u8| (ptr as usize).is_multiple_of(align);
+
+        for len in 1..20 {
+            let text = "x".repeat(len);
+            assert!(is_aligned(arena.alloc_str(&text).as_raw().cast()));
+            assert!(is_aligned(
+                arena.alloc_slice_copy(text.as_bytes()).as_raw().cast()
+            ));
+            let layout = Layout::from_size_align(len, 1).unwrap();
+            assert!(is_aligned(arena.alloc_layout(layout).as_raw().cast()));
+        }
+    }
+}
+
+#[test]
+fn closures_as_fn_mut() {
+    use std::{cell::Cell, rc::Rc};
+
+    let arena = Arena::new();
+    let calls = Cell::new(0);
+    let log = Rc::new(std::cell::RefCell::new(Vec::new()));
+
+    let mut callbacks: Vec<Boxed<dyn FnMut()>> = Vec::new();
+    callbacks.push(arena.alloc_fn(|| calls.set(calls.get() + 1)));
+    let (last, mut counter) = (&Cell::new(0), 10);
+    callbacks.push(arena.alloc_fn(move || {
+        counter += 1;
+        last.set(counter);
+    }));
+    let shared = log.clone();
+    callbacks.push(arena.alloc_fn(move || shared.borrow_mut().push("called")));
+
+    for callback in &mut callbacks {
+        callback();
+        callback();
+    }
+
+    assert_eq!(calls.get(), 2);
+    assert_eq!(last.get(), 12);
+    assert_eq!(*log.borrow(), ["called", "called"]);
+
+    // Dropping the trait objects drops the captured state
+    drop(callbacks);
+    assert_eq!(Rc::strong_count(&log), 1);
+}
+
+#[test]
+fn slice_from_reader() {
+    use std::io::Cursor;
+
+    let arena = Arena::new();
+    let mut blob = 5_u32.to_le_bytes().to_vec();
+    blob.extend_from_slice(b"hello");
+    let mut reader = Cursor::new(blob);
+
+    let mut prefix = [0; 4];
+    reader.read_exact(&mut prefix).unwrap();
+    let len = u32::from_le_bytes(prefix) as usize;
+    let bytes = arena.alloc_slice_from_reader(&mut reader, len).unwrap();
+    assert_eq!(&*bytes, b"hello");
+
+    let error = arena.alloc_slice_from_reader(&mut reader, 1).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn poison_and_recover() {
+    use std::panic::AssertUnwindSafe;
+
+    let tracker = testing::DropTracker::new();
+    let mut arena = Arena::new();
+
+    // A panicking closure is cleaned up after, the arena is still fine
+    assert!(std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_with(4, |i| {
+            assert!(i < 2, "boom");
+            tracker.token()
+        })
+    }))
+    .is_err());
+    assert_eq!(tracker.alive(), 0);
+    assert!(!arena.is_poisoned());
+
+    // Unwinding out of the chunk bookkeeping is not
+    assert!(std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let _guard = state::PoisonGuard::new(&arena.state);
+        panic!("boom")
+    }))
+    .is_err());
+    assert!(arena.is_poisoned());
+
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| arena.alloc(1_u8))).unwrap_err();
+    let message = error.downcast_ref::<String>().unwrap();
+    assert!(message.contains("arena poisoned"));
+
+    arena.clear_poison();
+    assert!(!arena.is_poisoned());
+    assert_eq!(*arena.alloc(1_u8), 1);
+}
+
+#[test]
+fn try_alloc_reports_failures() {
+    use std::panic::AssertUnwindSafe;
+
+    let tracker = testing::DropTracker::new();
+    let mut arena = Arena::builder().no_growth_panics(false).build();
+    assert_eq!(*arena.try_alloc(1_u8).unwrap(), 1);
+
+    // A new class needs chunks, which the scope refuses
+    let scope = arena.no_growth_scope();
+    let error = arena
+        .try_alloc((tracker.token(), [0_u8; 1000]))
+        .unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::ChunkAllocation);
+    assert!(error.is_growth_forbidden());
+    assert_eq!(tracker.alive(), 0);
+    drop(scope);
+
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let _guard = state::PoisonGuard::new(&arena.state);
+        panic!("boom")
+    }));
+    let error = arena.try_alloc(1_u8).unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::Poisoned);
+
+    arena.clear_poison();
+    assert!(arena.try_alloc([0_u8; 1000]).is_ok());
+}
+
+#[test]
+fn try_alloc_layout_reports_invalid_layouts() {
+    use std::panic::AssertUnwindSafe;
+
+    let arena = Arena::builder().min_align(64).build();
+    let bytes = arena.try_alloc_layout(Layout::new::<[u8; 10]>()).unwrap();
+    assert_eq!(bytes.len(), 10);
+    assert!((bytes.as_raw() as *const u8 as usize).is_multiple_of(64));
+
+    // Raising the alignment overflows the size
+    let layout = Layout::from_size_align(isize::MAX as usize - 10, 1).unwrap();
+    let error = arena.try_alloc_layout(layout).unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::InvalidLayout);
+
+    // The panicking version reports the same error
+    let panic = std::panic::catch_unwind(AssertUnwindSafe(|| arena.alloc_layout(layout)));
+    let message = panic.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains("invalid layout"));
+}
+
+#[test]
+fn failed_try_alloc_reserves_nothing() {
+    let arena = Arena::new();
+    drop(arena.alloc(0_u64));
+    let stats = arena.stats();
+
+    // Classes leading up to the failing one are given back as well
+    let layout = Layout::from_size_align(1 << 40, 1).unwrap();
+    assert!(arena.try_alloc_layout(layout).is_err());
+    assert_eq!(arena.stats().reserved_bytes, stats.reserved_bytes);
+    assert_eq!(arena.stats().chunks, stats.chunks);
+}
+
+#[test]
+fn try_alloc_layout_survives_absurd_layouts() {
+    let arena = Arena::new();
+
+    let error = arena
+        .try_alloc_layout(Layout::from_size_align(usize::MAX / 2, 1).unwrap())
+        .unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::TooLarge);
+    assert_eq!(error.chunk_size(), usize::MAX / 2);
+
+    // Big alignments need chunks just as big
+    let error = arena
+        .try_alloc_layout(Layout::from_size_align(1, 1 << (usize::BITS - 4)).unwrap())
+        .unwrap_err();
+    assert_eq!(error.kind(), AllocErrorKind::ChunkAllocation);
+
+    // A class picked by hand may be aligned to less than the layout
+    let layout = Layout::from_size_align(8, 1 << 20).unwrap();
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        arena.alloc_layout_in_class(layout, 0)
+    }));
+    let message = panic.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains("are only aligned to"), "{message}");
+
+    assert!(!arena.is_poisoned());
+    assert_eq!(*arena.alloc(1_u8), 1);
+}
+
+#[test]
+fn map_slices() {
+    use std::{panic::AssertUnwindSafe, rc::Rc};
+
+    let arena = Arena::new();
+    let words = ["one", "three", "five"];
+    assert_eq!(
+        &*arena.alloc_map_slice(&words, |word| word.len()),
+        &[3, 5, 4]
+    );
+    assert_eq!(
+        &*arena.alloc_map_slice_indexed(&words, |i, word| i + word.len()),
+        &[3, 6, 6]
+    );
+
+    // The mapped prefix is dropped when the closure panics
+    let counter = Rc::new(());
+    let arena = Arena::new();
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_map_slice(&words, |&word| {
+            assert_ne!(word, "five");
+            counter.clone()
+        });
+    }));
+    assert!(result.is_err());
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn reset_small_classes() {
+    let mut arena = Arena::new();
+    let small = arena.alloc(7_u64);
+    let small_ptr = small.as_raw();
+    std::mem::forget(small);
+    let large = arena.alloc_slice_fill_copy(8192, &3_u8);
+    let large_ptr = large.as_raw().cast::<u8>();
+    std::mem::forget(large);
+
+    arena.reset_classes_below(4096);
+
+    // The small class starts over, the large value is untouched
+    assert_eq!(arena.alloc(1_u64).as_raw(), small_ptr);
+    let large = unsafe { std::slice::from_raw_parts(large_ptr, 8192) };
+    assert!(large.iter().all(|&b| b == 3));
+    assert_ne!(
+        arena
+            .alloc_slice_fill_copy(8192, &0_u8)
+            .as_raw()
+            .cast::<u8>(),
+        large_ptr
+    );
+}
+
+#[test]
+fn uninit_slice_is_stale() {
+    let arena = Arena::new();
+    let filled = arena.alloc_slice_fill_copy(100, &0xFF_u8);
+    let address = filled.as_raw().cast::<u8>();
+    drop(filled);
+
+    // Same slot, nothing cleared it
+    let stale = arena.alloc_uninit_slice::<u8>(100);
+    assert_eq!(stale.as_raw().cast::<u8>(), address);
+    let bytes = unsafe { std::slice::from_raw_parts(address, 100) };
+    assert!(bytes.iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn binary_tree_of_nodes() {
+    struct Node<'arena> {
+        value: u32,
+        left: Option<Ref<'arena, Node<'arena>>>,
+        right: Option<Ref<'arena, Node<'arena>>>,
+    }
+
+    fn build<'arena>(arena: &'arena Arena, depth: u32, value: u32) -> Ref<'arena, Node<'arena>> {
+        let child = |offset| (depth > 0).then(|| build(arena, depth - 1, value * 2 + offset));
+        let (left, right) = (child(0), child(1));
+
+        arena.alloc_node(Node { value, left, right })
+    }
+
+    fn in_order(node: &Node<'_>, values: &mut Vec<u32>) {
+        if let Some(left) = &node.left {
+            in_order(left, values);
+        }
+        values.push(node.value);
+        if let Some(right) = &node.right {
+            in_order(right, values);
+        }
+    }
+
+    let arena = Arena::new();
+    let root = build(&arena, 2, 1);
+    let shared = root.left.clone().unwrap();
+
+    let mut values = Vec::new();
+    in_order(&root, &mut values);
+    assert_eq!(values, [4, 2, 5, 1, 6, 3, 7]);
+
+    // Subtrees stay alive through their own handles
+    drop(root);
+    values.clear();
+    in_order(&shared, &mut values);
+    assert_eq!(values, [4, 2, 5]);
+}
+
+#[test]
+fn alloc_n_packs_batches() {
+    #[derive(Debug, PartialEq)]
+    struct Entity {
+        id: usize,
+        position: [f32; 3],
+        health: u32,
+    }
+
+    let arena = Arena::new();
+    let entities = arena.alloc_n(1000, |id| Entity {
+        id,
+        position: [id as f32; 3],
+        health: 100,
+    });
+
+    assert_eq!(entities.len(), 1000);
+    for (id, entity) in entities.iter().enumerate() {
+        assert_eq!(entity.id, id);
+        assert_eq!(entity.position, [id as f32; 3]);
+    }
+
+    let together = entities
+        .windows(2)
+        .filter(|pair| pair[0].same_chunk(&pair[1]))
+        .count();
+    assert!(together >= 990, "{together}");
+
+    assert!(arena.alloc_n(0, |_| 0_u8).is_empty());
+}
+
+#[test]
+fn empty_batches_allocate_nothing() {
+    let arena = Arena::new();
+    let before = arena.stats();
+
+    assert!(arena.alloc_n(0, |_| [0_u8; 1 << 20]).is_empty());
+    let after = arena.stats();
+    assert_eq!(after.chunks, before.chunks);
+    assert_eq!(after.reserved_bytes, before.reserved_bytes);
+}
+
+#[test]
+fn odd_layouts_stay_aligned() {
+    let arena = Arena::new();
+    let mut handles = Vec::new();
+    let mut ranges = Vec::new();
+
+    // Sizes that are not multiples of their alignment, after any predecessor
+    for (round, size) in [1, 3, 7, 13, 29, 61, 125, 250]
+        .into_iter()
+        .cycle()
+        .take(64)
+        .enumerate()
+    {
+        let align = 1 << (round % 7);
+        let boxed = arena.alloc_layout(Layout::from_size_align(size, align).unwrap());
+        let start = boxed.as_raw().cast::<u8>() as usize;
+
+        assert!(
+            start.is_multiple_of(align),
+            "{size} bytes at {start:#x}, align {align}"
+        );
+        ranges.push(start..start + size);
+        handles.push(boxed);
+    }
+
+    // Live allocations never overlap
+    ranges.sort_by_key(|range| range.start);
+    assert!(ranges.windows(2).all(|pair| pair[0].end <= pair[1].start));
+}
+
+#[test]
+fn padded_values_own_their_line() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let arena = Arena::new();
+    let first = arena.alloc_padded(1_u64, 64, 64);
+    let second = arena.alloc_padded(2_u64, 64, 64);
+
+    let (first, second) = (first.as_raw() as usize, second.as_raw() as usize);
+    assert!(first.is_multiple_of(64) && second.is_multiple_of(64));
+    assert!(first.abs_diff(second) >= 64);
+
+    assert!(catch_unwind(AssertUnwindSafe(|| arena.alloc_padded(0_u64, 48, 64))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| arena.alloc_padded(0_u64, 8, 4))).is_err());
+}
+
+#[test]
+fn alloc_copy_pins_once() {
+    let arena = Arena::new();
+    let values: Vec<&u64> = (0..8).map(|i| arena.alloc_copy(i)).collect();
+
+    // One reference for the pin, one for the handle
+    let boxed = arena.alloc(0_u64);
+    let chunk = boxed.as_ptr().chunk;
+    assert!(chunk.is_pinned());
+    assert_eq!(chunk.refs(), 2);
+
+    // The chunk outlives its last handle
+    drop(boxed);
+    assert_eq!(chunk.refs(), 1);
+    let _other = arena.alloc(u64::MAX);
+    assert!(values.iter().map(|&&value| value).eq(0..8));
+}
+
+#[test]
+fn validate_random_operations() {
+    // Small xorshift generator, so failures reproduce
+    let mut seed = 0x2545_F491_4F6C_DD1D_u64;
+    let mut next = move |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % bound as u64) as usize
+    };
+
+    let arena = Arena::builder().max_free_chunks_per_class(2).build();
+    let mut handles = Vec::new();
+    for _ in 0..2000 {
+        match next(4) {
+            0 if !handles.is_empty() => {
+                handles.swap_remove(next(handles.len()));
+            }
+            1 => handles.truncate(handles.len() / 2),
+            _ => {
+                let len = 1 + next(1500);
+                handles.push(arena.alloc_slice_fill_copy(len, &0_u8));
+            }
+        }
+
+        arena.validate().unwrap();
+    }
+
+    drop(handles);
+    arena.validate().unwrap();
+}
+
+#[test]
+fn validate_reports_corruption() {
+    let arena = Arena::new();
+    let value = arena.alloc(0_u8);
+    let index = value.as_ptr().chunk.index;
+    value.as_ptr().chunk.toggle_free();
+
+    assert_eq!(
+        arena.validate(),
+        Err(CorruptionError::CurrentAndFree { class: 0, index })
+    );
+    value.as_ptr().chunk.toggle_free();
+    arena.validate().unwrap();
+}
+
+#[test]
+fn retired_chunk_freed_after_last_value() {
+    let arena = Arena::new();
+
+    // A stays in C after B moves the class on to C'
+    let a = arena.alloc([1_u8; 200]);
+    let chunk = a.placement().chunk;
+    arena.validate().unwrap();
+
+    let b = arena.alloc([2_u8; 100]);
+    assert_ne!(b.placement().chunk, chunk);
+    assert!(!a.as_ptr().chunk.is_current());
+    arena.validate().unwrap();
+
+    // C is neither current nor free, only A keeps it alive
+    drop(a);
+    arena.validate().unwrap();
+
+    // C was freed, and is the next chunk handed out
+    let c = testing::assert_no_chunk_growth(&arena, || arena.alloc([3_u8; 200]));
+    assert_eq!(c.placement().chunk, chunk);
+    arena.validate().unwrap();
+
+    drop((b, c));
+    arena.validate().unwrap();
+}
+
+#[test]
+fn threaded_fill_panic_drops_finished_parts() {
+    use std::{
+        panic::AssertUnwindSafe,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static MADE: AtomicUsize = AtomicUsize::new(0);
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted;
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let arena = Arena::new();
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_slice_fill_with_threads(100, 4, |i| {
+            if i == 60 {
+                panic!("fill failed");
+            }
+            MADE.fetch_add(1, Ordering::Relaxed);
+            Counted
+        });
+    }))
+    .unwrap_err();
+
+    // Three parts finished, and the one that panicked got ten values in
+    assert_eq!(MADE.load(Ordering::Relaxed), 85);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 85);
+    assert!(!arena.is_poisoned());
+}
+
+#[test]
+fn threaded_fill_matches_sequential() {
+    let arena = Arena::new();
+    let work = |i: usize| {
+        (i as u64)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            .rotate_left(17)
+    };
+
+    let sequential = arena.alloc_slice_fill_with(100_000, work);
+    let threaded = arena.alloc_slice_fill_with_threads(100_000, 4, work);
+    assert_eq!(&*threaded, &*sequential);
+
+    // More threads than values, and no threads at all
+    assert_eq!(
+        &*arena.alloc_slice_fill_with_threads(3, 8, |i| i),
+        &[0, 1, 2]
+    );
+    assert_eq!(
+        &*arena.alloc_slice_fill_with_threads(3, 0, |i| i),
+        &[0, 1, 2]
+    );
+}
+
+#[test]
+fn pair_handles_are_independent() {
+    let arena = Arena::new();
+    let (mut queue, mut done) = arena.alloc_pair(vec![1, 2, 3], false);
+    assert!(queue.same_chunk(&done));
+
+    while let Some(job) = queue.pop() {
+        assert!(job > 0);
+    }
+    *done = true;
+    drop(queue);
+    assert!(*done);
+}
+
+#[test]
+fn failed_pair_drops_the_first_value() {
+    use std::{panic::AssertUnwindSafe, rc::Rc};
+
+    let arena = Arena::builder().no_growth_panics(true).build();
+    // The class starts out with four chunks, leave room for the first value only
+    let fillers: Vec<_> = (0..4).map(|_| arena.alloc([0_u8; 150])).collect();
+    let _scope = arena.no_growth_scope();
+
+    let first = Rc::new(());
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_pair((first.clone(), [0_u8; 96]), [0_u8; 100]);
+    }))
+    .unwrap_err();
+
+    assert_eq!(Rc::strong_count(&first), 1);
+    drop(fillers);
+}
+
+#[test]
+fn slices_of_refs_to_nodes() {
+    enum Expr<'a> {
+        Literal(i64),
+        Sum(&'a [&'a Expr<'a>]),
+    }
+
+    fn eval(expr: &Expr<'_>) -> i64 {
+        match expr {
+            Expr::Literal(value) => *value,
+            Expr::Sum(terms) => terms.iter().map(|term| eval(term)).sum(),
+        }
+    }
+
+    let arena = Arena::new();
+    let literals: Vec<&Expr<'_>> = (1..=4)
+        .map(|value| &*arena.alloc(Expr::Literal(value)).leak())
+        .collect();
+
+    let terms = arena.alloc_slice_of_refs(literals.iter().copied()).leak();
+    let inner = &*arena.alloc(Expr::Sum(terms)).leak();
+
+    // Iterators without an exact length work too
+    let outer_terms = arena.alloc_slice_of_refs(
+        std::iter::once(inner).chain(literals.iter().copied().filter(|expr| eval(expr) > 2)),
+    );
+    assert_eq!(outer_terms.len(), 3);
+    let outer = arena.alloc(Expr::Sum(outer_terms.leak()));
+    assert_eq!(eval(&outer), 10 + 3 + 4);
+
+    let names = arena.alloc_slice_of_refs(["a", "bc"].map(|name| &*arena.alloc_str(name).leak()));
+    assert_eq!(&*names, &["a", "bc"]);
+}
+
+#[test]
+fn slice_and_rest_of_chunk() {
+    let arena = Arena::new();
+    let (slice, mut rest) = arena.alloc_slice_and_rest(4, |i| i as u32);
+    assert_eq!(&*slice, &[0, 1, 2, 3]);
+    assert_eq!(rest.len(), MIN_BLOCK_SIZE - 16);
+    assert_eq!(slice.chunk_remaining(), 0);
+    assert!(slice.same_chunk(&rest));
+
+    // Pack a second value into the rest by hand
+    for (slot, byte) in rest.iter_mut().zip(b"packed") {
+        slot.write(*byte);
+    }
+    let packed = unsafe { std::slice::from_raw_parts(rest.as_raw().cast::<u8>(), 6) };
+    assert_eq!(packed, b"packed");
+
+    // The rest keeps the chunk alive on its own
+    let chunk = slice.as_ptr().chunk;
+    drop(slice);
+    assert_eq!(chunk.refs(), 1);
+    drop(rest);
+
+    let (full, rest) = arena.alloc_slice_and_rest(MIN_BLOCK_SIZE, |_| 0_u8);
+    assert_eq!(full.len(), MIN_BLOCK_SIZE);
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn rest_of_chunk_is_reported() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let arena = Arena::builder().warn_large_alloc(100).build();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let sink = seen.clone();
+    arena.on_warning(move |warning| {
+        if let ArenaWarning::LargeAlloc { layout, .. } = warning {
+            sink.borrow_mut().push(layout.size());
+        }
+    });
+
+    let (_slice, rest) = arena.alloc_slice_and_rest(4, |i| i as u32);
+    assert_eq!(*seen.borrow(), [rest.len()]);
+}
+
+#[cfg(all(feature = "resident", unix))]
+#[test]
+fn resident_bytes_follow_touches() {
+    let arena = Arena::new();
+    arena.reserve_bytes(16 << 20);
+    let reserved = arena.stats().reserved_bytes;
+    let untouched = arena.resident_bytes();
+    assert!(untouched < reserved / 16, "{untouched} of {reserved}");
+
+    let mut big = arena.alloc_uninit_slice::<u8>(8 << 20);
+    big.fill(std::mem::MaybeUninit::new(1));
+    assert!(arena.resident_bytes() >= untouched + (8 << 20));
+}
+
+#[cfg(all(feature = "madvise", feature = "resident", unix))]
+#[test]
+fn free_pages_are_released() {
+    let arena = Arena::new();
+    let mut values: Vec<_> = (0..4)
+        .map(|_| arena.alloc_slice_fill_copy(1 << 20, &1_u8))
+        .collect();
+    let chunks = arena.stats().chunks;
+    values.truncate(1);
+
+    // Freed chunks keep their pages until they are released,
+    // except for the current one which is left alone
+    let before = arena.resident_bytes();
+    assert!(arena.release_free_pages() >= 2 << 20);
+    assert!(arena.resident_bytes() <= before - (2 << 20));
+    assert_eq!(values[0][1000], 1);
+
+    // The chunks are still there, ready for reuse
+    assert_eq!(arena.stats().chunks, chunks);
+    let reused = arena.alloc_slice_fill_copy(1 << 20, &2_u8);
+    assert_eq!(reused[1 << 19], 2);
+    arena.validate().unwrap();
+}
+
+#[cfg(all(feature = "numa", target_os = "linux"))]
+#[test]
+fn chunks_land_on_their_node() {
+    // From `linux/mempolicy.h`
+    const MPOL_F_NODE: libc::c_int = 1;
+    const MPOL_F_ADDR: libc::c_int = 2;
+
+    let mut nodes: Vec<u32> = std::fs::read_dir("/sys/devices/system/node")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .ok()?
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    nodes.sort();
+    if nodes.len() < 2 {
+        return;
+    }
+
+    for node in nodes {
+        let arena = Arena::with_numa_node(node);
+        let values = arena.alloc_slice_fill_copy(1 << 20, &1_u8);
+
+        // Ask which node the touched page is on
+        let mut placed: libc::c_int = -1;
+        let found = unsafe {
+            libc::syscall(
+                libc::SYS_get_mempolicy,
+                &mut placed,
+                std::ptr::null_mut::<libc::c_ulong>(),
+                0,
+                values.as_raw().cast::<u8>(),
+                MPOL_F_NODE | MPOL_F_ADDR,
+            )
+        };
+        assert_eq!(found, 0);
+        assert_eq!(placed, node as libc::c_int);
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    alloc::Layout,
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    num::NonZeroU32,
+};
+
+use bytemuck::{CheckedBitPattern, NoUninit, Pod, PodInOption, ZeroableInOption};
+
+use crate::{chunk::Backing, fail::fail, Arena};
+
+/// A 4 byte handle to a value, as its offset into the arena's reserved address space.
+///
+/// Only arenas with [`ArenaBuilder::reserve_address_space`](crate::ArenaBuilder::reserve_address_space)
+/// can make them, for values that land in the reservation, within its first 4 GiB.
+/// Offsets stay the same from run to run for the same allocations, so they can be
+/// written out as bytes and read back. `Option<ArenaOffset<T>>` is 4 bytes as well.
+#[repr(transparent)]
+pub struct ArenaOffset<T> {
+    /// The offset plus one
+    bits: NonZeroU32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ArenaOffset<T> {
+    /// Offset of the value from the start of the reservation.
+    #[inline]
+    pub const fn get(self) -> u32 {
+        self.bits.get() - 1
+    }
+}
+
+impl<T> Clone for ArenaOffset<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaOffset<T> {}
+
+impl<T> PartialEq for ArenaOffset<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl<T> Eq for ArenaOffset<T> {}
+
+impl<T> Hash for ArenaOffset<T> {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bits.hash(state)
+    }
+}
+
+impl<T> Debug for ArenaOffset<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ArenaOffset").field(&self.get()).finish()
+    }
+}
+
+// A transparent `NonZeroU32`
+unsafe impl<T: 'static> NoUninit for ArenaOffset<T> {}
+unsafe impl<T> ZeroableInOption for ArenaOffset<T> {}
+unsafe impl<T: 'static> PodInOption for ArenaOffset<T> {}
+unsafe impl<T: 'static> CheckedBitPattern for ArenaOffset<T> {
+    type Bits = u32;
+
+    #[inline]
+    fn is_valid_bit_pattern(bits: &u32) -> bool {
+        *bits != 0
+    }
+}
+
+impl Arena {
+    /// Allocate a value for the rest of the arena's life, behind an [`ArenaOffset`].
+    ///
+    /// Like [`Arena::alloc_copy`], the chunk holding it is pinned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value did not land in the reserved address space,
+    /// because there is none or it is used up, or if it is past the first 4 GiB.
+    #[track_caller]
+    pub fn alloc_offset<T: Pod>(&self, value: T) -> ArenaOffset<T> {
+        let layout = Layout::new::<T>();
+        let ptr = self.allocate(layout).cast::<T>();
+
+        let offset = match &self.state.reservation {
+            Some(reservation) if ptr.chunk.backing == Backing::Reserved => {
+                ptr.as_raw() as usize - reservation.start().as_ptr() as usize
+            }
+            _ => fail(
+                self.label(),
+                Some(layout),
+                None,
+                Some(ptr.chunk),
+                "offset handles need chunks from the reserved address space",
+            ),
+        };
+        let Some(bits) = u32::try_from(offset + 1).ok().and_then(NonZeroU32::new) else {
+            fail(
+                self.label(),
+                Some(layout),
+                None,
+                Some(ptr.chunk),
+                format_args!("offset {offset} does not fit in 32 bits"),
+            )
+        };
+
+        unsafe {
+            ptr.write(value);
+            ptr.chunk.pin();
+        }
+
+        ArenaOffset {
+            bits,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Address of the value at `offset`, after checking that it is aligned
+    /// and within the part of the reservation that chunks were carved from.
+    #[track_caller]
+    fn offset_address<T>(&self, offset: ArenaOffset<T>) -> *mut T {
+        let Some(reservation) = &self.state.reservation else {
+            fail(
+                self.label(),
+                None,
+                None,
+                None,
+                "offset handles need a reserved address space",
+            )
+        };
+
+        let start = offset.get() as usize;
+        let in_bounds = start + size_of::<T>() <= reservation.used();
+        if !in_bounds || !start.is_multiple_of(align_of::<T>()) {
+            fail(
+                self.label(),
+                Some(Layout::new::<T>()),
+                None,
+                None,
+                format_args!("offset {start} does not point at a value of the arena"),
+            )
+        }
+
+        unsafe { reservation.start().as_ptr().add(start).cast() }
+    }
+
+    /// Get the value behind an offset from [`Arena::alloc_offset`].
+    ///
+    /// # Safety
+    ///
+    /// Only bounds and alignment are checked. The offset must come from
+    /// [`Arena::alloc_offset`] on this arena, or on one that made the same
+    /// allocations in an earlier run, and its chunk must not have been
+    /// reclaimed since, such as by [`Arena::reset_classes_below`].
+    /// Offsets forged from bytes, or taken from another arena, may point at
+    /// uninitialized memory or at values borrowed mutably elsewhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset is out of bounds or misaligned.
+    #[track_caller]
+    pub unsafe fn resolve<T: Pod>(&self, offset: ArenaOffset<T>) -> &T {
+        &*self.offset_address(offset)
+    }
+
+    /// Like [`Arena::resolve`], for changing the value.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Arena::resolve`].
+    #[track_caller]
+    pub unsafe fn resolve_mut<T: Pod>(&mut self, offset: ArenaOffset<T>) -> &mut T {
+        &mut *self.offset_address(offset)
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn offsets_link_nodes() {
+    use bytemuck::Zeroable;
+    use std::panic::AssertUnwindSafe;
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Node {
+        value: u32,
+        next: Option<ArenaOffset<Node>>,
+    }
+    unsafe impl Zeroable for Node {}
+    unsafe impl Pod for Node {}
+
+    assert_eq!(size_of::<Option<ArenaOffset<Node>>>(), 4);
+
+    let mut arena = Arena::builder().reserve_address_space(1 << 20).build();
+    let mut head = None;
+    for value in 0..100 {
+        head = Some(arena.alloc_offset(Node { value, next: head }));
+    }
+
+    let mut sum = 0;
+    let mut next = head;
+    while let Some(offset) = next {
+        let node = unsafe { arena.resolve(offset) };
+        sum += node.value;
+        next = node.next;
+    }
+    assert_eq!(sum, 99 * 100 / 2);
+
+    let head = head.unwrap();
+    unsafe {
+        arena.resolve_mut(head).value = 1000;
+        assert_eq!(arena.resolve(head).value, 1000);
+    }
+
+    // Offsets survive a trip through bytes
+    let bytes = bytemuck::bytes_of(&head).to_vec();
+    let read: ArenaOffset<Node> = *bytemuck::checked::from_bytes(&bytes);
+    assert_eq!(read, head);
+    assert!(bytemuck::checked::try_from_bytes::<ArenaOffset<Node>>(&[0; 4]).is_err());
+
+    let far = ArenaOffset::<Node> {
+        bits: NonZeroU32::new(u32::MAX).unwrap(),
+        _marker: PhantomData,
+    };
+    let error = std::panic::catch_unwind(|| Arena::new().alloc_offset(0_u32)).unwrap_err();
+    assert!(error
+        .downcast_ref::<String>()
+        .unwrap()
+        .contains("reserved address space"));
+    let error = std::panic::catch_unwind(AssertUnwindSafe(|| unsafe { arena.resolve(far) }.value))
+        .unwrap_err();
+    assert!(error
+        .downcast_ref::<String>()
+        .unwrap()
+        .contains("does not point"));
+}
@@ -0,0 +1,126 @@
+use std::{mem, slice};
+
+use bytemuck::Pod;
+
+use crate::{ptr::Boxed, Arena};
+
+impl Arena {
+    /// Copy the values in a size class into one flat buffer,
+    /// for [`Arena::import_class`] to load back later, such as from a file.
+    ///
+    /// The class must only hold values of `T`, which have to be plain old data:
+    /// pointers and references would not mean anything once loaded back.
+    /// The used bytes of each chunk are copied as is, so values that were
+    /// dropped while newer values in their chunk were alive are included.
+    /// Values come out in allocation order within each chunk,
+    /// but chunks follow the order they are kept in.
+    ///
+    /// # Safety
+    ///
+    /// The used bytes of every chunk in the class are read directly, so they
+    /// must all be initialized values of `T`, with no padding from other
+    /// layouts in between. No value in the class may be borrowed mutably during
+    /// the call, such as through a live [`RefMut`](crate::ptr::RefMut) or [`Boxed`]
+    /// being written to, and no chunk of the class may be checked out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a chunk of the class holds bytes that do not add up to values of `T`.
+    pub unsafe fn export_class<T: Pod>(&self, class_index: usize) -> Vec<u8> {
+        let size = mem::size_of::<T>();
+        let chunks = unsafe { &*self.chunks.get() };
+        let Some(list) = chunks.get(class_index).filter(|_| size > 0) else {
+            return Vec::new();
+        };
+
+        let mut bytes = Vec::new();
+        for chunk in list.chunks() {
+            let bump = chunk.bump.get().as_ptr();
+            let used = chunk.size - chunk.remaining();
+            assert!(
+                used.is_multiple_of(size),
+                "size class {class_index} holds values other than `{}`",
+                std::any::type_name::<T>()
+            );
+
+            // Values are bumped downwards, so the oldest is at the top.
+            // The caller guarantees the bytes are initialized and not borrowed mutably
+            let used = unsafe { slice::from_raw_parts(bump, used) };
+            for value in used.chunks_exact(size).rev() {
+                bytes.extend_from_slice(value);
+            }
+        }
+
+        bytes
+    }
+
+    /// Allocate the values of a buffer from [`Arena::export_class`], in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer does not hold a whole number of values.
+    #[track_caller]
+    pub fn import_class<T: Pod>(&self, bytes: &[u8]) -> Vec<Boxed<'_, T>> {
+        let size = mem::size_of::<T>().max(1);
+        assert!(
+            bytes.len().is_multiple_of(size),
+            "buffer of {} bytes does not hold a whole number of `{}`",
+            bytes.len(),
+            std::any::type_name::<T>()
+        );
+
+        bytes
+            .chunks_exact(size)
+            .map(|value| self.alloc(bytemuck::pod_read_unaligned(value)))
+            .collect()
+    }
+}
+
+#[test]
+fn export_import_round_trip() {
+    use crate::size_to_index;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Particle {
+        position: [f32; 2],
+        id: u32,
+        flags: u32,
+    }
+    unsafe impl bytemuck::Zeroable for Particle {}
+    unsafe impl Pod for Particle {}
+
+    let arena = Arena::new();
+    let class = size_to_index(mem::size_of::<Particle>());
+    let particles: Vec<_> = (0..40)
+        .map(|id| Particle {
+            position: [id as f32, -(id as f32)],
+            id,
+            flags: id % 3,
+        })
+        .collect();
+    let handles: Vec<_> = particles.iter().map(|&p| arena.alloc(p).leak()).collect();
+    assert!(handles.len() * mem::size_of::<Particle>() > crate::MIN_BLOCK_SIZE);
+
+    // Only leaked particles are in the class, and nothing writes to them
+    let bytes = unsafe { arena.export_class::<Particle>(class) };
+    assert_eq!(bytes.len(), 40 * mem::size_of::<Particle>());
+
+    let other = Arena::new();
+    let mut imported: Vec<_> = other
+        .import_class::<Particle>(&bytes)
+        .iter()
+        .map(|p| **p)
+        .collect();
+    imported.sort_by_key(|p| p.id);
+    assert_eq!(imported, particles);
+
+    // Each chunk keeps its values in order
+    let first_chunk = &bytes[..8 * mem::size_of::<Particle>()];
+    let ids: Vec<u32> = first_chunk
+        .chunks_exact(mem::size_of::<Particle>())
+        .map(|value| bytemuck::pod_read_unaligned::<Particle>(value).id)
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    assert!(unsafe { other.export_class::<Particle>(class + 1) }.is_empty());
+}
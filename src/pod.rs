@@ -0,0 +1,94 @@
+use std::alloc::Layout;
+
+use crate::{Arena, CorruptionError};
+
+/// An arena for `Copy` values, which never need dropping.
+///
+/// Values are handed out as plain references, bumped straight out of the
+/// chunk lists without reference counting or drop bookkeeping. Every chunk
+/// it bumps is pinned, so nothing is freed on its own, and the chunks are all
+/// deallocated when the arena drops.
+#[derive(Debug, Default)]
+pub struct PodArena {
+    arena: Arena,
+}
+
+impl PodArena {
+    /// Create a new empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bump a layout out of the chunk list of its class.
+    #[inline]
+    #[track_caller]
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        // Chunks are aligned to their size, so big alignments need big chunks
+        let list = self.arena.list_for_size(layout.size().max(layout.align()));
+        let ptr = list.allocate(layout);
+
+        // No handle ever counts a reference, pinning keeps a chunk
+        // that stops being current from being reset or reused
+        ptr.chunk.pin();
+        ptr.as_raw()
+    }
+
+    /// Allocate a value, living as long as the arena.
+    #[inline]
+    #[track_caller]
+    pub fn alloc<T: Copy>(&self, value: T) -> &T {
+        let ptr = self.allocate(Layout::new::<T>()).cast::<T>();
+
+        unsafe {
+            ptr.write(value);
+            &*ptr
+        }
+    }
+
+    /// Allocate a copy of a slice, living as long as the arena.
+    #[track_caller]
+    pub fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> &[T] {
+        let layout = self.arena.slice_layout::<T>(source.len());
+        let ptr = self.allocate(layout).cast::<T>();
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(source.as_ptr(), ptr, source.len());
+            std::slice::from_raw_parts(ptr, source.len())
+        }
+    }
+
+    /// Check the internal bookkeeping of every size class,
+    /// like [`Arena::validate`].
+    pub fn validate(&self) -> Result<(), CorruptionError> {
+        self.arena.validate()
+    }
+
+    /// How many chunks were allocated.
+    #[inline]
+    pub fn chunks(&self) -> usize {
+        self.arena.stats().chunks
+    }
+}
+
+#[test]
+fn pod_values_outlive_their_chunks() {
+    let arena = PodArena::new();
+    let values: Vec<&u64> = (0..10_000).map(|n| arena.alloc(n)).collect();
+    let slices: Vec<&[u16]> = (0..100)
+        .map(|n| arena.alloc_slice_copy(&[n; 300]))
+        .collect();
+
+    assert!(arena.chunks() > 4);
+    assert!(values
+        .iter()
+        .enumerate()
+        .all(|(i, &&value)| value == i as u64));
+    assert!(slices
+        .iter()
+        .enumerate()
+        .all(|(i, slice)| slice.iter().all(|&n| n == i as u16)));
+    assert_eq!(*arena.alloc(()), ());
+
+    // Chunks that stopped being current are pinned, not orphaned
+    assert_eq!(arena.validate(), Ok(()));
+}
@@ -0,0 +1,91 @@
+use crate::Arena;
+
+/// How many chunks each size class of an arena needed at its peak,
+/// to prewarm another arena with.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapacityProfile {
+    /// Every size class, smallest first.
+    pub classes: Vec<ClassCapacity>,
+}
+
+/// The peak chunk count of a size class.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassCapacity {
+    /// Size of each chunk of the class.
+    pub chunk_size: usize,
+    /// Most chunks the class had at once, not counting emergency chunks.
+    pub chunks: usize,
+}
+
+impl Arena {
+    /// Capture how many chunks each size class needed at its peak.
+    pub fn capacity_profile(&self) -> CapacityProfile {
+        let chunks = unsafe { &*self.chunks.get() };
+
+        CapacityProfile {
+            classes: chunks
+                .iter()
+                .map(|list| ClassCapacity {
+                    chunk_size: list.chunk_size(),
+                    chunks: list.peak_chunks(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Allocate chunks up front until every size class has
+    /// as many as the profile says it needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the profile was captured with other size classes.
+    #[track_caller]
+    pub fn prewarm_from_profile(&self, profile: &CapacityProfile) {
+        for (index, class) in profile.classes.iter().enumerate() {
+            if class.chunks == 0 {
+                continue;
+            }
+
+            let list = self.list_for_index(index);
+            assert_eq!(
+                list.chunk_size(),
+                class.chunk_size,
+                "the profile was captured with other size classes"
+            );
+            list.reserve(class.chunks.saturating_sub(list.len()));
+        }
+    }
+}
+
+#[test]
+fn prewarmed_arenas_do_not_grow() {
+    let workload = |arena: &Arena| {
+        let small: Vec<_> = (0..40)
+            .map(|n| arena.alloc_slice_fill_copy(100, &n))
+            .collect();
+        let large = arena.alloc_slice_fill_copy(20_000, &0_u8);
+        drop(small);
+        let text: Vec<_> = (0..10)
+            .map(|_| arena.alloc_str(&"x".repeat(3000)))
+            .collect();
+        (large.len(), text.len())
+    };
+
+    let profiled = Arena::new();
+    workload(&profiled);
+    let profile = profiled.capacity_profile();
+    assert!(profile.classes.iter().any(|class| class.chunks > 4));
+
+    #[cfg(feature = "serde")]
+    let profile: CapacityProfile =
+        serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+
+    let warm = Arena::new();
+    warm.prewarm_from_profile(&profile);
+    let ((large, text), delta) = warm.measure(|| workload(&warm));
+    assert_eq!((large, text), (20_000, 10));
+    assert_eq!(delta.chunks, 0);
+    assert_eq!(warm.capacity_profile(), profile);
+}
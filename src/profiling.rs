@@ -0,0 +1,149 @@
+use std::{cell::RefCell, collections::HashMap, sync::OnceLock};
+
+use crate::chunk::Chunk;
+
+/// Receives the allocations of every arena, to forward them to a profiler.
+///
+/// Arena allocations come out of chunks the global allocator already handed over,
+/// so profilers hooking it only see whole chunks. Install one of these with
+/// [`set_alloc_profiler`] to see the values in them as well.
+pub trait AllocProfiler: Sync {
+    /// `size` bytes at `ptr` were bumped out of a chunk.
+    fn alloc(&self, ptr: *const u8, size: usize);
+
+    /// The allocation at `ptr` is gone.
+    ///
+    /// Values are freed a chunk at a time, so this is called once their chunk
+    /// is reset, or once the memory is handed out again.
+    fn free(&self, ptr: *const u8, size: usize);
+}
+
+/// The profiler every arena reports to.
+static PROFILER: OnceLock<&'static dyn AllocProfiler> = OnceLock::new();
+
+/// Report the allocations of every arena to `profiler` from now on.
+///
+/// Like a logger, this can only be set once. Returns `false` if a profiler
+/// was already installed, in which case `profiler` is ignored.
+pub fn set_alloc_profiler(profiler: &'static dyn AllocProfiler) -> bool {
+    PROFILER.set(profiler).is_ok()
+}
+
+/// Forwards arena allocations to a running [Tracy](https://github.com/wolfpld/tracy)
+/// client, as a memory pool of its own.
+#[cfg(feature = "profiling-tracy")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracyProfiler;
+
+#[cfg(feature = "profiling-tracy")]
+impl TracyProfiler {
+    /// Name of the memory pool in Tracy.
+    const POOL: &'static std::ffi::CStr = c"battle-arena";
+}
+
+#[cfg(feature = "profiling-tracy")]
+impl AllocProfiler for TracyProfiler {
+    fn alloc(&self, ptr: *const u8, size: usize) {
+        if tracy_client::Client::is_running() {
+            unsafe {
+                tracy_client::sys::___tracy_emit_memory_alloc_named(
+                    ptr.cast(),
+                    size,
+                    1,
+                    Self::POOL.as_ptr(),
+                )
+            }
+        }
+    }
+
+    fn free(&self, ptr: *const u8, _size: usize) {
+        if tracy_client::Client::is_running() {
+            unsafe {
+                tracy_client::sys::___tracy_emit_memory_free_named(
+                    ptr.cast(),
+                    1,
+                    Self::POOL.as_ptr(),
+                )
+            }
+        }
+    }
+}
+
+/// Forwards arena allocations to [dhat](https://docs.rs/dhat) as ad hoc events,
+/// weighted by their size.
+///
+/// dhat has no way to report heap allocations besides its global allocator,
+/// so frees are not reported, and the events only show up in ad hoc profiles.
+#[cfg(feature = "profiling-dhat")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DhatProfiler;
+
+#[cfg(feature = "profiling-dhat")]
+impl AllocProfiler for DhatProfiler {
+    fn alloc(&self, _ptr: *const u8, size: usize) {
+        dhat::ad_hoc_event(size);
+    }
+
+    fn free(&self, _ptr: *const u8, _size: usize) {}
+}
+
+/// Allocations reported to the profiler, which still have to be freed.
+#[derive(Debug, Default)]
+pub(crate) struct Profiled {
+    /// Address and size of the allocations in each chunk, by chunk start,
+    /// in the order they were bumped
+    chunks: RefCell<HashMap<usize, Vec<(usize, usize)>>>,
+}
+
+impl Profiled {
+    /// Report an allocation of `size` bytes at `ptr`, bumped from `bump` down.
+    #[inline]
+    pub fn alloc(&self, chunk: Chunk, ptr: *const u8, size: usize, bump: *const u8) {
+        let Some(profiler) = PROFILER.get() else {
+            return;
+        };
+
+        let mut chunks = self.chunks.borrow_mut();
+        let live = chunks.entry(chunk.start.as_ptr() as usize).or_default();
+
+        // Allocations below the bump pointer were given back without a reset
+        while let Some(&(address, size)) = live.last() {
+            if address >= bump as usize {
+                break;
+            }
+            profiler.free(address as *const u8, size);
+            live.pop();
+        }
+
+        profiler.alloc(ptr, size);
+        live.push((ptr as usize, size));
+    }
+
+    /// Free every allocation in a chunk that was reset.
+    #[inline]
+    pub fn release_chunk(&self, chunk: Chunk) {
+        let Some(profiler) = PROFILER.get() else {
+            return;
+        };
+
+        let live = self
+            .chunks
+            .borrow_mut()
+            .remove(&(chunk.start.as_ptr() as usize));
+        for (address, size) in live.into_iter().flatten() {
+            profiler.free(address as *const u8, size);
+        }
+    }
+}
+
+impl Drop for Profiled {
+    fn drop(&mut self) {
+        let Some(profiler) = PROFILER.get() else {
+            return;
+        };
+
+        for (address, size) in self.chunks.get_mut().drain().flat_map(|(_, live)| live) {
+            profiler.free(address as *const u8, size);
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use std::{
-    fmt::{Debug, Display},
+    fmt::{Debug, Display, Pointer},
     mem::{ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
+    ptr::{self, slice_from_raw_parts_mut, NonNull},
 };
 
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, PodCastError};
+
 use super::{Ptr, Ref, RefMut};
+use crate::{checked_array_size, drops::DropKey, Arena, Placement};
 
 #[repr(transparent)]
 pub struct Boxed<'chunk, T: ?Sized>(RefMut<'chunk, T>);
@@ -50,22 +55,198 @@ impl<'chunk, T: ?Sized> Boxed<'chunk, T> {
         unsafe { Ref::from_ptr(self.into_ptr()) }
     }
 
+    /// See [`Ref<T>::chunk_remaining()`] for details.
+    #[inline]
+    pub fn chunk_remaining(&self) -> usize {
+        self.0.chunk_remaining()
+    }
+
+    /// See [`Ref<T>::chunk_fill_ratio()`] for details.
+    #[inline]
+    pub fn chunk_fill_ratio(&self) -> f32 {
+        self.0.chunk_fill_ratio()
+    }
+
+    /// See [`Ref<T>::same_chunk()`] for details.
+    #[inline]
+    pub fn same_chunk<U: ?Sized>(&self, other: &Boxed<'_, U>) -> bool {
+        self.0.same_chunk(&other.0)
+    }
+
+    /// See [`Ref<T>::placement()`] for details.
+    #[inline]
+    pub fn placement(&self) -> Placement {
+        self.0.placement()
+    }
+
     /// See [`Ref<T>::leak()`] for details.
     #[inline]
     pub fn leak(self) -> &'chunk mut T {
         self.into_mut().leak()
     }
+
+    /// Checks if this value is the most recent allocation of its chunk.
+    #[inline]
+    fn at_tip(&self) -> bool {
+        self.as_ptr().chunk.bump.get().as_ptr() == self.as_raw().cast()
+    }
 }
 
 impl<'chunk, T> Boxed<'chunk, MaybeUninit<T>> {
     #[inline]
     pub fn init_with(self, value: T) -> Boxed<'chunk, T> {
-        Boxed::from_mut(self.into_mut().init_with(value))
+        Boxed::from_mut(self.into_mut().init_with(value)).registered()
     }
 
+    /// See [`RefMut<T>::assume_init()`] for details.
+    ///
+    /// # Safety
+    ///
+    /// The value must have been initialized.
     #[inline]
     pub unsafe fn assume_init(self) -> Boxed<'chunk, T> {
-        Boxed::from_mut(self.into_mut().assume_init())
+        Boxed::from_mut(self.into_mut().assume_init()).registered()
+    }
+}
+
+impl<'chunk, T> Boxed<'chunk, T> {
+    /// Hand the drop glue of a value that was just initialized
+    /// to the arena, if destructors run on teardown.
+    #[inline]
+    fn registered(self) -> Self {
+        let ptr = self.as_ptr();
+        if ptr.chunk.state.config.teardown_drops {
+            let key = DropKey::of(ptr.as_raw());
+            ptr.chunk
+                .state
+                .drops
+                .register(ptr.chunk, ptr.as_raw(), 1, key);
+        }
+
+        self
+    }
+}
+
+impl<'chunk, T> Boxed<'chunk, [MaybeUninit<T>]> {
+    /// Keep the first `len` values, assuming they are initialized.
+    ///
+    /// # Safety
+    ///
+    /// The first `len` values must have been initialized,
+    /// and `len` must not exceed the length of the slice.
+    #[inline]
+    pub unsafe fn assume_init_prefix(self, len: usize) -> Boxed<'chunk, [T]> {
+        debug_assert!(len <= self.len());
+        let ptr = self.into_ptr().cast::<T>();
+
+        Boxed::from_ptr(ptr.slice(len))
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'chunk> Boxed<'chunk, [u8]> {
+    /// Reinterpret these bytes as a slice of `T`, without copying.
+    ///
+    /// Fails if the bytes are not aligned for `T`, or their length is not a multiple
+    /// of its size. Bytes from [`Arena::alloc_pod_slice_from_bytes`], or from an arena
+    /// with a big enough [`min_align`](crate::ArenaBuilder::min_align), are always aligned.
+    pub fn cast_slice<T: Pod>(self) -> Result<Boxed<'chunk, [T]>, PodCastError> {
+        let len = bytemuck::try_cast_slice::<u8, T>(&self)?.len();
+        let ptr = self.into_ptr().cast::<T>();
+
+        Ok(unsafe { Boxed::from_ptr(ptr.slice(len)) })
+    }
+}
+
+impl<'chunk, T> Boxed<'chunk, [T]> {
+    /// Point this handle at `len` values starting at `data`,
+    /// within the same chunk.
+    unsafe fn relocate(&mut self, data: *mut T, len: usize) {
+        let old = self.as_ptr();
+        let state = old.chunk.state;
+
+        if state.config.teardown_drops {
+            state
+                .drops
+                .relocate(old.chunk, old.as_raw(), slice_from_raw_parts_mut(data, len));
+        }
+
+        #[cfg(feature = "leak-tracking")]
+        state.leaks.relocate(
+            old.as_raw().cast::<u8>() as usize,
+            data as usize,
+            crate::array_layout::<T>(len).unwrap(),
+        );
+
+        let new = Ptr::new_unchecked(old.chunk, slice_from_raw_parts_mut(data, len));
+        ptr::write(self, Boxed::from_ptr(new));
+    }
+
+    /// Try to add `additional` default values to the end of this slice, without reallocating.
+    ///
+    /// This only works when the slice is the most recent allocation of its chunk
+    /// and the chunk has room left. Since the arena bumps downwards, the values
+    /// are shifted down within the chunk to make room.
+    /// Returns `false` and leaves the slice alone otherwise.
+    pub fn try_grow_in_place(&mut self, additional: usize, arena: &Arena) -> bool
+    where
+        T: Default,
+    {
+        let chunk = self.as_ptr().chunk;
+        if chunk.state != arena.state_ref() || !self.at_tip() {
+            return false;
+        }
+
+        let len = self.len();
+        let old = self.as_raw().cast::<T>();
+        let available = old as usize - chunk.start.as_ptr() as usize;
+        match checked_array_size::<T>(additional) {
+            Some(bytes) if bytes <= available => unsafe {
+                let new = old.cast::<u8>().sub(bytes).cast::<T>();
+                chunk.commit_down_to(NonNull::new_unchecked(new.cast()));
+                ptr::copy(old, new, len);
+                chunk.bump.set(NonNull::new_unchecked(new.cast()));
+                self.relocate(new, len);
+
+                // A panicking default only leaks the values written so far
+                for index in len..len + additional {
+                    new.add(index).write(T::default());
+                }
+                self.relocate(new, len + additional);
+
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Shorten this slice to `new_len` values, dropping the rest.
+    ///
+    /// When the slice is the most recent allocation of its chunk, the memory
+    /// of the dropped values is given back, by shifting the kept values up.
+    pub fn shrink_in_place(&mut self, new_len: usize) {
+        let len = self.len();
+        if new_len >= len {
+            return;
+        }
+
+        let old = self.as_raw().cast::<T>();
+        unsafe {
+            // Shorten first so a panicking destructor cannot drop twice
+            self.relocate(old, new_len);
+            ptr::drop_in_place(slice_from_raw_parts_mut(old.add(new_len), len - new_len));
+
+            // Destructors may have allocated, so check the tip afterwards
+            if self.at_tip() {
+                let new = old.add(len - new_len);
+                ptr::copy(old, new, new_len);
+                self.as_ptr()
+                    .chunk
+                    .bump
+                    .set(NonNull::new_unchecked(new.cast()));
+                self.relocate(new, new_len);
+            }
+        }
     }
 }
 
@@ -113,10 +294,162 @@ impl<'chunk, T: ?Sized + Display> Display for Boxed<'chunk, T> {
     }
 }
 
+impl<'chunk, T: ?Sized> Pointer for Boxed<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_raw(), f)
+    }
+}
+
 impl<'chunk, T: ?Sized> Drop for Boxed<'chunk, T> {
     #[inline]
     fn drop(&mut self) {
+        let ptr = self.as_ptr();
+        if ptr.chunk.state.config.teardown_drops {
+            ptr.chunk
+                .state
+                .drops
+                .unregister(ptr.chunk, DropKey::of(ptr.as_raw()));
+        }
+
+        // A cleanup function from `alloc_with_dtor` runs first
+        if let Some(dtor) = ptr.chunk.state.drops.take_dtor(ptr.chunk, ptr.as_raw()) {
+            unsafe { dtor.run() }
+        }
+
         // Call the drop function for the value
         unsafe { core::ptr::drop_in_place(self.deref_mut()) }
+
+        // Give the slot back when nothing was allocated after it,
+        // checked after the destructor since it may have allocated
+        if self.at_tip() {
+            #[cfg(feature = "tagging")]
+            ptr.chunk
+                .state
+                .tags
+                .give_back(ptr.chunk, ptr.as_raw().cast());
+
+            let size = std::mem::size_of_val::<T>(self);
+            unsafe {
+                ptr.chunk
+                    .bump
+                    .set(NonNull::new_unchecked(ptr.as_raw().cast::<u8>().add(size)));
+            }
+        }
     }
 }
+
+#[test]
+fn grow_and_shrink_in_place() {
+    use std::rc::Rc;
+
+    let arena = Arena::new();
+    let mut buffer = arena.alloc_slice_fill_copy(4, &1_u8);
+    let remaining = buffer.chunk_remaining();
+
+    assert!(buffer.try_grow_in_place(4, &arena));
+    assert_eq!(&*buffer, &[1, 1, 1, 1, 0, 0, 0, 0]);
+    assert_eq!(buffer.chunk_remaining(), remaining - 4);
+
+    buffer.shrink_in_place(2);
+    assert_eq!(&*buffer, &[1, 1]);
+    assert_eq!(buffer.chunk_remaining(), remaining + 2);
+
+    // Only the most recent allocation can grow
+    let other = arena.alloc(0_u8);
+    assert!(!buffer.try_grow_in_place(1, &arena));
+    assert!(!buffer.try_grow_in_place(1, &Arena::new()));
+    drop(other);
+
+    let counter = Rc::new(());
+    let mut values = arena.alloc_slice_fill_with(4, |_| counter.clone());
+    values.shrink_in_place(1);
+    assert_eq!(Rc::strong_count(&counter), 2);
+    assert!(!values.try_grow_in_place(usize::MAX / 16, &arena));
+}
+
+#[test]
+fn dropped_tip_slot_is_reused() {
+    let arena = Arena::new();
+    let keep = arena.alloc(0_u64);
+    let remaining = keep.chunk_remaining();
+
+    let first = arena.alloc([1_u32; 4]);
+    let address = first.as_raw();
+    drop(first);
+    assert_eq!(keep.chunk_remaining(), remaining);
+    assert_eq!(arena.alloc([2_u32; 4]).as_raw(), address);
+
+    // Only the most recent allocation is given back
+    let older = arena.alloc(1_u32);
+    let newer = arena.alloc(2_u32);
+    let older_address = older.as_raw();
+    drop(older);
+    assert_ne!(arena.alloc(3_u32).as_raw(), older_address);
+    drop(newer);
+
+    let text = arena.alloc_str("slot");
+    let text_address = text.as_raw().cast::<u8>();
+    drop(text);
+    assert_eq!(arena.alloc_str("tops").as_raw().cast::<u8>(), text_address);
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+fn bytes_cast_to_pod_slices() {
+    let arena = Arena::new();
+    let words: [u32; 3] = [1, 2, 3];
+    let bytes: &[u8] = bytemuck::cast_slice(&words);
+
+    // The end of a chunk is aligned, so the first bytes are too
+    let first = arena.alloc_slice_copy(bytes);
+    assert_eq!(&*first.cast_slice::<u32>().unwrap(), &words);
+
+    // Bumping down past a single byte leaves the next bytes misaligned
+    let _byte = arena.alloc(0_u8);
+    let misaligned = arena.alloc_slice_copy(&bytes[..8]);
+    assert_ne!(misaligned.as_raw().cast::<u8>() as usize % 4, 0);
+    assert_eq!(
+        misaligned.cast_slice::<u32>().unwrap_err(),
+        PodCastError::TargetAlignmentGreaterAndInputNotAligned
+    );
+
+    // Copying realigns, whatever the alignment of the source
+    let mut source = [0_u8; 9];
+    source[1..].copy_from_slice(&bytes[..8]);
+    let copied = arena
+        .alloc_pod_slice_from_bytes::<u32>(&source[1..])
+        .unwrap();
+    assert_eq!(&*copied, &words[..2]);
+    assert_eq!(
+        arena
+            .alloc_pod_slice_from_bytes::<u32>(&bytes[..6])
+            .unwrap_err(),
+        PodCastError::OutputSliceWouldHaveSlop
+    );
+
+    // A minimum alignment lines every cast up, leaving only the length to check
+    let aligned = Arena::builder().min_align(4).build();
+    let _byte = aligned.alloc(0_u8);
+    let shared = aligned.alloc_slice_copy(bytes).into_ref();
+    assert_eq!(&*shared.cast_slice::<u32>().unwrap(), &words);
+    assert_eq!(
+        shared.slice(..6).cast_slice::<u32>().unwrap_err(),
+        PodCastError::OutputSliceWouldHaveSlop
+    );
+}
+
+#[test]
+fn fill_ratio_follows_the_chunk() {
+    use std::alloc::Layout;
+
+    let arena = Arena::new();
+    let class = crate::size_to_index(1 << 16);
+    let small = arena.alloc_layout_in_class(Layout::new::<u64>(), class);
+    assert!(small.chunk_fill_ratio() < 0.001);
+
+    let rest = Layout::array::<u8>(small.chunk_remaining()).unwrap();
+    let filler = arena.alloc_layout_in_class(rest, class);
+    assert!(filler.same_chunk(&small));
+    assert_eq!(small.chunk_fill_ratio(), 1.0);
+}
@@ -3,13 +3,23 @@ use std::{fmt::Debug, ptr::{NonNull, slice_from_raw_parts_mut}};
 use crate::chunk::Chunk;
 
 mod boxed;
+mod pending;
 mod r#ref;
 mod ref_mut;
+mod thin;
 
 pub use boxed::*;
+pub use pending::*;
 pub use r#ref::*;
 pub use ref_mut::*;
-
+pub use thin::*;
+
+/// A pointer to a value, along with the chunk holding it.
+///
+/// The chunk cannot be recovered from the value's address alone: masking by
+/// the chunk size needs the size, and not every chunk is aligned to its size
+/// (emergency chunks, inline chunks, and big chunks on wasm are only page aligned).
+/// [`ThinBoxed`] stores the chunk in the arena, next to the value, instead.
 #[derive(Debug)]
 pub(crate) struct Ptr<T: ?Sized> {
     pub(crate) chunk: Chunk,
@@ -37,6 +47,12 @@ impl<T: ?Sized> Ptr<T> {
         self.ptr.as_ptr()
     }
 
+    /// Change the pointer type, such as coercing it to a trait object.
+    #[inline]
+    pub unsafe fn map_raw<U: ?Sized>(self, f: impl FnOnce(*mut T) -> *mut U) -> Ptr<U> {
+        Ptr::new_unchecked(self.chunk, f(self.as_raw()))
+    }
+
     #[inline]
     pub const fn chunk(self) -> Chunk {
         self.chunk
@@ -55,16 +71,27 @@ impl<T: ?Sized> Ptr<T> {
     #[inline]
     pub unsafe fn add_ref(self) -> u64 {
         let old = self.chunk.add_ref();
+        #[cfg(feature = "debug-trace")]
         println!("Added ref for {}-{}", self.chunk.size, self.chunk.index);
+
+        #[cfg(feature = "leak-tracking")]
+        self.chunk.state.leaks.add_handle(self.ptr.cast::<u8>().as_ptr() as usize);
+
         old
     }
 
     #[inline]
     pub unsafe fn remove_ref(self) -> u64 {
         let old = self.chunk.remove_ref();
+        #[cfg(feature = "debug-trace")]
         println!("Removed ref for {}-{}", self.chunk.size, self.chunk.index);
+
+        #[cfg(feature = "leak-tracking")]
+        self.chunk.state.leaks.remove_handle(self.ptr.cast::<u8>().as_ptr() as usize);
+
         if old == 1 {
             self.chunk.reset_bump();
+            self.chunk.state.on_chunk_reset(self.chunk);
 
             if !self.chunk.is_current() {
                 self.chunk.free().expect("failed to free chunk");
@@ -129,8 +156,42 @@ impl<T> Ptr<[T]> {
 impl<T: ?Sized> Clone for Ptr<T> {
     #[inline]
     fn clone(&self) -> Self {
-        Self::new(self.chunk, self.ptr)
+        *self
     }
 }
 
 impl<T: ?Sized> Copy for Ptr<T> {}
+
+#[test]
+fn handle_sizes() {
+    use std::mem::size_of;
+
+    // Two words: the value and its chunk
+    assert_eq!(size_of::<Boxed<'_, u64>>(), 2 * size_of::<usize>());
+    assert_eq!(size_of::<Ref<'_, u64>>(), 2 * size_of::<usize>());
+    assert_eq!(size_of::<Option<RefMut<'_, u64>>>(), 2 * size_of::<usize>());
+    assert_eq!(size_of::<Ref<'_, [u64]>>(), 3 * size_of::<usize>());
+
+    assert_eq!(size_of::<ThinBoxed<'_, u64>>(), size_of::<usize>());
+    assert_eq!(size_of::<Option<ThinBoxed<'_, u64>>>(), size_of::<usize>());
+}
+
+#[test]
+fn handles_keep_their_niche() {
+    use std::mem::size_of;
+
+    // Checked at compile time, either non-null pointer leaves room for `None`
+    const {
+        assert!(size_of::<Option<Boxed<'_, u8>>>() == size_of::<Boxed<'_, u8>>());
+        assert!(size_of::<Option<Boxed<'_, [u8]>>>() == size_of::<Boxed<'_, [u8]>>());
+        assert!(size_of::<Option<Ref<'_, u8>>>() == size_of::<Ref<'_, u8>>());
+        assert!(size_of::<Option<RefMut<'_, u8>>>() == size_of::<RefMut<'_, u8>>());
+        assert!(size_of::<Option<Ptr<u8>>>() == size_of::<Ptr<u8>>());
+    }
+
+    let arena = crate::Arena::new();
+    let mut slots: Vec<Option<Boxed<'_, u8>>> = (0..4).map(|n| Some(arena.alloc(n))).collect();
+    slots[1] = None;
+    let values: Vec<_> = slots.iter().map(|slot| slot.as_deref().copied()).collect();
+    assert_eq!(values, [Some(0), None, Some(2), Some(3)]);
+}
@@ -0,0 +1,157 @@
+use std::{cell::Cell, fmt::Debug, marker::PhantomData, mem::MaybeUninit, ptr::addr_of_mut};
+
+use super::{Ptr, Ref};
+use crate::Arena;
+
+/// Slot of a value allocated by [`Arena::alloc_cyclic`].
+struct Cyclic<T> {
+    value: MaybeUninit<T>,
+    initialized: Cell<bool>,
+}
+
+/// A handle to a value that may still be under construction.
+///
+/// Handed out by [`Arena::alloc_cyclic`], it can be stored in the value
+/// being built, and only gives access to it once it is fully constructed.
+pub struct PendingRef<'chunk, T> {
+    ptr: Ptr<Cyclic<T>>,
+    _marker: PhantomData<&'chunk T>,
+}
+
+impl<'chunk, T> PendingRef<'chunk, T> {
+    /// Get a reference to the value, or `None` while it is still being constructed.
+    #[inline]
+    pub fn upgrade(&self) -> Option<Ref<'chunk, T>> {
+        let slot = unsafe { self.ptr.deref() };
+        if !slot.initialized.get() {
+            return None;
+        }
+
+        unsafe {
+            Some(Ref::new(
+                self.ptr.map_raw(|raw| addr_of_mut!((*raw).value).cast()),
+            ))
+        }
+    }
+
+    /// Checks if both handles point to the same value.
+    #[inline]
+    pub fn ptr_eq(&self, other: &PendingRef<'_, T>) -> bool {
+        self.ptr.as_raw() == other.ptr.as_raw()
+    }
+}
+
+impl<'chunk, T> Clone for PendingRef<'chunk, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        unsafe { self.ptr.add_ref() };
+
+        Self {
+            ptr: self.ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'chunk, T> Debug for PendingRef<'chunk, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let initialized = unsafe { self.ptr.deref() }.initialized.get();
+        f.debug_struct("PendingRef")
+            .field("ptr", &self.ptr.as_raw())
+            .field("initialized", &initialized)
+            .finish()
+    }
+}
+
+impl<'chunk, T> Drop for PendingRef<'chunk, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.ptr.remove_ref();
+        }
+    }
+}
+
+impl Arena {
+    /// Allocate a value that holds handles to itself.
+    ///
+    /// `f` gets a [`PendingRef`] to the slot the value will live in, which it can
+    /// clone into the value (or into values it allocates), but not dereference:
+    /// [`PendingRef::upgrade`] only succeeds once `f` returned.
+    ///
+    /// The value is shared with the handles it holds, so it comes back as a [`Ref`]
+    /// and is never dropped on its own. Like a cycle of `Rc`s, handles stored in the
+    /// value keep its chunk until the arena drops.
+    #[track_caller]
+    pub fn alloc_cyclic<'chunk, T>(
+        &'chunk self,
+        f: impl FnOnce(&PendingRef<'chunk, T>) -> T,
+    ) -> Ref<'chunk, T> {
+        let slot = self
+            .alloc(Cyclic {
+                value: MaybeUninit::uninit(),
+                initialized: Cell::new(false),
+            })
+            .into_ptr();
+        let pending = PendingRef {
+            ptr: slot,
+            _marker: PhantomData,
+        };
+
+        // A panic in `f` gives the slot back when `pending` drops
+        let value = f(&pending);
+
+        unsafe {
+            let ptr = slot.map_raw(|raw| addr_of_mut!((*raw).value).cast::<T>());
+            ptr.write(value);
+            slot.deref().initialized.set(true);
+            self.register_drop(ptr, 1, ptr);
+
+            // The slot's reference moves to the returned handle
+            std::mem::forget(pending);
+            Ref::from_ptr(ptr)
+        }
+    }
+}
+
+#[test]
+fn doubly_linked_nodes() {
+    struct Parent<'arena> {
+        name: &'static str,
+        child: Ref<'arena, Child<'arena>>,
+    }
+
+    struct Child<'arena> {
+        parent: PendingRef<'arena, Parent<'arena>>,
+    }
+
+    let arena = Arena::new();
+    let parent = arena.alloc_cyclic(|this| {
+        // Still under construction
+        assert!(this.upgrade().is_none());
+
+        let child = arena.alloc_node(Child {
+            parent: this.clone(),
+        });
+        Parent {
+            name: "root",
+            child,
+        }
+    });
+
+    let back = parent.child.parent.upgrade().unwrap();
+    assert_eq!(back.name, "root");
+    assert_eq!(back.as_raw(), parent.as_raw());
+
+    // A node pointing at itself
+    struct Looped<'arena> {
+        this: PendingRef<'arena, Looped<'arena>>,
+        value: u32,
+    }
+    let looped = arena.alloc_cyclic(|this| Looped {
+        this: this.clone(),
+        value: 7,
+    });
+    assert!(looped.this.ptr_eq(&looped.this.clone()));
+    assert_eq!(looped.this.upgrade().unwrap().value, 7);
+}
@@ -1,9 +1,13 @@
 use super::{Ptr, RefMut, Boxed};
+use crate::Placement;
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, PodCastError};
 use std::{
-    fmt::{Debug, Display},
+    fmt::{Debug, Display, Pointer},
     marker::PhantomData,
     mem::ManuallyDrop,
-    ops::Deref,
+    ops::{Bound, Deref, RangeBounds},
+    ptr::NonNull,
 };
 
 #[repr(transparent)]
@@ -60,6 +64,50 @@ impl<'chunk, T: ?Sized> Ref<'chunk, T> {
         self.ptr.as_raw()
     }
 
+    /// How many more bytes could be bump allocated from the chunk
+    /// holding this value.
+    #[inline]
+    pub fn chunk_remaining(&self) -> usize {
+        self.ptr.chunk.remaining()
+    }
+
+    /// Fraction of the chunk holding this value that is allocated,
+    /// counting every other value in it, from 0 to 1.
+    #[inline]
+    pub fn chunk_fill_ratio(&self) -> f32 {
+        let chunk = self.ptr.chunk;
+
+        (chunk.size - chunk.remaining()) as f32 / chunk.size as f32
+    }
+
+    /// How many handles are keeping the chunk holding this value alive.
+    ///
+    /// References are counted per chunk, not per allocation, so this
+    /// includes the handles to every other value in the chunk.
+    #[inline]
+    pub fn strong_count(&self) -> u64 {
+        self.ptr.chunk.refs()
+    }
+
+    /// Checks if this value lives in the same chunk as another.
+    #[inline]
+    pub fn same_chunk<U: ?Sized>(&self, other: &Ref<'_, U>) -> bool {
+        self.ptr.chunk == other.ptr.chunk
+    }
+
+    /// Where this value lives, relative to its chunk,
+    /// for comparing layouts across runs.
+    #[inline]
+    pub fn placement(&self) -> Placement {
+        let chunk = self.ptr.chunk;
+
+        Placement {
+            class: chunk.state.config.class_for_size(chunk.size),
+            chunk: chunk.index,
+            offset: self.as_raw().cast::<u8>() as usize - chunk.start.as_ptr() as usize,
+        }
+    }
+
     /// Leak this value.
     ///
     /// The chunk cannot be freed once a value is freed within it.
@@ -67,6 +115,59 @@ impl<'chunk, T: ?Sized> Ref<'chunk, T> {
     pub fn leak(self) -> &'chunk T {
         unsafe { &*self.into_ptr().as_raw() }
     }
+
+    /// Make a new handle to a part of this value.
+    #[inline]
+    fn map<U: ?Sized>(&self, f: impl FnOnce(&T) -> &U) -> Ref<'chunk, U> {
+        let part = NonNull::from(f(self.deref()));
+
+        unsafe { Ref::new(Ptr::new(self.ptr.chunk, part)) }
+    }
+}
+
+/// Turn any range into one that both slices and strings can be indexed with.
+#[inline]
+pub(crate) fn bounds(range: impl RangeBounds<usize>) -> (Bound<usize>, Bound<usize>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+impl<'chunk, T> Ref<'chunk, [T]> {
+    /// Make a handle to part of this slice, which keeps the chunk alive on its own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing.
+    #[inline]
+    #[track_caller]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Ref<'chunk, [T]> {
+        self.map(|slice| &slice[bounds(range)])
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'chunk> Ref<'chunk, [u8]> {
+    /// Make a handle to these bytes as a slice of `T`, without copying.
+    ///
+    /// Fails like [`Boxed::cast_slice`].
+    pub fn cast_slice<T: Pod>(&self) -> Result<Ref<'chunk, [T]>, PodCastError> {
+        bytemuck::try_cast_slice::<u8, T>(self)?;
+
+        Ok(self.map(bytemuck::cast_slice))
+    }
+}
+
+impl<'chunk> Ref<'chunk, str> {
+    /// Make a handle to part of this string, which keeps the chunk alive on its own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or not on char boundaries,
+    /// like string indexing.
+    #[inline]
+    #[track_caller]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Ref<'chunk, str> {
+        self.map(|text| &text[bounds(range)])
+    }
 }
 
 impl<'chunk, T: ?Sized> Deref for Ref<'chunk, T> {
@@ -99,6 +200,13 @@ impl<'chunk, T: ?Sized + Display> Display for Ref<'chunk, T> {
     }
 }
 
+impl<'chunk, T: ?Sized> Pointer for Ref<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_raw(), f)
+    }
+}
+
 impl<'chunk, T: ?Sized> Clone for Ref<'chunk, T> {
     #[inline]
     fn clone(&self) -> Self {
@@ -114,3 +222,75 @@ impl<'chunk, T: ?Sized> Drop for Ref<'chunk, T> {
         }
     }
 }
+
+#[test]
+fn chunk_remaining_shrinks() {
+    let arena = crate::Arena::new();
+    let first = arena.alloc(0_u64).into_ref();
+    let remaining = first.chunk_remaining();
+    assert!(remaining <= crate::MIN_BLOCK_SIZE - 8);
+
+    let _second = arena.alloc([0_u8; 32]);
+    assert_eq!(first.chunk_remaining(), remaining - 32);
+}
+
+#[test]
+fn pointer_formatting() {
+    let arena = crate::Arena::new();
+    let boxed = arena.alloc(1_u32);
+    assert_eq!(format!("{boxed:p}"), format!("{:p}", boxed.as_raw()));
+
+    let text = arena.alloc_str("text").into_ref();
+    assert_eq!(format!("{text:p}"), format!("{:p}", text.as_raw()));
+
+    let slice = arena.alloc_slice_copy(&[1, 2]).into_mut();
+    assert_eq!(format!("{slice:#p}"), format!("{:#p}", slice.as_raw()));
+}
+
+#[test]
+fn strong_count_follows_clones() {
+    let arena = crate::Arena::new();
+    let first = arena.alloc(1_u32).into_ref();
+    let base = first.strong_count();
+
+    let clones = vec![first.clone(); 3];
+    assert_eq!(first.strong_count(), base + 3);
+
+    // Other values of the chunk count too
+    let other = arena.alloc(2_u32);
+    assert!(other.same_chunk(&arena.alloc(3_u32)));
+    assert_eq!(first.strong_count(), base + 4);
+
+    drop(clones);
+    drop(other);
+    assert_eq!(first.strong_count(), base);
+}
+
+#[test]
+fn spans_keep_source_alive() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let arena = crate::Arena::new();
+    let source = arena.alloc_str("let x = 1;").into_ref();
+    let refs = source.strong_count();
+
+    let name = source.slice(4..5);
+    let value = source.slice(8..=8);
+    assert_eq!((&*name, &*value), ("x", "1"));
+    assert_eq!(source.strong_count(), refs + 2);
+    assert_eq!(&*source.slice(..3), "let");
+    assert_eq!(&*source.slice(9..), ";");
+
+    // Spans outlive the handle to the whole source
+    drop(source);
+    assert_eq!(name.strong_count(), refs + 1);
+
+    let numbers = arena.alloc_slice_copy(&[1, 2, 3, 4]).into_ref();
+    assert_eq!(&*numbers.slice(1..3), &[2, 3]);
+    assert!(numbers.slice(4..).is_empty());
+
+    let unicode = arena.alloc_str("é").into_ref();
+    let error = catch_unwind(AssertUnwindSafe(|| unicode.slice(1..))).unwrap_err();
+    assert!(error.downcast_ref::<String>().unwrap().contains("char boundary"));
+    assert!(catch_unwind(AssertUnwindSafe(|| numbers.slice(2..5))).is_err());
+}
@@ -1,9 +1,11 @@
-use super::{Boxed, Ptr, Ref};
+use super::{r#ref::bounds, Boxed, Ptr, Ref};
+use crate::Placement;
 use std::{
-    fmt::{Debug, Display},
+    fmt::{Debug, Display, Pointer},
     marker::PhantomData,
     mem::{ManuallyDrop, MaybeUninit},
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, RangeBounds},
+    ptr::NonNull,
 };
 
 #[repr(transparent)]
@@ -58,11 +60,110 @@ impl<'chunk, T: ?Sized> RefMut<'chunk, T> {
         ManuallyDrop::new(self).as_ptr()
     }
 
+    /// See [`Ref<T>::chunk_remaining()`] for details.
+    #[inline]
+    pub fn chunk_remaining(&self) -> usize {
+        self.ptr.chunk.remaining()
+    }
+
+    /// See [`Ref<T>::chunk_fill_ratio()`] for details.
+    #[inline]
+    pub fn chunk_fill_ratio(&self) -> f32 {
+        unsafe { ManuallyDrop::new(Ref::from_ptr(self.ptr)).chunk_fill_ratio() }
+    }
+
+    /// See [`Ref<T>::same_chunk()`] for details.
+    #[inline]
+    pub fn same_chunk<U: ?Sized>(&self, other: &RefMut<'_, U>) -> bool {
+        self.ptr.chunk == other.ptr.chunk
+    }
+
+    /// See [`Ref<T>::placement()`] for details.
+    #[inline]
+    pub fn placement(&self) -> Placement {
+        unsafe { ManuallyDrop::new(Ref::from_ptr(self.ptr)).placement() }
+    }
+
     /// See [`Ref<T>::leak()`] for details.
     #[inline]
     pub fn leak(self) -> &'chunk mut T {
         unsafe { &mut *ManuallyDrop::new(self).as_raw() }
     }
+
+    /// Narrow this handle to a part of the value, keeping its reference.
+    #[inline]
+    fn map<U: ?Sized>(mut self, f: impl FnOnce(&mut T) -> &mut U) -> RefMut<'chunk, U> {
+        let chunk = self.ptr.chunk;
+        let part = NonNull::from(f(self.deref_mut()));
+
+        let _ = self.into_ptr();
+        unsafe { RefMut::from_ptr(Ptr::new(chunk, part)) }
+    }
+
+    /// Split this handle into two handles to disjoint parts of the value,
+    /// such as two of its fields.
+    ///
+    /// Both handles keep the chunk alive on their own.
+    /// See [`project!`](crate::project) for splitting by field names.
+    #[inline]
+    pub fn map_split<U: ?Sized, V: ?Sized>(
+        mut self,
+        f: impl FnOnce(&mut T) -> (&mut U, &mut V),
+    ) -> (RefMut<'chunk, U>, RefMut<'chunk, V>) {
+        let chunk = self.ptr.chunk;
+        let (left, right) = f(self.deref_mut());
+        let (left, right) = (NonNull::from(left), NonNull::from(right));
+
+        // The reference of this handle goes to the left one
+        let _ = self.into_ptr();
+        unsafe {
+            (
+                RefMut::from_ptr(Ptr::new(chunk, left)),
+                RefMut::new(Ptr::new(chunk, right)),
+            )
+        }
+    }
+}
+
+/// Split a [`RefMut`] into handles to two of its fields.
+///
+/// `project!(value => .left, .right)` is [`RefMut::map_split`] with a closure
+/// borrowing both fields, so naming the same field twice does not compile.
+/// Fields can be nested, like `.position.x`.
+#[macro_export]
+macro_rules! project {
+    ($value:expr => $(. $left:tt)+ , $(. $right:tt)+ $(,)?) => {
+        $crate::ptr::RefMut::map_split($value, |value| {
+            (&mut value $(. $left)+, &mut value $(. $right)+)
+        })
+    };
+}
+
+impl<'chunk, T> RefMut<'chunk, [T]> {
+    /// Narrow this handle to part of the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing.
+    #[inline]
+    #[track_caller]
+    pub fn slice(self, range: impl RangeBounds<usize>) -> RefMut<'chunk, [T]> {
+        self.map(|slice| &mut slice[bounds(range)])
+    }
+}
+
+impl<'chunk> RefMut<'chunk, str> {
+    /// Narrow this handle to part of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or not on char boundaries,
+    /// like string indexing.
+    #[inline]
+    #[track_caller]
+    pub fn slice(self, range: impl RangeBounds<usize>) -> RefMut<'chunk, str> {
+        self.map(|text| &mut text[bounds(range)])
+    }
 }
 
 impl<'chunk, T> RefMut<'chunk, MaybeUninit<T>> {
@@ -78,6 +179,10 @@ impl<'chunk, T> RefMut<'chunk, MaybeUninit<T>> {
     }
 
     /// Unsafely assume that the value is initialized.
+    ///
+    /// # Safety
+    ///
+    /// The value must have been initialized.
     #[inline]
     pub unsafe fn assume_init(self) -> RefMut<'chunk, T> {
         RefMut::from_ptr(self.into_ptr().cast())
@@ -135,3 +240,44 @@ impl<'chunk, T: ?Sized + Display> Display for RefMut<'chunk, T> {
         self.deref().fmt(f)
     }
 }
+
+impl<'chunk, T: ?Sized> Pointer for RefMut<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_raw(), f)
+    }
+}
+
+#[test]
+fn split_fields() {
+    struct World {
+        physics: Vec<u32>,
+        audio: (u8, String),
+    }
+
+    let arena = crate::Arena::new();
+    let world = arena
+        .alloc(World {
+            physics: vec![1, 2],
+            audio: (0, String::new()),
+        })
+        .into_mut();
+
+    let (mut physics, mut volume) = crate::project!(world => .physics, .audio.0);
+    physics.push(3);
+    *volume = 11;
+    assert!(physics.same_chunk(&volume));
+    assert_eq!(physics.as_ptr().chunk.refs(), 2);
+    drop(physics);
+    assert_eq!(volume.as_ptr().chunk.refs(), 1);
+
+    let value = arena.alloc((1_u8, [2_u8; 4])).into_mut();
+    let (first, rest) = value.map_split(|(first, rest)| (first, &mut rest[1..]));
+    assert_eq!((*first, rest.len()), (1, 3));
+
+    let mut middle = rest.slice(1..2);
+    middle[0] = 5;
+    let mut word = arena.alloc_str("a word").into_mut().slice(2..);
+    word.make_ascii_uppercase();
+    assert_eq!((&*middle, &*word), (&[5][..], "WORD"));
+}
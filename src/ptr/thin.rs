@@ -0,0 +1,165 @@
+use std::{
+    fmt::{Debug, Display, Pointer},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    ptr::{addr_of_mut, NonNull},
+};
+
+use super::{Boxed, Ptr};
+use crate::{chunk::Chunk, Arena};
+
+/// What [`Arena::alloc_thin`] allocates: the value, followed by its chunk.
+///
+/// The value comes first so it starts where the allocation does.
+#[repr(C)]
+struct ThinSlot<T> {
+    value: T,
+    chunk: Chunk,
+}
+
+/// A one word owning handle, which keeps its chunk next to the value
+/// in the arena instead of in the handle.
+///
+/// Each value takes a word more of the chunk than with [`Boxed`],
+/// which is cheaper when handles are stored or copied around a lot.
+/// Dropping the handle drops the value and gives its chunk back, like a [`Boxed`].
+#[repr(transparent)]
+pub struct ThinBoxed<'chunk, T> {
+    slot: NonNull<ThinSlot<T>>,
+    _marker: PhantomData<&'chunk mut T>,
+}
+
+impl<'chunk, T> ThinBoxed<'chunk, T> {
+    #[inline]
+    pub fn as_raw(&self) -> *mut T {
+        unsafe { addr_of_mut!((*self.slot.as_ptr()).value) }
+    }
+
+    /// Leak this value, it is never dropped.
+    #[inline]
+    pub fn leak(self) -> &'chunk mut T {
+        unsafe { &mut *ManuallyDrop::new(self).as_raw() }
+    }
+
+    /// Turn this handle into a [`Boxed`], which points at its chunk itself.
+    #[inline]
+    pub fn into_boxed(self) -> Boxed<'chunk, T> {
+        unsafe { Boxed::from_ptr(ManuallyDrop::new(self).as_ptr()) }
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> Ptr<T> {
+        unsafe {
+            let chunk = (*self.slot.as_ptr()).chunk;
+            Ptr::new_unchecked(chunk, self.as_raw())
+        }
+    }
+}
+
+impl<'chunk, T> Deref for ThinBoxed<'chunk, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.as_raw() }
+    }
+}
+
+impl<'chunk, T> DerefMut for ThinBoxed<'chunk, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.as_raw() }
+    }
+}
+
+impl<'chunk, T: Debug> Debug for ThinBoxed<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<'chunk, T: Display> Display for ThinBoxed<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<'chunk, T> Pointer for ThinBoxed<'chunk, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_raw(), f)
+    }
+}
+
+impl<'chunk, T> Drop for ThinBoxed<'chunk, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // Drops the value and gives back its reference, like any other box
+        drop(unsafe { Boxed::<'chunk, T>::from_ptr(self.as_ptr()) })
+    }
+}
+
+impl Arena {
+    /// Allocate a value behind a [`ThinBoxed`] handle.
+    #[track_caller]
+    pub fn alloc_thin<T>(&self, value: T) -> ThinBoxed<'_, T> {
+        let slot = self
+            .allocate(std::alloc::Layout::new::<ThinSlot<T>>())
+            .cast::<ThinSlot<T>>();
+        let chunk = slot.chunk;
+
+        unsafe {
+            slot.write(ThinSlot { value, chunk });
+            let ptr = slot.cast::<T>();
+            ptr.add_ref();
+            self.register_drop(ptr, 1, ptr);
+        }
+
+        ThinBoxed {
+            slot: slot.ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[test]
+fn thin_is_one_word() {
+    use std::mem::size_of;
+
+    assert_eq!(size_of::<ThinBoxed<u8>>(), size_of::<usize>());
+    assert_eq!(size_of::<Option<ThinBoxed<u8>>>(), size_of::<usize>());
+
+    let arena = Arena::new();
+    let mut value = arena.alloc_thin(41_u64);
+    *value += 1;
+    assert_eq!(*value, 42);
+
+    let text = arena.alloc_thin(String::from("thin"));
+    assert_eq!(text.len(), 4);
+}
+
+#[test]
+fn thin_gives_its_chunk_back() {
+    let tracker = crate::testing::DropTracker::new();
+    let arena = Arena::builder().drop_on_teardown(true).build();
+
+    let first = arena.alloc_thin(tracker.token());
+    let chunk = first.as_ptr().chunk;
+    drop(first);
+    assert_eq!(tracker.alive(), 0);
+    assert_eq!(chunk.refs(), 0);
+
+    // Leaked values are still dropped on teardown, dropped ones are not
+    arena.alloc_thin(tracker.token()).leak();
+    let boxed = arena.alloc_thin(tracker.token()).into_boxed();
+    assert_eq!(tracker.alive(), 2);
+    drop(boxed);
+    #[cfg(feature = "leak-tracking")]
+    assert_eq!(arena.leak_report()[0].count, 1);
+
+    drop(arena);
+    assert_eq!(tracker.alive(), 0);
+}
@@ -0,0 +1,145 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+};
+
+use crate::chunk::{sys, Backing, Chunk};
+
+/// Byte written over the memory of quarantined chunks.
+pub const QUARANTINE_POISON: u8 = 0xDE;
+
+/// Freed chunks held back from reuse, see [`ArenaBuilder::quarantine`](crate::ArenaBuilder::quarantine).
+#[derive(Debug, Default)]
+pub(crate) struct Quarantine {
+    /// Chunks in the order they were freed,
+    /// with the chunk request count they are held until
+    held: RefCell<VecDeque<(Chunk, usize)>>,
+    /// How many chunks were requested so far
+    requests: Cell<usize>,
+}
+
+impl Quarantine {
+    /// Hold a freed chunk for the next `requests` chunk requests.
+    ///
+    /// Its memory is made inaccessible when it comes from reserved address space,
+    /// and poisoned otherwise if `poison` is set.
+    pub fn hold(&self, chunk: Chunk, requests: usize, poison: bool) {
+        unsafe {
+            if protectable(chunk) {
+                sys::protect(chunk.start, chunk.size, false);
+            } else if poison {
+                chunk
+                    .start
+                    .as_ptr()
+                    .write_bytes(QUARANTINE_POISON, chunk.size);
+            }
+        }
+
+        let until = self.requests.get() + requests;
+        self.held.borrow_mut().push_back((chunk, until));
+    }
+
+    /// Count a chunk request, giving back the chunks held long enough.
+    pub fn tick(&self) {
+        let requests = self.requests.get() + 1;
+        self.requests.set(requests);
+
+        loop {
+            let expired = {
+                let mut held = self.held.borrow_mut();
+                match held.front() {
+                    Some(&(_, until)) if until < requests => held.pop_front(),
+                    _ => None,
+                }
+            };
+            let Some((chunk, _)) = expired else {
+                break;
+            };
+
+            unsafe { release(chunk) };
+        }
+    }
+
+    /// Give back every held chunk right away.
+    pub fn release_all(&self) {
+        let held = std::mem::take(&mut *self.held.borrow_mut());
+        for (chunk, _) in held {
+            unsafe { release(chunk) };
+        }
+    }
+
+    /// Checks if a chunk is being held.
+    pub fn contains(&self, chunk: Chunk) -> bool {
+        self.held.borrow().iter().any(|&(held, _)| held == chunk)
+    }
+}
+
+/// Checks if the memory of a chunk can be made inaccessible,
+/// which takes whole pages of reserved address space.
+#[inline]
+fn protectable(chunk: Chunk) -> bool {
+    chunk.backing == Backing::Reserved && chunk.size.is_multiple_of(sys::page_size())
+}
+
+/// Make a held chunk accessible again and put it on its free list.
+unsafe fn release(chunk: Chunk) {
+    if protectable(chunk) {
+        sys::protect(chunk.start, chunk.size, true);
+    }
+
+    chunk.list.free_now(chunk).expect("failed to free chunk");
+}
+
+#[test]
+fn quarantined_chunks_are_not_reused() {
+    let arena = crate::Arena::builder()
+        .quarantine(2)
+        .poison_quarantined(true)
+        .build();
+
+    // Moving on to a new chunk and then dropping the only value
+    // of the old one frees it
+    let first = arena.alloc([1_u8; 200]);
+    let freed = first.as_ptr().chunk;
+    let mut alive = vec![arena.alloc([2_u8; 200])];
+    drop(first);
+    assert!(!freed.is_free());
+    arena.validate().unwrap();
+
+    let data = unsafe { std::slice::from_raw_parts(freed.start.as_ptr(), freed.size) };
+    assert!(data.iter().all(|&byte| byte == QUARANTINE_POISON));
+
+    // Held for two chunk requests, handed out on the third
+    for _ in 0..2 {
+        alive.push(arena.alloc([3_u8; 200]));
+        assert_ne!(alive.last().unwrap().as_ptr().chunk, freed);
+    }
+    alive.push(arena.alloc([4_u8; 200]));
+    assert_eq!(alive.last().unwrap().as_ptr().chunk, freed);
+    arena.validate().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn reserved_chunks_are_protected() {
+    let mut arena = crate::Arena::builder()
+        .reserve_address_space(1 << 20)
+        .quarantine(1)
+        .build();
+
+    let first = arena.alloc_slice_fill_copy(5000, &1_u8);
+    let freed = first.as_ptr().chunk;
+    let second = arena.alloc_slice_fill_copy(5000, &2_u8);
+    drop(first);
+    assert!(arena.state.quarantine.contains(freed));
+
+    // Reused memory is accessible again
+    let third = arena.alloc_slice_fill_copy(5000, &3_u8);
+    let fourth = arena.alloc_slice_fill_copy(5000, &4_u8);
+    assert_eq!(fourth.as_ptr().chunk, freed);
+    assert!(fourth.iter().all(|&byte| byte == 4));
+
+    drop((second, third, fourth));
+    arena.reset_classes_below(usize::MAX);
+    arena.validate().unwrap();
+}
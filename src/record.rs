@@ -0,0 +1,227 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use crate::{chunk::Chunk, Arena, CapacityProfile, ClassCapacity};
+
+/// Something an arena did while recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorkloadEvent {
+    /// A layout was bumped out of a chunk.
+    Alloc {
+        /// Position in the recording.
+        sequence: u64,
+        /// Bytes asked for.
+        size: usize,
+        /// Alignment asked for.
+        align: usize,
+        /// Size class of the chunk.
+        class: u32,
+        /// Index of the chunk within its class.
+        chunk: u32,
+    },
+    /// Every allocation in a chunk was gone, so it could be reused.
+    Reset {
+        /// Position in the recording.
+        sequence: u64,
+        /// Size class of the chunk.
+        class: u32,
+        /// Index of the chunk within its class.
+        chunk: u32,
+    },
+}
+
+/// The allocations an arena made between [`Arena::start_recording`]
+/// and [`Arena::stop_recording`], to plan the capacity of other arenas with.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkloadProfile {
+    /// Chunk size of every size class the events mention, smallest first.
+    pub chunk_sizes: Vec<usize>,
+    /// Everything that happened, oldest first.
+    pub events: Vec<WorkloadEvent>,
+}
+
+/// What it takes to run a recorded workload, from [`WorkloadProfile::analyze`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WorkloadAnalysis {
+    /// Chunks every size class needs so the workload allocates none,
+    /// for [`Arena::prewarm_from_profile`].
+    pub capacity: CapacityProfile,
+    /// Most chunks holding allocations at once, across every class.
+    pub peak_chunks: usize,
+    /// Most bytes requested by allocations alive at once.
+    ///
+    /// Values are freed a chunk at a time, so this counts every
+    /// allocation until its chunk is reset.
+    pub peak_bytes: usize,
+}
+
+impl WorkloadProfile {
+    /// Replay the events, working out how many chunks were in use at the peak.
+    pub fn analyze(&self) -> WorkloadAnalysis {
+        let mut live: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut live_per_class = vec![0; self.chunk_sizes.len()];
+        let mut peak_per_class = vec![0; self.chunk_sizes.len()];
+        let (mut bytes, mut peak_bytes, mut peak_chunks) = (0, 0, 0);
+
+        for event in &self.events {
+            match *event {
+                WorkloadEvent::Alloc {
+                    size, class, chunk, ..
+                } => {
+                    let used = live.entry((class, chunk)).or_insert_with(|| {
+                        live_per_class[class as usize] += 1;
+                        0
+                    });
+                    *used += size;
+                    bytes += size;
+                }
+                WorkloadEvent::Reset { class, chunk, .. } => {
+                    if let Some(used) = live.remove(&(class, chunk)) {
+                        live_per_class[class as usize] -= 1;
+                        bytes -= used;
+                    }
+                }
+            }
+
+            for (peak, live) in peak_per_class.iter_mut().zip(&live_per_class) {
+                *peak = (*peak).max(*live);
+            }
+            peak_chunks = peak_chunks.max(live.len());
+            peak_bytes = peak_bytes.max(bytes);
+        }
+
+        WorkloadAnalysis {
+            capacity: CapacityProfile {
+                classes: self
+                    .chunk_sizes
+                    .iter()
+                    .zip(peak_per_class)
+                    .map(|(&chunk_size, chunks)| ClassCapacity { chunk_size, chunks })
+                    .collect(),
+            },
+            peak_chunks,
+            peak_bytes,
+        }
+    }
+}
+
+/// Events logged while an arena is recording.
+#[derive(Debug, Default)]
+pub(crate) struct Recorder {
+    recording: Cell<bool>,
+    events: RefCell<Vec<WorkloadEvent>>,
+}
+
+impl Recorder {
+    /// Position of the next event.
+    fn sequence(&self) -> u64 {
+        self.events.borrow().len() as u64
+    }
+
+    /// Log an allocation of `size` bytes aligned to `align` in `chunk`.
+    #[inline]
+    pub fn record_alloc(&self, chunk: Chunk, size: usize, align: usize) {
+        if !self.recording.get() {
+            return;
+        }
+
+        let event = WorkloadEvent::Alloc {
+            sequence: self.sequence(),
+            size,
+            align,
+            class: chunk.state.config.class_for_size(chunk.size) as u32,
+            chunk: chunk.index as u32,
+        };
+        self.events.borrow_mut().push(event);
+    }
+
+    /// Log that every allocation in `chunk` is gone.
+    #[inline]
+    pub fn record_reset(&self, chunk: Chunk) {
+        if !self.recording.get() {
+            return;
+        }
+
+        let event = WorkloadEvent::Reset {
+            sequence: self.sequence(),
+            class: chunk.state.config.class_for_size(chunk.size) as u32,
+            chunk: chunk.index as u32,
+        };
+        self.events.borrow_mut().push(event);
+    }
+}
+
+impl Arena {
+    /// Start logging every allocation and chunk reset, dropping any earlier recording.
+    pub fn start_recording(&self) {
+        let recorder = &self.state.recorder;
+        recorder.events.borrow_mut().clear();
+        recorder.recording.set(true);
+    }
+
+    /// Stop recording, and take what was logged since [`Arena::start_recording`].
+    pub fn stop_recording(&self) -> WorkloadProfile {
+        let recorder = &self.state.recorder;
+        recorder.recording.set(false);
+
+        let events = recorder.events.take();
+        let classes = events
+            .iter()
+            .map(|event| match *event {
+                WorkloadEvent::Alloc { class, .. } | WorkloadEvent::Reset { class, .. } => {
+                    class as usize + 1
+                }
+            })
+            .max()
+            .unwrap_or(0);
+
+        WorkloadProfile {
+            chunk_sizes: (0..classes)
+                .map(|class| self.state.config.class_size(class))
+                .collect(),
+            events,
+        }
+    }
+}
+
+#[test]
+fn replayed_workloads_do_not_grow() {
+    let workload = |arena: &Arena| {
+        let small: Vec<_> = (0..40)
+            .map(|n| arena.alloc_slice_fill_copy(100, &n))
+            .collect();
+        drop(small);
+        let text: Vec<_> = (0..10)
+            .map(|_| arena.alloc_str(&"x".repeat(3000)))
+            .collect();
+        text.len()
+    };
+
+    let recorded = Arena::new();
+    recorded.alloc(0_u64);
+    recorded.start_recording();
+    workload(&recorded);
+    let profile = recorded.stop_recording();
+    recorded.alloc(0_u64);
+
+    // Every chunk is reset once its values are dropped
+    assert_eq!(profile.events.len(), 2 * (40 + 10));
+
+    #[cfg(feature = "serde")]
+    let profile: WorkloadProfile =
+        serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+
+    let analysis = profile.analyze();
+    assert_eq!(analysis.peak_bytes, 10 * 3000);
+    assert!(analysis.peak_chunks > 4);
+
+    let warm = Arena::new();
+    warm.prewarm_from_profile(&analysis.capacity);
+    let (len, delta) = warm.measure(|| workload(&warm));
+    assert_eq!(len, 10);
+    assert_eq!(delta.chunks, 0);
+}
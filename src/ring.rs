@@ -0,0 +1,279 @@
+use std::{
+    alloc::{self, Layout},
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use crate::RingFullError;
+
+/// Alignment of the memory of a ring.
+const RING_ALIGN: usize = 16;
+
+/// Where an allocation of the ring starts, and whether it is still alive.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    start: usize,
+    live: bool,
+}
+
+/// An arena reusing a fixed region of memory in a cycle, for streams
+/// where the oldest values go first, such as audio or video frames.
+///
+/// Each allocation goes after the newest one, wrapping around to the start
+/// of the region when it reaches the end. Space is reused once its value and
+/// every value allocated before it are dropped, so a value that is held on to
+/// blocks the ring. When there is no room, allocating fails instead of growing.
+pub struct RingArena {
+    start: NonNull<u8>,
+    capacity: usize,
+    /// Offset the next allocation goes after
+    head: Cell<usize>,
+    /// Allocations that were not reclaimed yet, oldest first
+    slots: RefCell<VecDeque<Slot>>,
+    /// Generation of the oldest slot, every allocation takes the next one
+    first_generation: Cell<u64>,
+    /// How many times allocations wrapped around
+    laps: Cell<usize>,
+}
+
+impl RingArena {
+    /// Create a ring over `capacity` bytes of memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero or too big for a layout.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "ring arenas need some capacity");
+        let layout =
+            Layout::from_size_align(capacity, RING_ALIGN).expect("ring capacity overflows");
+        let start = NonNull::new(unsafe { alloc::alloc(layout) })
+            .unwrap_or_else(|| alloc::handle_alloc_error(layout));
+
+        Self {
+            start,
+            capacity,
+            head: Cell::new(0),
+            slots: RefCell::new(VecDeque::new()),
+            first_generation: Cell::new(0),
+            laps: Cell::new(0),
+        }
+    }
+
+    /// Size of the memory of the ring.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// How many times allocations wrapped around to the start of the ring.
+    #[inline]
+    pub fn laps(&self) -> usize {
+        self.laps.get()
+    }
+
+    /// Allocate a value after the newest one.
+    pub fn alloc<T>(&self, value: T) -> Result<RingBoxed<'_, T>, RingFullError> {
+        let (ptr, generation) = self.allocate(Layout::new::<T>())?;
+        let ptr = ptr.cast::<T>();
+
+        unsafe {
+            ptr.as_ptr().write(value);
+            Ok(RingBoxed::new(ptr, generation, self))
+        }
+    }
+
+    /// Allocate a copy of a slice after the newest value.
+    pub fn alloc_slice_copy<T: Copy>(
+        &self,
+        source: &[T],
+    ) -> Result<RingBoxed<'_, [T]>, RingFullError> {
+        let (ptr, generation) = self.allocate(Layout::for_value(source))?;
+        let ptr = ptr.cast::<T>();
+
+        unsafe {
+            ptr::copy_nonoverlapping(source.as_ptr(), ptr.as_ptr(), source.len());
+            let slice = NonNull::slice_from_raw_parts(ptr, source.len());
+            Ok(RingBoxed::new(slice, generation, self))
+        }
+    }
+
+    /// Forget the dead slots at the front, making their space reusable.
+    fn reclaim(&self, slots: &mut VecDeque<Slot>) {
+        while slots.front().is_some_and(|slot| !slot.live) {
+            slots.pop_front();
+            self.first_generation.set(self.first_generation.get() + 1);
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(NonNull<u8>, u64), RingFullError> {
+        let mut slots = self.slots.borrow_mut();
+        self.reclaim(&mut slots);
+
+        // Every slot takes a byte at least, so a full ring
+        // cannot be mistaken for an empty one
+        let size = layout.size().max(1);
+        let base = self.start.as_ptr() as usize;
+        let fit = |from: usize, limit: usize| {
+            let start = (base + from).checked_next_multiple_of(layout.align())? - base;
+            let end = start.checked_add(size)?;
+            (end <= limit).then_some((start, end))
+        };
+
+        let head = self.head.get();
+        let wrapped = |limit| fit(0, limit).map(|(start, end)| (start, end, true));
+        let placed = match slots.front() {
+            None => fit(head, self.capacity)
+                .map(|(start, end)| (start, end, false))
+                .or_else(|| wrapped(self.capacity)),
+            Some(oldest) if oldest.start < head => fit(head, self.capacity)
+                .map(|(start, end)| (start, end, false))
+                .or_else(|| wrapped(oldest.start)),
+            Some(oldest) => fit(head, oldest.start).map(|(start, end)| (start, end, false)),
+        };
+        let Some((start, end, wrapped)) = placed else {
+            return Err(RingFullError::new(layout, self.capacity));
+        };
+
+        if wrapped {
+            self.laps.set(self.laps.get() + 1);
+        }
+        self.head.set(end);
+        slots.push_back(Slot { start, live: true });
+        let generation = self.first_generation.get() + slots.len() as u64 - 1;
+
+        Ok((
+            unsafe { NonNull::new_unchecked((base + start) as *mut u8) },
+            generation,
+        ))
+    }
+
+    /// Mark the slot of `generation` as dead.
+    fn release(&self, generation: u64) {
+        let mut slots = self.slots.borrow_mut();
+        let index = generation.wrapping_sub(self.first_generation.get()) as usize;
+
+        match slots.get_mut(index) {
+            Some(slot) if slot.live => slot.live = false,
+            _ => debug_assert!(false, "ring slot {generation} was already reclaimed"),
+        }
+        self.reclaim(&mut slots);
+    }
+}
+
+impl Debug for RingArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingArena")
+            .field("capacity", &self.capacity)
+            .field("head", &self.head.get())
+            .field("slots", &self.slots.borrow().len())
+            .field("laps", &self.laps.get())
+            .finish()
+    }
+}
+
+impl Drop for RingArena {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity, RING_ALIGN).unwrap();
+
+        // Every handle borrows the ring, so none are left
+        unsafe { alloc::dealloc(self.start.as_ptr(), layout) }
+    }
+}
+
+/// An owned value in a [`RingArena`], whose space is reused once it
+/// and every value allocated before it are dropped.
+pub struct RingBoxed<'ring, T: ?Sized> {
+    ptr: NonNull<T>,
+    /// Which allocation of the ring this is
+    generation: u64,
+    ring: &'ring RingArena,
+    _marker: PhantomData<T>,
+}
+
+impl<'ring, T: ?Sized> RingBoxed<'ring, T> {
+    #[inline]
+    unsafe fn new(ptr: NonNull<T>, generation: u64, ring: &'ring RingArena) -> Self {
+        Self {
+            ptr,
+            generation,
+            ring,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the raw pointer for this value.
+    #[inline]
+    pub fn as_raw(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+impl<'ring, T: ?Sized> Deref for RingBoxed<'ring, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<'ring, T: ?Sized> DerefMut for RingBoxed<'ring, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<'ring, T: ?Sized + Debug> Debug for RingBoxed<'ring, T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<'ring, T: ?Sized> Drop for RingBoxed<'ring, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+        self.ring.release(self.generation);
+    }
+}
+
+#[test]
+fn ring_wraps_around() {
+    let ring = RingArena::new(4096);
+    let mut frames = VecDeque::new();
+    let mut addresses = std::collections::HashSet::new();
+
+    for n in 0..64_u8 {
+        if frames.len() == 3 {
+            frames.pop_front();
+        }
+
+        let frame = ring.alloc_slice_copy(&[n; 1000]).unwrap();
+        assert!(frame.iter().all(|&byte| byte == n));
+        addresses.insert(frame.as_raw().cast::<u8>() as usize);
+        frames.push_back(frame);
+    }
+
+    // Four frames fit, and the fifth wraps around
+    assert!(ring.laps() >= 15);
+    assert_eq!(addresses.len(), 4);
+
+    // Holding on to the oldest frame blocks the ring,
+    // even once the frames after it are gone
+    let oldest = frames.pop_front().unwrap();
+    frames.clear();
+    let more: Vec<_> = (0..3)
+        .map_while(|_| ring.alloc_slice_copy(&[0_u8; 1000]).ok())
+        .collect();
+    assert_eq!(more.len(), 1);
+    let error = ring.alloc([0_u64; 125]).unwrap_err();
+    assert_eq!(error.requested().size(), 1000);
+
+    drop(oldest);
+    assert!(ring.alloc([0_u64; 125]).is_ok());
+}
@@ -0,0 +1,210 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    mem::{self, MaybeUninit},
+    ptr::NonNull,
+};
+
+use crate::{
+    array_layout,
+    builder::Config,
+    chunk::{Backing, Chunk, ChunkFooter, ChunkList, ListRef},
+    drops::PrefixGuard,
+    ptr::{Boxed, Ptr},
+    state::{State, StateRef},
+    ArenaAlloc, StackFullError,
+};
+
+/// An arena holding `N` bytes inline, for a few temporaries
+/// without touching the heap.
+///
+/// It hands out the same [`Boxed`] handles as [`Arena`](crate::Arena),
+/// and its memory is reused once every handle is gone.
+/// When the buffer is exhausted, allocations fail instead of spilling.
+pub struct StackArena<const N: usize> {
+    buf: UnsafeCell<[MaybeUninit<u8>; N]>,
+    /// Footer of the chunk covering `buf`, written on first use
+    footer: UnsafeCell<MaybeUninit<ChunkFooter>>,
+    /// List the chunk claims to belong to, it never frees the chunk
+    list: UnsafeCell<MaybeUninit<ChunkList>>,
+    /// Set once the footer and list were written
+    ready: Cell<bool>,
+    state: State,
+}
+
+impl<const N: usize> StackArena<N> {
+    /// Create an empty stack arena.
+    pub fn new() -> Self {
+        Self {
+            buf: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            footer: UnsafeCell::new(MaybeUninit::uninit()),
+            list: UnsafeCell::new(MaybeUninit::uninit()),
+            ready: Cell::new(false),
+            state: State::new(Config::default()),
+        }
+    }
+
+    /// Size of the inline buffer.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// How many bytes are left, ignoring alignment.
+    pub fn remaining(&self) -> usize {
+        self.chunk().remaining()
+    }
+
+    /// The chunk covering the buffer.
+    ///
+    /// The footer points at the buffer, so it is written again whenever the
+    /// arena moved. Handles borrow the arena, so none are alive by then.
+    fn chunk(&self) -> Chunk {
+        let start = self.buf.get().cast::<u8>();
+        let footer = self.footer.get().cast::<ChunkFooter>();
+
+        unsafe {
+            let chunk = Chunk::from_footer(NonNull::new_unchecked(footer));
+            if !self.ready.get() || chunk.start.as_ptr() != start {
+                let state = StateRef::new(&self.state);
+                let list = self.list.get().cast::<ChunkList>();
+                list.write(ChunkList::unboxed(N, state));
+
+                footer.write(ChunkFooter::new(
+                    NonNull::new_unchecked(start),
+                    N,
+                    0,
+                    None,
+                    ListRef::new(&*list),
+                    Backing::Inline,
+                    state,
+                ));
+                // Staying current keeps the chunk from ever being freed
+                chunk.toggle_current();
+                self.ready.set(true);
+            }
+
+            chunk
+        }
+    }
+
+    /// Bump allocate a layout in the buffer.
+    fn allocate(&self, layout: Layout) -> Result<Ptr<u8>, StackFullError> {
+        let chunk = self.chunk();
+        if !chunk.can_fit(layout) {
+            return Err(StackFullError::new(layout, chunk.remaining()));
+        }
+
+        Ok(Ptr::new(chunk, chunk.alloc_layout(layout)))
+    }
+
+    /// Allocate a value.
+    pub fn alloc<T>(&self, value: T) -> Result<Boxed<'_, T>, StackFullError> {
+        let ptr = self.allocate(Layout::new::<T>())?.cast::<T>();
+
+        unsafe {
+            ptr.write(value);
+            Ok(Boxed::new(ptr))
+        }
+    }
+
+    /// Allocate a slice, filled with `f` applied to every index.
+    pub fn alloc_slice_fill_with<T>(
+        &self,
+        len: usize,
+        mut f: impl FnMut(usize) -> T,
+    ) -> Result<Boxed<'_, [T]>, StackFullError> {
+        let layout = array_layout::<T>(len)
+            .ok_or_else(|| StackFullError::new(Layout::new::<T>(), self.remaining()))?;
+        let ptr = self.allocate(layout)?.cast::<T>();
+
+        unsafe {
+            // A panicking closure only drops what it made
+            let mut prefix = PrefixGuard {
+                start: ptr.as_raw(),
+                len: 0,
+            };
+            for i in 0..len {
+                ptr.add(i).write(f(i));
+                prefix.len += 1;
+            }
+            mem::forget(prefix);
+
+            Ok(Boxed::new(ptr.slice(len)))
+        }
+    }
+
+    /// Allocate a copy of a slice.
+    pub fn alloc_slice_copy<T: Copy>(
+        &self,
+        source: &[T],
+    ) -> Result<Boxed<'_, [T]>, StackFullError> {
+        let ptr = self.allocate(Layout::for_value(source))?.cast::<T>();
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(source.as_ptr(), ptr.as_raw(), source.len());
+            Ok(Boxed::new(ptr.slice(source.len())))
+        }
+    }
+
+    /// Allocate a copy of a string.
+    pub fn alloc_str(&self, source: &str) -> Result<Boxed<'_, str>, StackFullError> {
+        let ptr = self.alloc_slice_copy(source.as_bytes())?.into_ptr();
+
+        unsafe { Ok(Boxed::from_ptr(ptr.map_raw(|raw| raw as *mut str))) }
+    }
+}
+
+impl<const N: usize> Default for StackArena<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Allocations panic when the buffer is exhausted.
+impl<const N: usize> ArenaAlloc for StackArena<N> {
+    #[track_caller]
+    fn alloc_value<T>(&self, value: T) -> &mut T {
+        self.alloc(value).unwrap().leak()
+    }
+
+    #[track_caller]
+    fn alloc_str(&self, source: &str) -> &str {
+        StackArena::alloc_str(self, source).unwrap().leak()
+    }
+
+    #[track_caller]
+    fn alloc_slice_copy<T: Copy>(&self, source: &[T]) -> &[T] {
+        StackArena::alloc_slice_copy(self, source).unwrap().leak()
+    }
+}
+
+#[test]
+fn stack_arena_reuse() {
+    use std::rc::Rc;
+
+    let arena = StackArena::<64>::new();
+    let byte = arena.alloc(1_u8).unwrap();
+    let wide = arena.alloc(2_u64).unwrap();
+    assert!((wide.as_raw() as usize).is_multiple_of(8));
+    let text = arena.alloc_str("hi").unwrap();
+    assert_eq!((*byte, *wide, &*text), (1, 2, "hi"));
+
+    // Exhausted, without spilling anywhere
+    let error = arena.alloc([0_u8; 64]).unwrap_err();
+    assert_eq!(error.requested(), Layout::new::<[u8; 64]>());
+    assert!(error.remaining() < 64);
+
+    // Values drop with their handles, then the buffer starts over
+    let counter = Rc::new(());
+    let values = arena.alloc_slice_fill_with(2, |_| counter.clone()).unwrap();
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop((byte, wide, text, values));
+    assert_eq!(Rc::strong_count(&counter), 1);
+    assert_eq!(arena.remaining(), 64);
+
+    // Moved arenas point their chunk at the new buffer
+    let moved = arena;
+    let words: &[&str] = crate::arena_alloc::parse_words(&moved, "a b");
+    assert_eq!(words, ["a", "b"]);
+}
@@ -0,0 +1,287 @@
+use std::{
+    cell::Cell,
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    builder::Config,
+    chunk::{Chunk, Reservation},
+    drops::DropRegistry,
+    epoch::Epochs,
+    fail::fail,
+    hooks::{DropHooks, RecycleHooks},
+    warnings::Warnings,
+    AllocError,
+};
+
+#[cfg(feature = "failpoints")]
+use crate::failpoints::FailPoints;
+#[cfg(feature = "latency-hist")]
+use crate::latency::LatencyHistogram;
+#[cfg(feature = "leak-tracking")]
+use crate::leak::LeakTable;
+#[cfg(feature = "profiling")]
+use crate::profiling::Profiled;
+#[cfg(feature = "quarantine")]
+use crate::quarantine::Quarantine;
+#[cfg(feature = "record")]
+use crate::record::Recorder;
+#[cfg(feature = "tagging")]
+use crate::tag::Tags;
+#[cfg(feature = "trace-export")]
+use crate::trace::TraceBuffer;
+
+/// Source of arena IDs, never reused within a process.
+static NEXT_ARENA_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Arena wide state that chunks need to reach.
+///
+/// It is boxed by the arena so its address stays
+/// stable when the arena moves.
+#[derive(Debug)]
+pub(crate) struct State {
+    /// Tells arenas apart, even ones built at the address of a dropped one
+    pub(crate) id: u64,
+    /// Bumped whenever a chunk leaves its list, so marks pointing
+    /// at chunks can tell they may be stale
+    pub(crate) chunk_generation: Cell<u64>,
+    /// Options set by the builder
+    pub(crate) config: Config,
+    /// Drop glue to run on teardown, when enabled
+    pub(crate) drops: DropRegistry,
+    /// Callbacks to run when the arena drops
+    pub(crate) hooks: DropHooks,
+    /// Callbacks to run when a chunk is recycled
+    pub(crate) recycle_hooks: RecycleHooks,
+    /// Set when an allocation unwound halfway through
+    pub(crate) poisoned: Cell<bool>,
+    /// How many no-growth scopes are alive
+    pub(crate) no_growth: Cell<usize>,
+    /// Warning thresholds and callbacks
+    pub(crate) warnings: Warnings,
+    /// Allocations that live for a number of epochs
+    pub(crate) epochs: Epochs,
+    /// Address space that chunks are carved out of, when configured
+    pub(crate) reservation: Option<Reservation>,
+    /// How many chunks were allocated over the lifetime of the arena
+    pub(crate) chunk_allocations: Cell<usize>,
+    /// How many chunks asked for explicit huge pages and did not get them
+    pub(crate) huge_page_fallbacks: Cell<usize>,
+    /// How many allocations were bumped out of chunks
+    pub(crate) allocations: Cell<usize>,
+    /// Bytes those allocations asked for
+    pub(crate) requested_bytes: Cell<usize>,
+    /// Bytes the bump pointers moved for them, including alignment padding
+    pub(crate) consumed_bytes: Cell<usize>,
+    /// Allocation tags
+    #[cfg(feature = "tagging")]
+    pub(crate) tags: Tags,
+    /// Where live allocations came from
+    #[cfg(feature = "leak-tracking")]
+    pub(crate) leaks: LeakTable,
+    /// Time spent allocating chunks
+    #[cfg(feature = "latency-hist")]
+    pub(crate) latency: LatencyHistogram,
+    /// Injected chunk allocation failures
+    #[cfg(feature = "failpoints")]
+    pub(crate) failpoints: FailPoints,
+    /// Freed chunks held back from reuse
+    #[cfg(feature = "quarantine")]
+    pub(crate) quarantine: Quarantine,
+    /// Allocations logged for a workload profile
+    #[cfg(feature = "record")]
+    pub(crate) recorder: Recorder,
+    /// Allocations reported to the profiler
+    #[cfg(feature = "profiling")]
+    pub(crate) profiled: Profiled,
+    /// Newest events, for a timeline
+    #[cfg(feature = "trace-export")]
+    pub(crate) trace: TraceBuffer,
+}
+
+impl State {
+    pub fn new(config: Config) -> Self {
+        let reservation = config.reserve.and_then(Reservation::new);
+
+        Self {
+            id: NEXT_ARENA_ID.fetch_add(1, Ordering::Relaxed),
+            chunk_generation: Cell::new(0),
+            config,
+            drops: DropRegistry::default(),
+            hooks: DropHooks::default(),
+            recycle_hooks: RecycleHooks::default(),
+            poisoned: Cell::new(false),
+            no_growth: Cell::new(0),
+            warnings: Warnings::default(),
+            epochs: Epochs::default(),
+            reservation,
+            chunk_allocations: Cell::new(0),
+            huge_page_fallbacks: Cell::new(0),
+            allocations: Cell::new(0),
+            requested_bytes: Cell::new(0),
+            consumed_bytes: Cell::new(0),
+            #[cfg(feature = "tagging")]
+            tags: Tags::default(),
+            #[cfg(feature = "leak-tracking")]
+            leaks: LeakTable::default(),
+            #[cfg(feature = "latency-hist")]
+            latency: LatencyHistogram::default(),
+            #[cfg(feature = "failpoints")]
+            failpoints: FailPoints::default(),
+            #[cfg(feature = "quarantine")]
+            quarantine: Quarantine::default(),
+            #[cfg(feature = "record")]
+            recorder: Recorder::default(),
+            #[cfg(feature = "profiling")]
+            profiled: Profiled::default(),
+            #[cfg(feature = "trace-export")]
+            trace: TraceBuffer::default(),
+        }
+    }
+
+    /// Called when every allocation in a chunk is gone
+    /// and its bump pointer was reset.
+    #[inline]
+    pub fn on_chunk_reset(&self, chunk: Chunk) {
+        self.recycle_hooks.run(chunk);
+
+        if self.config.teardown_drops {
+            self.drops.release_chunk(chunk);
+        } else {
+            self.drops.release_dtors(chunk);
+        }
+
+        #[cfg(feature = "tagging")]
+        self.tags.release_chunk(chunk);
+
+        #[cfg(feature = "record")]
+        self.recorder.record_reset(chunk);
+
+        #[cfg(feature = "profiling")]
+        self.profiled.release_chunk(chunk);
+
+        #[cfg(feature = "trace-export")]
+        self.trace.reset(chunk);
+    }
+
+    /// Called when a chunk list allocated a chunk.
+    #[inline]
+    pub fn count_chunk_allocation(&self, _chunk: Chunk) {
+        self.chunk_allocations.set(self.chunk_allocations.get() + 1);
+
+        #[cfg(feature = "trace-export")]
+        self.trace.chunk_alloc(_chunk);
+    }
+
+    /// Called when `requested` bytes were bumped out of a chunk,
+    /// moving its bump pointer by `consumed` bytes.
+    #[inline]
+    pub fn count_allocation(&self, requested: usize, consumed: usize) {
+        self.allocations.set(self.allocations.get() + 1);
+        self.requested_bytes
+            .set(self.requested_bytes.get().wrapping_add(requested));
+        self.consumed_bytes
+            .set(self.consumed_bytes.get().wrapping_add(consumed));
+    }
+
+    /// Checks if any callback was registered on the arena.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn has_callbacks(&self) -> bool {
+        !(self.hooks.is_empty() && self.recycle_hooks.is_empty() && self.warnings.is_empty())
+    }
+
+    /// Called before asking the backing allocator for `size` bytes of chunks.
+    ///
+    /// Refuses inside a no-growth scope, and always for arenas made
+    /// [`from_raw_parts`](crate::Arena::from_raw_parts).
+    #[inline]
+    pub fn check_growth(&self, size: usize) -> Result<(), AllocError> {
+        if self.config.fixed_chunks || self.no_growth.get() > 0 {
+            return Err(AllocError::growth_forbidden(size));
+        }
+
+        Ok(())
+    }
+
+    /// Called once an allocation failed and nothing else can hold it.
+    ///
+    /// Refused growth inside a no-growth scope panics instead, if configured to.
+    #[inline]
+    #[track_caller]
+    pub fn refuse_growth(&self, error: AllocError) -> AllocError {
+        let panics = self
+            .config
+            .no_growth_panics
+            .unwrap_or(cfg!(debug_assertions));
+
+        if panics
+            && error.is_growth_forbidden()
+            && !self.config.fixed_chunks
+            && self.no_growth.get() > 0
+        {
+            fail(self.config.label, None, None, None, error)
+        }
+
+        error
+    }
+
+    /// Called when a chunk was reset while allocations in it may still be tracked.
+    pub fn on_chunk_discarded(&self, chunk: Chunk) {
+        self.on_chunk_reset(chunk);
+
+        #[cfg(feature = "leak-tracking")]
+        self.leaks
+            .release_range(chunk.start.as_ptr() as usize, chunk.size);
+    }
+}
+
+/// Pointer to the [`State`] of an arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub(crate) struct StateRef(NonNull<State>);
+
+impl StateRef {
+    #[inline]
+    pub fn new(state: &State) -> Self {
+        Self(NonNull::from(state))
+    }
+}
+
+impl Deref for StateRef {
+    type Target = State;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+/// Poisons the arena if it is dropped while unwinding.
+///
+/// Held across allocation steps that leave the arena
+/// inconsistent when they are interrupted.
+pub(crate) struct PoisonGuard<'a>(&'a State);
+
+impl<'a> PoisonGuard<'a> {
+    #[inline]
+    pub fn new(state: &'a State) -> Self {
+        Self(state)
+    }
+
+    /// The guarded steps completed.
+    #[inline]
+    pub fn disarm(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for PoisonGuard<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.poisoned.set(true);
+        }
+    }
+}
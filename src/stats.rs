@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+/// A snapshot of an arena's memory usage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// The label of the arena, if it has one.
+    pub label: Option<&'static str>,
+    /// Number of size classes.
+    pub classes: usize,
+    /// Number of chunks allocated across every class.
+    pub chunks: usize,
+    /// Bytes requested from the backing allocator for chunks.
+    pub reserved_bytes: usize,
+    /// Number of chunks mapped with explicit huge pages.
+    pub huge_page_chunks: usize,
+    /// Number of chunks advised to use transparent huge pages.
+    ///
+    /// The kernel decides whether they really get them.
+    pub advised_chunks: usize,
+    /// Number of chunks that could not get explicit huge pages,
+    /// and asked for transparent ones instead.
+    pub huge_page_fallbacks: usize,
+    /// Number of chunks deallocated by the retention policy.
+    pub trimmed_chunks: usize,
+    /// Number of undersized chunks allocated because a full one could not be.
+    pub emergency_chunks: usize,
+}
+
+/// What the allocations made during [`Arena::measure`](crate::Arena::measure) took up.
+///
+/// Measurements include any nested in them, and allocations that fail count nowhere.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationDelta {
+    /// Number of allocations.
+    pub allocations: usize,
+    /// Bytes the allocations asked for.
+    pub requested_bytes: usize,
+    /// Bytes taken from chunks, including alignment padding.
+    pub consumed_bytes: usize,
+    /// Number of chunks allocated.
+    pub chunks: usize,
+}
+
+/// Where a value lives, without its address.
+///
+/// Chunks are numbered in the order their class allocated them,
+/// so the same allocations give the same placements on every run,
+/// wherever the backing allocator put the chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    /// Size class of the chunk.
+    pub class: usize,
+    /// Index of the chunk within its class.
+    pub chunk: usize,
+    /// Offset of the value from the start of the chunk.
+    pub offset: usize,
+}
+
+impl Display for ArenaStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = self.label {
+            write!(f, "{label}: ")?;
+        }
+
+        write!(
+            f,
+            "{} chunks in {} classes, {} bytes reserved ({} huge page chunks, {} advised, {} huge page fallbacks, {} trimmed, {} emergency)",
+            self.chunks,
+            self.classes,
+            self.reserved_bytes,
+            self.huge_page_chunks,
+            self.advised_chunks,
+            self.huge_page_fallbacks,
+            self.trimmed_chunks,
+            self.emergency_chunks
+        )
+    }
+}
+
+#[test]
+fn placements_are_reproducible() {
+    use crate::Arena;
+
+    let run = || {
+        let arena = Arena::new();
+        let first = arena.alloc(1_u64);
+        let bytes = arena.alloc([2_u8; 3]);
+        let word = arena.alloc(3_u32).into_ref();
+        let text = arena.alloc_str("hello").into_mut();
+        let big = arena.alloc_slice_fill_copy(300, &0_u8);
+
+        vec![
+            first.placement(),
+            bytes.placement(),
+            word.placement(),
+            text.placement(),
+            big.placement(),
+        ]
+    };
+
+    // Values are bumped down from the end of the chunk
+    let placement = |class, offset| Placement {
+        class,
+        chunk: 3,
+        offset,
+    };
+    let golden = [
+        placement(0, 248),
+        placement(0, 245),
+        placement(0, 240),
+        placement(0, 235),
+        placement(1, 212),
+    ];
+    for _ in 0..3 {
+        assert_eq!(run(), golden);
+    }
+}
+
+#[test]
+fn measurements_nest() {
+    use crate::Arena;
+
+    let arena = Arena::new();
+    let _warm = arena.alloc(0_u8);
+
+    let ((inner, value), outer) = arena.measure(|| {
+        let _byte = arena.alloc(1_u8);
+        let (value, inner) = arena.measure(|| arena.alloc(2_u64));
+        (inner, value)
+    });
+    assert_eq!(*value, 2);
+    assert_eq!(inner.allocations, 1);
+    assert_eq!(inner.requested_bytes, 8);
+    // The bytes left the bump pointer misaligned for the u64
+    assert_eq!(inner.consumed_bytes, 14);
+    assert_eq!(inner.chunks, 0);
+
+    assert_eq!(outer.allocations, 2);
+    assert_eq!(outer.requested_bytes, 9);
+    assert_eq!(outer.consumed_bytes - inner.consumed_bytes, 1);
+
+    // Growing a new class shows up as chunks
+    let (_big, grown) = arena.measure(|| arena.alloc_slice_fill_copy(1000, &0_u8));
+    assert!(grown.chunks > 0);
+    assert_eq!(grown.requested_bytes, 1000);
+}
@@ -0,0 +1,296 @@
+use std::{
+    alloc::Layout,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    builder::Config,
+    chunk::{sys, AtomicSlot, Chunk, ChunkList, FreeList, ListRef},
+    fail::fail,
+    state::{State, StateRef},
+    AllocError, MIN_BLOCK_SIZE,
+};
+
+/// Size of the chunks of a [`SyncArena`] made with [`SyncArena::new`].
+pub const SYNC_CHUNK_SIZE: usize = 1 << 16;
+
+/// An arena shared between threads, which all allocate and drop values in it.
+///
+/// Chunks count their references atomically, and whichever thread drops
+/// the last value in a chunk puts it back on a lock free list. Bumping through
+/// the current chunk takes a lock, so allocating costs more than with
+/// [`Arena`](crate::Arena). Every chunk has the same size, and values
+/// that do not fit in one are refused.
+pub struct SyncArena {
+    /// Chunk allocations bump through, locked while allocating
+    current: Mutex<Option<Chunk>>,
+    /// Chunks without values, only popped under the lock
+    free_list: FreeList<AtomicSlot>,
+    /// Every chunk, deallocating them on drop. Only touched under the lock
+    list: Box<ChunkList>,
+    state: Box<State>,
+}
+
+// Chunks are only bumped and allocated under the lock,
+// everything handles touch from other threads is atomic
+unsafe impl Send for SyncArena {}
+unsafe impl Sync for SyncArena {}
+
+impl SyncArena {
+    /// Create an empty arena, with chunks of [`SYNC_CHUNK_SIZE`] bytes.
+    pub fn new() -> Self {
+        Self::with_chunk_size(SYNC_CHUNK_SIZE)
+    }
+
+    /// Create an empty arena, with chunks of `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a power of two of at least [`MIN_BLOCK_SIZE`].
+    pub fn with_chunk_size(size: usize) -> Self {
+        assert!(
+            size.is_power_of_two() && size >= MIN_BLOCK_SIZE,
+            "chunk size must be a power of two of at least MIN_BLOCK_SIZE, got {size}"
+        );
+
+        let state = Box::new(State::new(Config::default()));
+        let list = ChunkList::empty(size, StateRef::new(&state));
+
+        Self {
+            current: Mutex::new(None),
+            free_list: FreeList::new(),
+            list,
+            state,
+        }
+    }
+
+    /// Size of every chunk.
+    #[inline]
+    pub fn chunk_size(&self) -> usize {
+        self.list.chunk_size()
+    }
+
+    /// How many chunks were allocated.
+    pub fn chunks(&self) -> usize {
+        let _current = self.lock();
+        self.list.len()
+    }
+
+    /// How many chunks hold no values.
+    #[inline]
+    pub fn free_chunks(&self) -> usize {
+        self.free_list.len()
+    }
+
+    /// Allocate a value.
+    #[track_caller]
+    pub fn alloc<T>(&self, value: T) -> SyncBoxed<'_, T> {
+        match self.try_alloc(value) {
+            Ok(boxed) => boxed,
+            Err(error) => fail(None, Some(Layout::new::<T>()), None, None, error),
+        }
+    }
+
+    /// Allocate a value, returning an error instead of panicking.
+    ///
+    /// The value is dropped if it could not be allocated.
+    pub fn try_alloc<T>(&self, value: T) -> Result<SyncBoxed<'_, T>, AllocError> {
+        let (chunk, ptr) = self.allocate(Layout::new::<T>())?;
+        let ptr = ptr.cast::<T>();
+
+        unsafe { ptr.as_ptr().write(value) };
+        Ok(SyncBoxed {
+            ptr,
+            chunk,
+            arena: self,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Lock the current chunk, which a panic elsewhere leaves usable.
+    fn lock(&self) -> MutexGuard<'_, Option<Chunk>> {
+        self.current
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    /// Bump a layout out of the current chunk, taking a reference to it.
+    fn allocate(&self, layout: Layout) -> Result<(Chunk, NonNull<u8>), AllocError> {
+        let size = self.chunk_size();
+        if layout.size() > size {
+            return Err(AllocError::too_large(layout.size()));
+        }
+        let chunk_align = sys::chunk_align(size);
+        if layout.align() > chunk_align {
+            return Err(AllocError::unsupported_align(size, chunk_align));
+        }
+
+        let mut current = self.lock();
+        let chunk = match *current {
+            Some(chunk) if chunk.can_fit(layout) => chunk,
+            full => {
+                // Retiring first lets an empty chunk come straight back
+                *current = None;
+                if let Some(full) = full {
+                    if full.retire_shared() {
+                        self.release(full);
+                    }
+                }
+
+                let chunk = self.next_chunk()?;
+                chunk.toggle_current();
+                *current = Some(chunk);
+                chunk
+            }
+        };
+
+        chunk.add_ref_shared();
+        Ok((chunk, chunk.alloc_layout(layout)))
+    }
+
+    /// Take a free chunk, or allocate one. Called under the lock.
+    fn next_chunk(&self) -> Result<Chunk, AllocError> {
+        if let Some(chunk) = self.free_list.pop() {
+            return Ok(chunk);
+        }
+
+        let list = &self.list;
+        let chunk = unsafe {
+            Chunk::try_allocate(
+                list.chunk_size(),
+                list.len(),
+                list.chunks().next(),
+                ListRef::new(list),
+                list.state(),
+            )?
+        };
+        self.state.count_chunk_allocation(chunk);
+        list.link(chunk);
+
+        Ok(chunk)
+    }
+
+    /// Put a chunk without references back on the free list, from any thread.
+    fn release(&self, chunk: Chunk) {
+        unsafe { chunk.reset_bump() };
+        self.free_list.push(chunk).expect("failed to free chunk");
+    }
+}
+
+impl Default for SyncArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for SyncArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncArena")
+            .field("chunk_size", &self.chunk_size())
+            .field("chunks", &self.chunks())
+            .field("free_chunks", &self.free_chunks())
+            .finish()
+    }
+}
+
+/// A value in a [`SyncArena`], which can be sent to and dropped on any thread.
+pub struct SyncBoxed<'arena, T> {
+    ptr: NonNull<T>,
+    chunk: Chunk,
+    arena: &'arena SyncArena,
+    _marker: PhantomData<T>,
+}
+
+// The chunk is only touched through its atomic reference count
+unsafe impl<T: Send> Send for SyncBoxed<'_, T> {}
+unsafe impl<T: Sync> Sync for SyncBoxed<'_, T> {}
+
+impl<T> Deref for SyncBoxed<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for SyncBoxed<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Debug> Debug for SyncBoxed<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<T> Drop for SyncBoxed<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+
+        if self.chunk.remove_ref_shared() {
+            self.arena.release(self.chunk);
+        }
+    }
+}
+
+#[test]
+fn threads_share_an_arena() {
+    use std::sync::Arc;
+
+    type Kept<'arena> = Vec<SyncBoxed<'arena, (usize, Arc<()>)>>;
+
+    const THREADS: usize = 8;
+    let values = if cfg!(miri) { 1_000 } else { 1_000_000 };
+
+    let arena = SyncArena::new();
+    let shared = Arc::new(());
+    let kept: Vec<Kept<'_>> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let (arena, shared) = (&arena, &shared);
+                scope.spawn(move || {
+                    let mut kept = Vec::new();
+                    for n in 0..values {
+                        let value = arena.alloc((thread * values + n, shared.clone()));
+                        // A few values outlive the thread, the rest are dropped right away
+                        if n < 100 {
+                            kept.push(value);
+                        }
+                    }
+                    kept
+                })
+            })
+            .collect();
+
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect()
+    });
+
+    assert_eq!(Arc::strong_count(&shared), 1 + THREADS * 100);
+    for (thread, kept) in kept.iter().enumerate() {
+        assert!(kept
+            .iter()
+            .enumerate()
+            .all(|(n, value)| value.0 == thread * values + n));
+    }
+
+    // Chunks are reused once their values are gone
+    let chunks = arena.chunks();
+    assert!(chunks < 4 * THREADS, "{chunks} chunks");
+
+    drop(kept);
+    assert_eq!(Arc::strong_count(&shared), 1);
+    assert!(arena.free_chunks() >= chunks - 1);
+
+    assert!(arena.try_alloc([0_u8; SYNC_CHUNK_SIZE + 1]).is_err());
+}
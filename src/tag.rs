@@ -0,0 +1,252 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    ops::Deref,
+};
+
+use crate::{chunk::Chunk, Arena};
+
+/// How many of the newest tagged allocations are remembered,
+/// so their bytes can be released when their slot is given back.
+const RECENT: usize = 64;
+
+/// Per tag allocation counters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TagReport {
+    /// Name passed to [`Arena::tagged`].
+    pub name: &'static str,
+    /// Allocations made under this tag.
+    pub allocations: usize,
+    /// Bytes ever allocated under this tag.
+    pub total_bytes: usize,
+    /// Bytes in chunks that have not been reclaimed yet,
+    /// either by a reset or by dropping the newest value of a chunk.
+    pub live_bytes: usize,
+}
+
+/// A tagged allocation that may still be given back to its chunk.
+#[derive(Debug)]
+struct Recent {
+    chunk: Chunk,
+    ptr: *const u8,
+    tag: usize,
+    bytes: usize,
+}
+
+/// The tag table of an arena.
+#[derive(Debug, Default)]
+pub(crate) struct Tags {
+    /// Index of the active tag
+    current: Cell<Option<usize>>,
+    /// Counters, indexed by tag
+    table: RefCell<Vec<TagReport>>,
+    /// Bytes each tag holds in a chunk, indexed by tag,
+    /// so they can be released when the chunk resets
+    held: RefCell<Vec<Vec<(Chunk, usize)>>>,
+    /// The newest tagged allocations, newest last
+    recent: RefCell<VecDeque<Recent>>,
+}
+
+impl Tags {
+    fn index_of(&self, name: &'static str) -> usize {
+        let mut table = self.table.borrow_mut();
+
+        match table.iter().position(|report| report.name == name) {
+            Some(index) => index,
+            None => {
+                table.push(TagReport {
+                    name,
+                    ..TagReport::default()
+                });
+                self.held.borrow_mut().push(Vec::new());
+                table.len() - 1
+            }
+        }
+    }
+
+    /// Attribute an allocation to the active tag, if any.
+    #[inline]
+    pub fn record(&self, chunk: Chunk, ptr: *const u8, bytes: usize) {
+        let Some(index) = self.current.get() else {
+            return;
+        };
+
+        let report = &mut self.table.borrow_mut()[index];
+        report.allocations += 1;
+        report.total_bytes += bytes;
+        report.live_bytes += bytes;
+
+        // The chunk is almost always the newest one of the tag
+        let held = &mut self.held.borrow_mut()[index];
+        match held.iter_mut().rev().find(|(held, _)| *held == chunk) {
+            Some((_, held)) => *held += bytes,
+            None => held.push((chunk, bytes)),
+        }
+
+        let mut recent = self.recent.borrow_mut();
+        if recent.len() == RECENT {
+            recent.pop_front();
+        }
+        recent.push_back(Recent {
+            chunk,
+            ptr,
+            tag: index,
+            bytes,
+        });
+    }
+
+    /// Release the live bytes of a tagged allocation whose slot
+    /// was given back to its chunk.
+    ///
+    /// Only the newest allocations are remembered, the bytes of older ones
+    /// stay live until their chunk resets.
+    pub fn give_back(&self, chunk: Chunk, ptr: *const u8) {
+        let mut recent = self.recent.borrow_mut();
+        let Some(position) = recent
+            .iter()
+            .rposition(|recent| recent.chunk == chunk && recent.ptr == ptr)
+        else {
+            return;
+        };
+        let Recent { tag, bytes, .. } = recent.remove(position).unwrap();
+
+        self.table.borrow_mut()[tag].live_bytes -= bytes;
+        if let Some((_, held)) = self.held.borrow_mut()[tag]
+            .iter_mut()
+            .rev()
+            .find(|(held, _)| *held == chunk)
+        {
+            *held -= bytes;
+        }
+    }
+
+    /// Release the live bytes every tag held in a chunk.
+    pub fn release_chunk(&self, chunk: Chunk) {
+        self.recent
+            .borrow_mut()
+            .retain(|recent| recent.chunk != chunk);
+
+        let mut table = self.table.borrow_mut();
+        for (report, held) in table.iter_mut().zip(self.held.borrow_mut().iter_mut()) {
+            if let Some(position) = held.iter().position(|(held, _)| *held == chunk) {
+                report.live_bytes -= held.swap_remove(position).1;
+            }
+        }
+    }
+}
+
+/// Routes allocations to a tag while it is alive.
+///
+/// The previous tag is restored when the guard drops, so nested guards
+/// have to drop in the reverse order they were made.
+#[derive(Debug)]
+pub struct TagGuard<'a> {
+    arena: &'a Arena,
+    index: usize,
+    previous: Option<usize>,
+}
+
+impl<'a> TagGuard<'a> {
+    /// Get the tagged arena.
+    #[inline]
+    pub fn arena(&self) -> &'a Arena {
+        self.arena
+    }
+}
+
+impl<'a> Deref for TagGuard<'a> {
+    type Target = Arena;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.arena
+    }
+}
+
+impl<'a> Drop for TagGuard<'a> {
+    fn drop(&mut self) {
+        let current = &self.arena.state.tags.current;
+        assert!(
+            current.get() == Some(self.index) || std::thread::panicking(),
+            "tag guards must drop in the reverse order they were made"
+        );
+        current.set(self.previous);
+    }
+}
+
+impl Arena {
+    /// Attribute allocations made while the guard is alive to `name`.
+    pub fn tagged(&self, name: &'static str) -> TagGuard<'_> {
+        let tags = &self.state.tags;
+        let index = tags.index_of(name);
+
+        TagGuard {
+            arena: self,
+            index,
+            previous: tags.current.replace(Some(index)),
+        }
+    }
+
+    /// List the allocation counters of every tag.
+    pub fn tag_report(&self) -> Vec<TagReport> {
+        self.state.tags.table.borrow().clone()
+    }
+}
+
+#[test]
+fn tag_live_bytes() {
+    let arena = Arena::new();
+
+    let physics = arena.tagged("physics");
+    let value = physics.arena().alloc([0_u8; 64]);
+    drop(physics);
+    let untagged = arena.alloc(0_u64);
+
+    let report = arena.tag_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].name, "physics");
+    assert_eq!(report[0].allocations, 1);
+    assert_eq!(report[0].live_bytes, 64);
+
+    drop(untagged);
+    drop(value);
+    let report = arena.tag_report();
+    assert_eq!(report[0].total_bytes, 64);
+    assert_eq!(report[0].live_bytes, 0);
+
+    // Slots given back and reused are only counted while they are taken
+    let physics = arena.tagged("physics");
+    for _ in 0..1000 {
+        let outer = arena.alloc([0_u8; 64]);
+        drop(arena.alloc([0_u8; 64]));
+        drop(outer);
+    }
+    let kept = arena.alloc([0_u8; 64]);
+    drop(physics);
+
+    let report = arena.tag_report();
+    assert_eq!(report[0].allocations, 2002);
+    assert_eq!(report[0].live_bytes, 64);
+    drop(kept);
+    assert_eq!(arena.tag_report()[0].live_bytes, 0);
+}
+
+#[test]
+fn tag_guards_nest() {
+    use std::panic::AssertUnwindSafe;
+
+    let arena = Arena::new();
+    let outer = arena.tagged("outer");
+    let inner = arena.tagged("inner");
+    arena.alloc(0_u64).leak();
+    drop(inner);
+    arena.alloc(0_u64).leak();
+
+    let report = arena.tag_report();
+    assert_eq!((report[0].allocations, report[1].allocations), (1, 1));
+
+    // Dropping the outer guard first would leave the inner tag active
+    let inner = arena.tagged("inner");
+    std::panic::catch_unwind(AssertUnwindSafe(|| drop(outer))).unwrap_err();
+    drop(inner);
+}
@@ -0,0 +1,123 @@
+use std::{cell::RefCell, future::Future, mem::ManuallyDrop};
+
+use crate::Arena;
+
+/// How many arenas of finished scopes each thread keeps around.
+const POOL_SIZE: usize = 4;
+
+thread_local! {
+    /// Reset arenas, ready for the next scope on this thread.
+    static POOL: RefCell<Vec<Arena>> = const { RefCell::new(Vec::new()) };
+}
+
+tokio::task_local! {
+    static ARENA: ScopeArena;
+}
+
+/// The arena of an [`arena_scope`], which goes back to the pool when the scope ends.
+struct ScopeArena(ManuallyDrop<Arena>);
+
+impl Drop for ScopeArena {
+    fn drop(&mut self) {
+        let mut arena = unsafe { ManuallyDrop::take(&mut self.0) };
+
+        // Callbacks would leak into unrelated scopes
+        if arena.is_poisoned() || arena.state.has_callbacks() {
+            return;
+        }
+
+        // Handles never leave `current_arena`, so none are left
+        arena.reset_classes_below(usize::MAX);
+
+        let _ = POOL.try_with(move |pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < POOL_SIZE {
+                pool.push(arena);
+            }
+        });
+    }
+}
+
+/// Run `future` with an arena that [`current_arena`] reaches from anywhere beneath it.
+///
+/// The arena comes from a per-thread pool, and is reset and returned to it
+/// when the scope completes or is dropped halfway.
+/// Arenas are not [`Send`], so neither is the scope. Run it with
+/// [`spawn_local`](tokio::task::spawn_local) or on a current thread runtime.
+pub async fn arena_scope<F: Future>(future: F) -> F::Output {
+    let arena = POOL
+        .with(|pool| pool.borrow_mut().pop())
+        .unwrap_or_default();
+
+    ARENA
+        .scope(ScopeArena(ManuallyDrop::new(arena)), future)
+        .await
+}
+
+/// Run `f` with the arena of the surrounding [`arena_scope`].
+///
+/// Nothing borrowed from the arena can be returned from `f`,
+/// so no handle is held across an `.await` or outlives the scope.
+///
+/// # Panics
+///
+/// Panics outside of an arena scope.
+#[track_caller]
+pub fn current_arena<R>(f: impl FnOnce(&Arena) -> R) -> R {
+    match try_current_arena(f) {
+        Some(result) => result,
+        None => panic!("current_arena called outside of an arena scope"),
+    }
+}
+
+/// Run `f` with the arena of the surrounding [`arena_scope`],
+/// or return `None` outside of one.
+pub fn try_current_arena<R>(f: impl FnOnce(&Arena) -> R) -> Option<R> {
+    ARENA.try_with(|arena| f(&arena.0)).ok()
+}
+
+#[test]
+fn scopes_reuse_pooled_arenas() {
+    use std::{
+        pin::pin,
+        task::{Context, Waker},
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let pooled = || POOL.with(|pool| pool.borrow().len());
+
+    let sum = runtime.block_on(arena_scope(async {
+        let first = current_arena(|arena| arena.alloc_slice_copy(&[1, 2, 3]).iter().sum::<i32>());
+        tokio::task::yield_now().await;
+
+        first + current_arena(|arena| *arena.alloc(4))
+    }));
+    assert_eq!(sum, 10);
+    assert_eq!(pooled(), 1);
+    assert!(try_current_arena(|_| ()).is_none());
+
+    // A cancelled scope gives its arena back too
+    {
+        let scope = arena_scope(async {
+            current_arena(|arena| {
+                // The pooled arena kept its chunks
+                assert!(arena.stats().chunks > 0);
+                arena.alloc(1_u8).leak();
+            });
+            std::future::pending::<()>().await
+        });
+        let mut scope = pin!(scope);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(scope.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(pooled(), 0);
+    }
+    assert_eq!(pooled(), 1);
+
+    // Arenas with callbacks are not handed to other scopes
+    runtime.block_on(arena_scope(async {
+        current_arena(|arena| arena.on_drop(|| ()));
+    }));
+    assert_eq!(pooled(), 0);
+}
@@ -0,0 +1,118 @@
+//! Helpers for testing code that uses arenas.
+
+use std::{cell::Cell, rc::Rc};
+
+use crate::Arena;
+
+#[derive(Debug, Default)]
+struct Counts {
+    created: Cell<usize>,
+    dropped: Cell<usize>,
+}
+
+/// Counts the [`DropToken`]s it handed out, and how many of them dropped.
+#[derive(Debug, Default, Clone)]
+pub struct DropTracker(Rc<Counts>);
+
+impl DropTracker {
+    /// Create a tracker with no tokens.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make a new token counted by this tracker.
+    pub fn token(&self) -> DropToken {
+        self.0.created.set(self.0.created.get() + 1);
+
+        DropToken(self.0.clone())
+    }
+
+    /// How many tokens were made, clones included.
+    pub fn created(&self) -> usize {
+        self.0.created.get()
+    }
+
+    /// How many tokens dropped.
+    pub fn dropped(&self) -> usize {
+        self.0.dropped.get()
+    }
+
+    /// How many tokens are still alive.
+    pub fn alive(&self) -> usize {
+        self.created() - self.dropped()
+    }
+}
+
+/// A payload that tells its [`DropTracker`] when it drops.
+///
+/// Cloning a token counts as making a new one.
+#[derive(Debug)]
+pub struct DropToken(Rc<Counts>);
+
+impl Clone for DropToken {
+    fn clone(&self) -> Self {
+        DropTracker(self.0.clone()).token()
+    }
+}
+
+impl Drop for DropToken {
+    fn drop(&mut self) {
+        self.0.dropped.set(self.0.dropped.get() + 1);
+    }
+}
+
+/// How many chunks `arena` allocated while `f` ran.
+pub fn chunk_allocations_during(arena: &Arena, f: impl FnOnce()) -> usize {
+    let before = arena.state.chunk_allocations.get();
+    f();
+
+    arena.state.chunk_allocations.get() - before
+}
+
+/// Run `f`, panicking if `arena` allocated any chunk meanwhile.
+#[track_caller]
+pub fn assert_no_chunk_growth<R>(arena: &Arena, f: impl FnOnce() -> R) -> R {
+    let before = arena.state.chunk_allocations.get();
+    let result = f();
+
+    let grown = arena.state.chunk_allocations.get() - before;
+    assert!(grown == 0, "the arena allocated {grown} chunks");
+
+    result
+}
+
+#[test]
+fn counts_chunk_allocations() {
+    let arena = Arena::new();
+    let tracker = DropTracker::new();
+
+    // A new class comes with four chunks
+    let allocations = chunk_allocations_during(&arena, || drop(arena.alloc(tracker.token())));
+    assert_eq!(allocations, 4);
+    assert_eq!((tracker.created(), tracker.alive()), (1, 0));
+
+    let token = tracker.token();
+    let clone = token.clone();
+    assert_eq!((tracker.created(), tracker.alive()), (3, 2));
+    drop((token, clone));
+    assert_eq!(tracker.dropped(), 3);
+
+    let values = assert_no_chunk_growth(&arena, || {
+        (0..4).map(|_| arena.alloc([0_u8; 250])).collect::<Vec<_>>()
+    });
+    assert_eq!(
+        chunk_allocations_during(&arena, || drop(arena.alloc([0_u8; 250]))),
+        1
+    );
+    drop(values);
+
+    // Two more classes are needed
+    let error = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        assert_no_chunk_growth(&arena, || arena.alloc([0_u8; 1000]))
+    }))
+    .unwrap_err();
+    assert!(error
+        .downcast_ref::<String>()
+        .unwrap()
+        .contains("allocated 8 chunks"));
+}
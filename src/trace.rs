@@ -0,0 +1,269 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::{self, Display, Write},
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{chunk::Chunk, Arena};
+
+/// How many events an arena keeps, older ones are overwritten.
+pub const TRACE_CAPACITY: usize = 1 << 16;
+
+/// Logical clock shared by every arena, so their events can be ordered.
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceKind {
+    /// `bytes` were bumped out of the chunk
+    Alloc,
+    /// The chunk was allocated
+    ChunkAlloc,
+    /// The chunk was taken off the free list
+    ChunkReuse,
+    /// The chunk was given back to its list
+    ChunkFree,
+    /// Every allocation in the chunk was gone
+    Reset,
+}
+
+impl TraceKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Alloc => "alloc",
+            Self::ChunkAlloc => "chunk alloc",
+            Self::ChunkReuse => "chunk reuse",
+            Self::ChunkFree => "chunk free",
+            Self::Reset => "reset",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TraceEvent {
+    tick: u64,
+    kind: TraceKind,
+    class: u32,
+    chunk: u32,
+    bytes: usize,
+}
+
+/// The newest events of an arena, in a ring buffer.
+#[derive(Debug, Default)]
+pub(crate) struct TraceBuffer {
+    events: RefCell<Vec<TraceEvent>>,
+    /// Slot the next event goes in once the buffer is full
+    next: Cell<usize>,
+}
+
+impl TraceBuffer {
+    #[inline]
+    fn push(&self, kind: TraceKind, chunk: Chunk, bytes: usize) {
+        let event = TraceEvent {
+            tick: CLOCK.fetch_add(1, Ordering::Relaxed),
+            kind,
+            class: chunk.state.config.class_for_size(chunk.size) as u32,
+            chunk: chunk.index as u32,
+            bytes,
+        };
+
+        let mut events = self.events.borrow_mut();
+        if events.len() < TRACE_CAPACITY {
+            events.push(event);
+        } else {
+            let next = self.next.get();
+            events[next] = event;
+            self.next.set((next + 1) % TRACE_CAPACITY);
+        }
+    }
+
+    /// Log `bytes` bumped out of `chunk`.
+    #[inline]
+    pub fn alloc(&self, chunk: Chunk, bytes: usize) {
+        self.push(TraceKind::Alloc, chunk, bytes);
+    }
+
+    /// Log that `chunk` was allocated.
+    #[inline]
+    pub fn chunk_alloc(&self, chunk: Chunk) {
+        self.push(TraceKind::ChunkAlloc, chunk, chunk.size);
+    }
+
+    /// Log that `chunk` was taken off the free list.
+    #[inline]
+    pub fn chunk_reuse(&self, chunk: Chunk) {
+        self.push(TraceKind::ChunkReuse, chunk, 0);
+    }
+
+    /// Log that `chunk` was given back to its list.
+    #[inline]
+    pub fn chunk_free(&self, chunk: Chunk) {
+        self.push(TraceKind::ChunkFree, chunk, 0);
+    }
+
+    /// Log that every allocation in `chunk` is gone.
+    #[inline]
+    pub fn reset(&self, chunk: Chunk) {
+        self.push(TraceKind::Reset, chunk, 0);
+    }
+
+    /// Every event kept, oldest first.
+    fn ordered(&self) -> Vec<TraceEvent> {
+        let events = self.events.borrow();
+        let (newer, older) = events.split_at(self.next.get());
+
+        older.iter().chain(newer).copied().collect()
+    }
+}
+
+/// A string written as a quoted JSON string.
+struct JsonStr<'a>(&'a str);
+
+impl Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                // Every other control character needs a unicode escape
+                c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+impl Arena {
+    /// Write the newest allocation events as Chrome trace-event JSON,
+    /// for `chrome://tracing` or Perfetto.
+    ///
+    /// Each size class gets a track of its events and a counter of the bytes in use.
+    /// Timestamps count events rather than time, so the spacing is not to scale.
+    /// Only the last [`TRACE_CAPACITY`] events are kept, so bytes allocated
+    /// before them are not counted.
+    pub fn export_chrome_trace(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let events = self.state.trace.ordered();
+        let config = &self.state.config;
+        let label = self.label().unwrap_or("arena");
+
+        write!(w, "{{\"traceEvents\":[")?;
+        write!(
+            w,
+            "{{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":1,\"args\":{{\"name\":{}}}}}",
+            JsonStr(label)
+        )?;
+
+        let mut classes: Vec<u32> = events.iter().map(|event| event.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        for &class in &classes {
+            let size = config.class_size(class as usize);
+            write!(
+                w,
+                ",{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{class},\
+                 \"args\":{{\"name\":\"{size} byte chunks\"}}}}"
+            )?;
+        }
+
+        let mut chunk_bytes: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut class_bytes: HashMap<u32, usize> = HashMap::new();
+        for event in events {
+            let TraceEvent {
+                tick,
+                kind,
+                class,
+                chunk,
+                bytes,
+            } = event;
+            write!(
+                w,
+                ",{{\"name\":\"{}\",\"ph\":\"i\",\"s\":\"t\",\"ts\":{tick},\"pid\":1,\"tid\":{class},\
+                 \"args\":{{\"chunk\":{chunk},\"bytes\":{bytes}}}}}",
+                kind.name()
+            )?;
+
+            let in_use = class_bytes.entry(class).or_default();
+            match kind {
+                TraceKind::Alloc => {
+                    *chunk_bytes.entry((class, chunk)).or_default() += bytes;
+                    *in_use += bytes;
+                }
+                TraceKind::Reset => *in_use -= chunk_bytes.remove(&(class, chunk)).unwrap_or(0),
+                _ => continue,
+            }
+            write!(
+                w,
+                ",{{\"name\":\"bytes in use ({} byte chunks)\",\"ph\":\"C\",\"ts\":{tick},\"pid\":1,\
+                 \"args\":{{\"bytes\":{in_use}}}}}",
+                config.class_size(class as usize)
+            )?;
+        }
+
+        write!(w, "]}}")
+    }
+}
+
+#[test]
+fn chrome_traces_follow_chunks() {
+    let arena = Arena::builder().label("traced").build();
+    let values: Vec<_> = (0..20)
+        .map(|n| arena.alloc_slice_fill_copy(50, &n))
+        .collect();
+    drop(values);
+    arena.alloc_str("again");
+
+    let mut json = Vec::new();
+    arena.export_chrome_trace(&mut json).unwrap();
+    let trace: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let count = |name: &str| events.iter().filter(|event| event["name"] == name).count();
+
+    assert_eq!(events[0]["args"]["name"], "traced");
+    assert_eq!(count("alloc"), 21);
+    assert_eq!(count("chunk alloc"), 20);
+    // The chunks reserved up front come off the free list
+    assert_eq!(count("chunk reuse"), 4);
+    // Every chunk is reset, and all but the current one freed
+    assert_eq!(count("reset"), 21);
+    assert_eq!(count("chunk free"), 19);
+
+    // Counters go back down once the chunks are reset
+    let mut counter = events
+        .iter()
+        .filter(|event| event["ph"] == "C" && event["name"] == "bytes in use (256 byte chunks)")
+        .map(|event| event["args"]["bytes"].as_u64().unwrap());
+    assert_eq!(counter.clone().max(), Some(20 * 200));
+    assert_eq!(counter.next_back(), Some(0));
+
+    // The buffer stays bounded
+    for _ in 0..TRACE_CAPACITY {
+        arena.alloc(0_u8);
+    }
+    assert_eq!(arena.state.trace.ordered().len(), TRACE_CAPACITY);
+    let ticks: Vec<_> = arena
+        .state
+        .trace
+        .ordered()
+        .iter()
+        .map(|event| event.tick)
+        .collect();
+    assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn chrome_trace_labels_are_escaped() {
+    let label = "\"quoted\" \\ tab\t bell\u{7} délè \u{1f980}";
+    let arena = Arena::builder().label(label).build();
+    arena.alloc(0_u8);
+
+    let mut json = Vec::new();
+    arena.export_chrome_trace(&mut json).unwrap();
+    let trace: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(trace["traceEvents"][0]["args"]["name"], label);
+}
@@ -0,0 +1,160 @@
+use std::{
+    alloc::Layout,
+    cell::Cell,
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+
+use crate::{
+    builder::Config,
+    chunk::ChunkList,
+    state::{State, StateRef},
+    MIN_BLOCK_SIZE,
+};
+
+/// How many values each chunk of a [`TypedArena`] should hold at least.
+const VALUES_PER_CHUNK: usize = 64;
+
+/// An arena holding values of a single type.
+///
+/// Values are handed out as plain references without reference counting,
+/// and they are all dropped when the arena drops.
+#[derive(Debug)]
+pub struct TypedArena<T> {
+    layout: Layout,
+    /// Chunks holding the values
+    list: Box<ChunkList>,
+    /// How many values were allocated
+    len: Cell<usize>,
+    /// State the chunks point at, it has to outlive them
+    _state: Box<State>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for TypedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TypedArena<T> {
+    /// Create a new empty typed arena.
+    pub fn new() -> Self {
+        let layout = Layout::new::<T>();
+        // Chunks of values too big for that many hold as many as fit
+        let size = size_of::<T>()
+            .checked_mul(VALUES_PER_CHUNK)
+            .and_then(usize::checked_next_power_of_two)
+            .or_else(|| size_of::<T>().checked_next_power_of_two())
+            .unwrap_or_else(|| {
+                panic!(
+                    "values of {} bytes are too big for a typed arena",
+                    size_of::<T>()
+                )
+            })
+            .max(align_of::<T>())
+            .max(MIN_BLOCK_SIZE);
+
+        let state = Box::new(State::new(Config::default()));
+        let list = ChunkList::empty(size, StateRef::new(&state));
+
+        Self {
+            layout,
+            list,
+            len: Cell::new(0),
+            _state: state,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocate a value, living as long as the arena.
+    #[track_caller]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let ptr = self.list.allocate(self.layout).cast::<T>();
+        self.len.set(self.len.get() + 1);
+
+        unsafe {
+            ptr.write(value);
+            &mut *ptr.as_raw()
+        }
+    }
+
+    /// How many values were allocated.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Checks if no values were allocated.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for TypedArena<T> {
+    fn drop(&mut self) {
+        if !std::mem::needs_drop::<T>() {
+            return;
+        }
+
+        // Zero sized values all share one address
+        if size_of::<T>() == 0 {
+            for _ in 0..self.len() {
+                unsafe { NonNull::<T>::dangling().as_ptr().drop_in_place() };
+            }
+            return;
+        }
+
+        // Values are packed downwards from the end of each chunk
+        for chunk in self.list.chunks() {
+            let end = unsafe { chunk.start.as_ptr().add(chunk.size) };
+            let used = end as usize - chunk.bump.get().as_ptr() as usize;
+            let values = chunk.bump.get().as_ptr().cast::<T>();
+
+            unsafe {
+                std::ptr::slice_from_raw_parts_mut(values, used / size_of::<T>()).drop_in_place();
+            }
+        }
+    }
+}
+
+#[test]
+fn typed_nodes() {
+    use std::rc::Rc;
+
+    struct Node {
+        value: usize,
+        _counter: Rc<()>,
+    }
+
+    let counter = Rc::new(());
+    let arena = TypedArena::new();
+    let nodes: Vec<&mut Node> = (0..10000)
+        .map(|value| {
+            arena.alloc(Node {
+                value,
+                _counter: counter.clone(),
+            })
+        })
+        .collect();
+
+    assert_eq!(arena.len(), 10000);
+    assert!(nodes.iter().enumerate().all(|(i, node)| node.value == i));
+    assert_eq!(Rc::strong_count(&counter), 10001);
+
+    drop(nodes);
+    drop(arena);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn huge_typed_values() {
+    // Sixty four of them would overflow the chunk size
+    let arena = TypedArena::<[u8; 1 << 60]>::new();
+    assert_eq!(arena.list.chunk_size(), 1 << 60);
+    assert!(arena.is_empty());
+}
@@ -0,0 +1,242 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    fmt::Display,
+    panic::Location,
+};
+
+use crate::{Arena, ArenaStats};
+
+type WarningHook = Box<dyn FnMut(&ArenaWarning)>;
+
+/// Something unusual an arena noticed, delivered to [`Arena::on_warning`] callbacks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArenaWarning {
+    /// A single allocation was above [`ArenaBuilder::warn_large_alloc`](crate::ArenaBuilder::warn_large_alloc).
+    LargeAlloc {
+        layout: Layout,
+        location: &'static Location<'static>,
+        stats: ArenaStats,
+    },
+    /// The reserved bytes crossed one of the
+    /// [`ArenaBuilder::warn_at_watermarks`](crate::ArenaBuilder::warn_at_watermarks).
+    Watermark {
+        fraction: f32,
+        limit: usize,
+        stats: ArenaStats,
+    },
+}
+
+impl Display for ArenaWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArenaWarning::LargeAlloc {
+                layout,
+                location,
+                stats,
+            } => write!(
+                f,
+                "large allocation of {} bytes (align {}) at {location}; {stats}",
+                layout.size(),
+                layout.align()
+            ),
+            ArenaWarning::Watermark {
+                fraction,
+                limit,
+                stats,
+            } => write!(
+                f,
+                "reserved bytes crossed {:.0}% of {limit}; {stats}",
+                fraction * 100.0
+            ),
+        }
+    }
+}
+
+/// Thresholds set by the builder.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct WarningConfig {
+    pub(crate) large_alloc: Option<usize>,
+    pub(crate) soft_limit: Option<usize>,
+    /// Fractions of the soft limit, sorted
+    pub(crate) watermarks: Vec<f32>,
+}
+
+/// Warning thresholds and the callbacks that hear about them.
+#[derive(Default)]
+pub(crate) struct Warnings {
+    config: WarningConfig,
+    /// How many watermarks are crossed right now
+    crossed: Cell<usize>,
+    /// Chunk allocations and frees when the watermarks were last checked,
+    /// reserved bytes only change along with them
+    checked_at: Cell<Option<(usize, u64)>>,
+    hooks: RefCell<Vec<WarningHook>>,
+}
+
+impl std::fmt::Debug for Warnings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Warnings")
+            .field("config", &self.config)
+            .field("crossed", &self.crossed.get())
+            .field("hooks", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl Warnings {
+    pub fn new(config: WarningConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Checks if no callback was registered.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        // Borrowed while a callback runs, so there is one
+        self.hooks.try_borrow().is_ok_and(|hooks| hooks.is_empty())
+    }
+
+    /// Tell every callback, unless one of them is what caused the warning.
+    fn emit(&self, warning: ArenaWarning) {
+        if let Ok(mut hooks) = self.hooks.try_borrow_mut() {
+            for hook in hooks.iter_mut() {
+                hook(&warning);
+            }
+        }
+    }
+}
+
+impl Arena {
+    /// Run `f` whenever the arena warns about something unusual,
+    /// as configured with [`ArenaBuilder::warn_large_alloc`](crate::ArenaBuilder::warn_large_alloc)
+    /// and [`ArenaBuilder::warn_at_watermarks`](crate::ArenaBuilder::warn_at_watermarks).
+    ///
+    /// Allocations made by the callback itself do not warn.
+    pub fn on_warning(&self, f: impl FnMut(&ArenaWarning) + 'static) {
+        self.state.warnings.hooks.borrow_mut().push(Box::new(f));
+    }
+
+    /// Check a finished allocation against the warning thresholds.
+    #[track_caller]
+    pub(crate) fn check_warnings(&self, layout: Layout) {
+        let warnings = &self.state.warnings;
+        // Callbacks allocating while they run are not checked either
+        if warnings
+            .hooks
+            .try_borrow()
+            .map_or(true, |hooks| hooks.is_empty())
+        {
+            return;
+        }
+
+        let config = &warnings.config;
+        if config
+            .large_alloc
+            .is_some_and(|bytes| layout.size() > bytes)
+        {
+            warnings.emit(ArenaWarning::LargeAlloc {
+                layout,
+                location: Location::caller(),
+                stats: self.stats(),
+            });
+        }
+
+        let Some(limit) = config.soft_limit.filter(|_| !config.watermarks.is_empty()) else {
+            return;
+        };
+        let chunks_changed = (
+            self.state.chunk_allocations.get(),
+            self.state.chunk_generation.get(),
+        );
+        if warnings.checked_at.replace(Some(chunks_changed)) == Some(chunks_changed) {
+            return;
+        }
+        let stats = self.stats();
+        let reached =
+            |fraction: &f32| stats.reserved_bytes as f64 >= *fraction as f64 * limit as f64;
+        let crossed = config.watermarks.iter().take_while(|f| reached(f)).count();
+
+        // Each watermark fires once on the way up, and again only after dropping back below it
+        for &fraction in &config.watermarks[warnings.crossed.get().min(crossed)..crossed] {
+            warnings.emit(ArenaWarning::Watermark {
+                fraction,
+                limit,
+                stats,
+            });
+        }
+        warnings.crossed.set(crossed);
+    }
+}
+
+#[test]
+fn large_allocs_and_watermarks() {
+    use std::rc::Rc;
+
+    let arena = Arena::builder()
+        .warn_large_alloc(4096)
+        .soft_limit(200_000)
+        .warn_at_watermarks(&[0.8, 0.5])
+        .build();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let sink = seen.clone();
+    arena.on_warning(move |warning| sink.borrow_mut().push(warning.clone()));
+
+    let small = arena.alloc_slice_fill_copy(100, &0_u8);
+    assert!(seen.borrow().is_empty());
+
+    let line = line!() + 1;
+    let large = arena.alloc_slice_fill_copy(8192, &0_u8);
+    match &seen.borrow()[0] {
+        ArenaWarning::LargeAlloc {
+            layout, location, ..
+        } => {
+            assert_eq!(layout.size(), 8192);
+            assert_eq!((location.file(), location.line()), (file!(), line));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    // Creating the 8 KiB class reserves the smaller ones too, crossing 50% once
+    let fractions = |seen: &[ArenaWarning]| -> Vec<f32> {
+        seen.iter()
+            .filter_map(|warning| match warning {
+                ArenaWarning::Watermark { fraction, .. } => Some(*fraction),
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(fractions(&seen.borrow()), [0.5]);
+    let more = arena.alloc_slice_fill_copy(8192, &0_u8);
+    assert_eq!(fractions(&seen.borrow()), [0.5]);
+
+    // A bigger class crosses the next one
+    let huge = arena.alloc_slice_fill_copy(2000, &0_u64);
+    assert_eq!(fractions(&seen.borrow()), [0.5, 0.8]);
+    assert!(seen.borrow().last().unwrap().to_string().contains("80%"));
+    drop((small, large, more, huge));
+}
+
+#[test]
+fn callbacks_can_allocate() {
+    let arena = Arena::builder()
+        .warn_large_alloc(1024)
+        .soft_limit(100_000)
+        .warn_at_watermarks(&[0.1])
+        .build()
+        .into_leaked();
+    let seen = std::rc::Rc::new(Cell::new(0));
+    let sink = seen.clone();
+    arena.on_warning(move |_| {
+        sink.set(sink.get() + 1);
+        arena.alloc_slice_fill_copy(4096, &0_u8).leak();
+    });
+
+    arena.alloc_slice_fill_copy(4096, &0_u8).leak();
+    assert_eq!(seen.get(), 2);
+
+    drop(unsafe { Arena::from_leaked(arena) });
+}
@@ -0,0 +1,126 @@
+//! Tests for `#[derive(DeserializeIn)]`, run with the `serde` feature.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use battle_arena::{
+    de::{DeserializeIn, InArena},
+    ptr::Boxed,
+    Arena,
+};
+use serde::de::DeserializeSeed;
+
+#[derive(DeserializeIn)]
+struct Document<'arena> {
+    title: Boxed<'arena, str>,
+    #[arena(rename = "tag-list")]
+    tags: Boxed<'arena, [Boxed<'arena, str>]>,
+    pages: Boxed<'arena, [Page<'arena>]>,
+    cover: Option<Boxed<'arena, Shape<'arena>>>,
+    version: u32,
+}
+
+#[derive(DeserializeIn)]
+struct Page<'arena>(u32, Boxed<'arena, str>);
+
+#[derive(DeserializeIn, Debug, PartialEq)]
+struct Meters(f64);
+
+#[derive(DeserializeIn, Debug, PartialEq)]
+struct Marker;
+
+#[derive(DeserializeIn)]
+enum Shape<'arena> {
+    Empty,
+    Circle(Meters),
+    Line(Meters, Meters),
+    #[arena(rename = "text")]
+    Label {
+        content: Boxed<'arena, str>,
+    },
+}
+
+#[derive(DeserializeIn)]
+struct Pair<T> {
+    left: T,
+    right: T,
+}
+
+#[test]
+fn structs_and_enums() {
+    let arena = Arena::new();
+    let json = r#"{
+        "title": "Guide",
+        "tag-list": ["arena", "serde"],
+        "pages": [[1, "intro"], [2, "usage"]],
+        "cover": {"text": {"content": "hello"}},
+        "version": 3,
+        "unknown": [true]
+    }"#;
+
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let document = InArena::<Document>::new(&arena)
+        .deserialize(&mut deserializer)
+        .unwrap();
+
+    assert_eq!(&*document.title, "Guide");
+    assert_eq!(document.tags.len(), 2);
+    assert_eq!(&*document.tags[1], "serde");
+    assert_eq!((document.pages[1].0, &*document.pages[1].1), (2, "usage"));
+    assert!(matches!(
+        document.cover.as_deref(),
+        Some(Shape::Label { content }) if &**content == "hello"
+    ));
+    assert_eq!(document.version, 3);
+}
+
+#[test]
+fn variants_and_generics() {
+    let arena = Arena::new();
+    let shapes = |json: &str| {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        Boxed::<[Shape]>::deserialize_in(&mut deserializer, &arena).unwrap()
+    };
+
+    let parsed = shapes(r#"["Empty", {"Circle": 2.5}, {"Line": [1.0, 2.0]}]"#);
+    assert!(matches!(parsed[0], Shape::Empty));
+    assert!(matches!(parsed[1], Shape::Circle(Meters(radius)) if radius == 2.5));
+    assert!(matches!(parsed[2], Shape::Line(Meters(a), Meters(b)) if (a, b) == (1.0, 2.0)));
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"left": 1, "right": 2}"#);
+    let pair = Pair::<u8>::deserialize_in(&mut deserializer, &arena).unwrap();
+    assert_eq!((pair.left, pair.right), (1, 2));
+
+    let mut deserializer = serde_json::Deserializer::from_str("null");
+    assert_eq!(
+        Marker::deserialize_in(&mut deserializer, &arena).unwrap(),
+        Marker
+    );
+}
+
+#[test]
+fn errors_are_reported() {
+    let arena = Arena::new();
+    let error = |json: &str| {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        match Document::deserialize_in(&mut deserializer, &arena) {
+            Ok(_) => panic!("{json} should not deserialize"),
+            Err(error) => error.to_string(),
+        }
+    };
+
+    assert!(error(r#"{"title": "x"}"#).contains("missing field `tag-list`"));
+    assert!(error(r#"{"title": "x", "title": "y"}"#).contains("duplicate field `title`"));
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#""Square""#);
+    let message = Shape::deserialize_in(&mut deserializer, &arena)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(message.contains("unknown variant `Square`"), "{message}");
+}
+
+#[test]
+fn derive_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,72 @@
+//! Tests for reporting allocations to a profiler, run with the `profiling` feature.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{collections::HashMap, sync::Mutex};
+
+use battle_arena::{set_alloc_profiler, AllocProfiler, Arena};
+
+/// Live allocations by address, and how many events were seen.
+#[derive(Default)]
+struct Events {
+    live: HashMap<usize, usize>,
+    allocs: usize,
+    frees: usize,
+}
+
+struct Recording(Mutex<Option<Events>>);
+
+impl Recording {
+    fn with<R>(&self, f: impl FnOnce(&mut Events) -> R) -> R {
+        f(self.0.lock().unwrap().get_or_insert_with(Events::default))
+    }
+}
+
+impl AllocProfiler for Recording {
+    fn alloc(&self, ptr: *const u8, size: usize) {
+        self.with(|events| {
+            let previous = events.live.insert(ptr as usize, size);
+            assert!(previous.is_none(), "{ptr:p} allocated twice");
+            events.allocs += 1;
+        });
+    }
+
+    fn free(&self, ptr: *const u8, size: usize) {
+        self.with(|events| {
+            assert_eq!(events.live.remove(&(ptr as usize)), Some(size));
+            events.frees += 1;
+        });
+    }
+}
+
+static RECORDING: Recording = Recording(Mutex::new(None));
+
+#[test]
+fn allocations_and_frees_are_reported() {
+    assert!(set_alloc_profiler(&RECORDING));
+    assert!(!set_alloc_profiler(&RECORDING));
+    let counts = || RECORDING.with(|events| (events.allocs, events.frees, events.live.len()));
+
+    let arena = Arena::new();
+    let first = arena.alloc(1_u64);
+    let second = arena.alloc_slice_copy(&[1_u32, 2, 3]);
+    assert_eq!(counts(), (2, 0, 2));
+    RECORDING.with(|events| {
+        assert_eq!(events.live[&(first.as_raw() as usize)], 8);
+        assert_eq!(events.live[&(second.as_raw() as *const u8 as usize)], 12);
+    });
+
+    // The newest value hands its memory back, which is freed once reused
+    drop(second);
+    let third = arena.alloc(3_u32);
+    assert_eq!(counts(), (3, 1, 2));
+
+    // Resetting the chunk frees the rest
+    drop((first, third));
+    assert_eq!(counts(), (3, 3, 0));
+
+    // So does dropping the arena
+    let _leaked = arena.alloc([0_u8; 100]).leak();
+    drop(arena);
+    assert_eq!(counts(), (4, 4, 0));
+}
@@ -0,0 +1,9 @@
+use battle_arena::{de::DeserializeIn, ptr::Boxed};
+
+#[derive(DeserializeIn)]
+struct Borrowed<'arena, 'other> {
+    text: Boxed<'arena, str>,
+    other: &'other str,
+}
+
+fn main() {}
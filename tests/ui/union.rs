@@ -0,0 +1,9 @@
+use battle_arena::de::DeserializeIn;
+
+#[derive(DeserializeIn)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
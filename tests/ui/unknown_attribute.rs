@@ -0,0 +1,9 @@
+use battle_arena::de::DeserializeIn;
+
+#[derive(DeserializeIn)]
+struct Settings {
+    #[arena(default)]
+    level: u32,
+}
+
+fn main() {}
@@ -0,0 +1,47 @@
+//! Smoke tests for `wasm32-unknown-unknown`.
+//!
+//! Run with `wasm-pack test --node`, or through
+//! `cargo test --target wasm32-unknown-unknown` with `wasm-bindgen-test-runner`
+//! set as the target runner.
+
+#![cfg(target_arch = "wasm32")]
+
+use battle_arena::Arena;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn values_slices_strings() {
+    let arena = Arena::new();
+
+    let value = arena.alloc(42_u64);
+    let slice = arena.alloc_slice_fill_copy(100, &7_u32);
+    let text = arena.alloc_str("hello wasm");
+
+    assert_eq!(*value, 42);
+    assert!(slice.iter().all(|&v| v == 7));
+    assert_eq!(&*text, "hello wasm");
+}
+
+#[wasm_bindgen_test]
+fn big_classes() {
+    let arena = Arena::new();
+
+    // Classes beyond the 64 KiB page size used to abort
+    for size in [64 << 10, 1 << 20, 4 << 20] {
+        let slice = arena.alloc_slice_fill_copy(size, &1_u8);
+        assert_eq!(slice.len(), size);
+        assert!(slice.iter().all(|&b| b == 1));
+    }
+}
+
+#[wasm_bindgen_test]
+fn drop_and_reuse() {
+    let arena = Arena::new();
+
+    let first = arena.alloc_slice_fill_copy(1 << 20, &0_u8);
+    let address = first.as_raw().cast::<u8>();
+    drop(first);
+
+    let second = arena.alloc_slice_fill_copy(1 << 20, &0_u8);
+    assert_eq!(second.as_raw().cast::<u8>(), address);
+}